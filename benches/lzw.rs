@@ -0,0 +1,47 @@
+// Save/load throughput of LZW compressed filters
+//
+// cargo bench --bench lzw
+
+use bloom_filter_yss::BloomFilterBuilder;
+use std::fs;
+use std::time::Instant;
+
+const ROUNDS: u32 = 5;
+
+fn main() {
+    fs::create_dir_all("tmp").unwrap();
+    let path = "tmp/bench_lzw.bin";
+
+    for capacity in [100_000, 1_000_000, 4_000_000] {
+        let mut bloom_filter = BloomFilterBuilder::new(capacity).build();
+        for i in 0..capacity / 2 {
+            bloom_filter.insert(&i.to_string());
+        }
+        let bytes = bloom_filter.bit_array.byte_array.len();
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            bloom_filter.to_file(path);
+        }
+        let save = start.elapsed() / ROUNDS;
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            BloomFilterBuilder::load(path).unwrap();
+        }
+        let load = start.elapsed() / ROUNDS;
+
+        let mib = bytes as f64 / (1 << 20) as f64;
+        println!(
+            "capacity {:>9}: {:>8.2} MiB, save {:>10.2?} ({:>7.1} MiB/s), load {:>10.2?} ({:>7.1} MiB/s)",
+            capacity,
+            mib,
+            save,
+            mib / save.as_secs_f64(),
+            load,
+            mib / load.as_secs_f64(),
+        );
+    }
+
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,139 @@
+use crate::params;
+use crate::rng::SplitMix64;
+use crate::BloomFilterBuilder;
+
+// z-score of the two-sided 95% confidence interval
+const Z_95: f64 = 1.959_963_984_540_054;
+
+// Inserts random keys into a fresh filter, then probes keys that were never
+// inserted and counts how many are reported as present.
+#[derive(Debug, Clone)]
+pub struct FalsePositiveTest {
+    pub capacity: usize,
+    pub false_positive_rate: f64,
+    pub inserted: usize,
+    pub probes: usize,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct FalsePositiveReport {
+    pub size: usize,
+    pub hash_count: usize,
+    pub inserted: usize,
+    pub probes: usize,
+    pub false_positives: usize,
+    pub measured: f64,
+    // Wilson score interval at 95% confidence
+    pub confidence_interval: (f64, f64),
+    // (1 - e^(-kn/m))^k for the filter that was actually built
+    pub theoretical: f64,
+    pub target: f64,
+}
+
+impl FalsePositiveTest {
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        Self {
+            capacity,
+            false_positive_rate,
+            inserted: capacity,
+            probes: 100_000,
+            seed: 0,
+        }
+    }
+
+    pub fn run(&self) -> FalsePositiveReport {
+        let mut bloom_filter = BloomFilterBuilder::new(self.capacity)
+            .false_positive_rate(self.false_positive_rate)
+            .build();
+        let mut rng = SplitMix64::new(self.seed);
+
+        // Prefixes keep both key sets disjoint
+        for _ in 0..self.inserted {
+            bloom_filter.insert(&format!("in:{:016x}", rng.next_u64()));
+        }
+        let false_positives = (0..self.probes)
+            .filter(|_| bloom_filter.lookup(&format!("out:{:016x}", rng.next_u64())))
+            .count();
+
+        let size = bloom_filter.bit_array.size;
+        let hash_count = bloom_filter.hash_count;
+        FalsePositiveReport {
+            size,
+            hash_count,
+            inserted: self.inserted,
+            probes: self.probes,
+            false_positives,
+            measured: ratio(false_positives, self.probes),
+            confidence_interval: wilson_interval(false_positives, self.probes),
+            theoretical: params::false_positive_rate_of(self.inserted, size, hash_count),
+            target: self.false_positive_rate,
+        }
+    }
+}
+
+fn ratio(successes: usize, trials: usize) -> f64 {
+    if trials == 0 {
+        0.0
+    } else {
+        successes as f64 / trials as f64
+    }
+}
+
+fn wilson_interval(successes: usize, trials: usize) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+    let n = trials as f64;
+    let p = ratio(successes, trials);
+    let z2 = Z_95 * Z_95;
+
+    let denominator = 1.0 + z2 / n;
+    let center = (p + z2 / (2.0 * n)) / denominator;
+    let half_width = Z_95 / denominator * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    (
+        (center - half_width).max(0.0),
+        (center + half_width).min(1.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wilson_interval() {
+        let (low, high) = wilson_interval(10, 1000);
+        assert!((low - 0.005_441).abs() < 1e-6);
+        assert!((high - 0.018_309).abs() < 1e-6);
+        assert_eq!(wilson_interval(0, 0), (0.0, 1.0));
+        assert!(wilson_interval(0, 100).0 < 1e-12);
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let report = FalsePositiveTest {
+            probes: 50_000,
+            ..FalsePositiveTest::new(5_000, 0.01)
+        }
+        .run();
+        let (low, high) = report.confidence_interval;
+
+        assert_eq!(report.inserted, 5_000);
+        assert_eq!(report.probes, 50_000);
+        assert!(low <= report.measured && report.measured <= high);
+        assert!(low <= report.theoretical && report.theoretical <= high);
+        // k is rounded up, which lands slightly off the target
+        assert!((report.theoretical - report.target).abs() < report.target * 0.05);
+    }
+
+    #[test]
+    fn test_deterministic_seed() {
+        let test = FalsePositiveTest {
+            probes: 10_000,
+            seed: 42,
+            ..FalsePositiveTest::new(1_000, 0.05)
+        };
+        assert_eq!(test.run().false_positives, test.run().false_positives);
+    }
+}
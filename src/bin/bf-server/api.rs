@@ -0,0 +1,283 @@
+// JSON endpoints over named filters
+//
+// GET    /filters                list filter names
+// PUT    /filters/{name}         create, body {"capacity", "error_rate", "compression"}
+// GET    /filters/{name}         statistics
+// DELETE /filters/{name}         remove
+// POST   /filters/{name}/keys    add, body {"key"} or {"keys": [...]}
+// POST   /filters/{name}/check   lookup, body {"key"} or {"keys": [...]}
+// GET    /filters/{name}/bytes   filter in the crate file format
+// POST   /save                   write the snapshot
+
+use crate::http::{Request, Response};
+use crate::store::{NamedFilter, Store, DEFAULT_CAPACITY, DEFAULT_ERROR_RATE};
+use bloom_filter_yss::CompressMode;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+pub fn route(store: &Mutex<Store>, request: &Request) -> Response {
+    let mut store = store.lock().unwrap();
+    let segments: Vec<&str> = request.segments.iter().map(String::as_str).collect();
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["filters"]) => {
+            Response::json(200, json!({ "filters": store.names().collect::<Vec<_>>() }))
+        }
+        ("PUT", ["filters", name]) => create(&mut store, name, &request.body),
+        ("GET", ["filters", name]) => match store.get(name) {
+            Some(filter) => Response::json(200, stats(name, filter)),
+            None => not_found(name),
+        },
+        ("DELETE", ["filters", name]) => match store.remove(name) {
+            true => Response::json(200, json!({ "deleted": name })),
+            false => not_found(name),
+        },
+        ("POST", ["filters", name, "keys"]) => match store.get_mut(name) {
+            Some(filter) => with_keys(&request.body, "added", |key| filter.insert(key.as_bytes())),
+            None => not_found(name),
+        },
+        ("POST", ["filters", name, "check"]) => match store.get(name) {
+            Some(filter) => with_keys(&request.body, "present", |key| {
+                filter.bloom_filter.lookup(key)
+            }),
+            None => not_found(name),
+        },
+        ("GET", ["filters", name, "bytes"]) => match store.get(name) {
+            Some(filter) => Response::bytes(filter.bloom_filter.to_bytes()),
+            None => not_found(name),
+        },
+        ("POST", ["save"]) => match store.save() {
+            Ok(()) => Response::json(200, json!({ "saved": true })),
+            Err(err) => Response::error(500, format!("{:#}", err)),
+        },
+        (
+            _,
+            ["filters"] | ["filters", _] | ["filters", _, "keys" | "check" | "bytes"] | ["save"],
+        ) => Response::error(405, "method not allowed"),
+        _ => Response::error(404, "no such endpoint"),
+    }
+}
+
+fn create(store: &mut Store, name: &str, body: &[u8]) -> Response {
+    let body = match parse_body(body) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let capacity = match body.get("capacity") {
+        None => DEFAULT_CAPACITY,
+        Some(value) => match value.as_u64() {
+            Some(capacity) => capacity as usize,
+            None => return Response::error(400, "capacity must be a positive integer"),
+        },
+    };
+    let error_rate = match body.get("error_rate") {
+        None => DEFAULT_ERROR_RATE,
+        Some(value) => match value.as_f64() {
+            Some(error_rate) => error_rate,
+            None => return Response::error(400, "error_rate must be a number"),
+        },
+    };
+    let compress_mode = match body.get("compression").map(Value::as_str) {
+        None | Some(Some("lzw")) => CompressMode::Lzw,
+        Some(Some("none")) => CompressMode::None,
+        Some(_) => return Response::error(400, "compression must be lzw or none"),
+    };
+
+    if store.get(name).is_some() {
+        return Response::error(409, format!("filter {} already exists", name));
+    }
+    if let Err(err) = store.reserve(name, capacity, error_rate) {
+        return Response::error(400, format!("{:#}", err));
+    }
+    let filter = store.get_mut(name).expect("filter was just reserved");
+    filter.bloom_filter.compress_mode = compress_mode;
+
+    Response::json(201, stats(name, filter))
+}
+
+fn stats(name: &str, filter: &NamedFilter) -> Value {
+    let params = filter.params();
+    let compression = match filter.bloom_filter.compress_mode {
+        CompressMode::None => "none",
+        CompressMode::Lzw => "lzw",
+    };
+    json!({
+        "name": name,
+        "capacity": filter.capacity,
+        "error_rate": filter.error_rate,
+        "bits": params.bits,
+        "bytes": params.memory_bytes(),
+        "hash_count": params.hash_count,
+        "items": filter.items,
+        "fill_ratio": filter.bloom_filter.fill_ratio(),
+        "estimated_count": filter.bloom_filter.estimated_count(),
+        "compression": compression,
+    })
+}
+
+// Applies f to {"key": k} or to each of {"keys": [...]}, answering with a
+// single value or an array under `field`
+fn with_keys<F>(body: &[u8], field: &str, mut f: F) -> Response
+where
+    F: FnMut(&str) -> bool,
+{
+    let body = match parse_body(body) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    match (body.get("key"), body.get("keys")) {
+        (Some(Value::String(key)), None) => Response::json(200, json!({ field: f(key) })),
+        (None, Some(Value::Array(keys))) => {
+            let Some(keys) = keys.iter().map(Value::as_str).collect::<Option<Vec<_>>>() else {
+                return Response::error(400, "keys must be strings");
+            };
+            let results: Vec<bool> = keys.into_iter().map(f).collect();
+            Response::json(200, json!({ field: results }))
+        }
+        _ => Response::error(400, "expected a string `key` or an array of `keys`"),
+    }
+}
+
+fn parse_body(body: &[u8]) -> Result<serde_json::Map<String, Value>, Response> {
+    if body.is_empty() {
+        return Ok(serde_json::Map::new());
+    }
+    match serde_json::from_slice(body) {
+        Ok(Value::Object(object)) => Ok(object),
+        Ok(_) => Err(Response::error(400, "body must be a JSON object")),
+        Err(err) => Err(Response::error(400, format!("invalid JSON: {}", err))),
+    }
+}
+
+fn not_found(name: &str) -> Response {
+    Response::error(404, format!("filter {} not found", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bloom_filter_yss::BloomFilter;
+
+    fn call(store: &Mutex<Store>, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let request = Request {
+            method: method.to_string(),
+            segments: path
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect(),
+            body: body.as_bytes().to_vec(),
+        };
+        let response = route(store, &request);
+        let value = serde_json::from_slice(&response.body).unwrap_or(Value::Null);
+        (response.status, value)
+    }
+
+    #[test]
+    fn test_create_and_stats() {
+        let store = Mutex::new(Store::open(None).unwrap());
+        let (status, value) = call(
+            &store,
+            "PUT",
+            "/filters/f",
+            r#"{"capacity": 1000, "error_rate": 0.001, "compression": "none"}"#,
+        );
+        assert_eq!(status, 201);
+        assert_eq!(value["bits"], 14378);
+        assert_eq!(value["hash_count"], 10);
+        assert_eq!(value["compression"], "none");
+
+        assert_eq!(call(&store, "PUT", "/filters/f", "").0, 409);
+        assert_eq!(
+            call(&store, "PUT", "/filters/g", r#"{"capacity": 0}"#).0,
+            400
+        );
+        assert_eq!(
+            call(&store, "PUT", "/filters/g", r#"{"error_rate": "x"}"#).0,
+            400
+        );
+        assert_eq!(call(&store, "PUT", "/filters/g", "[1]").0, 400);
+        assert_eq!(call(&store, "PUT", "/filters/g", "{").0, 400);
+
+        let (status, value) = call(&store, "PUT", "/filters/g", "");
+        assert_eq!(status, 201);
+        assert_eq!(value["capacity"], 100);
+        assert_eq!(value["compression"], "lzw");
+
+        let (_, value) = call(&store, "GET", "/filters", "");
+        assert_eq!(value, json!({ "filters": ["f", "g"] }));
+        assert_eq!(call(&store, "DELETE", "/filters/g", "").0, 200);
+        assert_eq!(call(&store, "GET", "/filters/g", "").0, 404);
+    }
+
+    #[test]
+    fn test_keys() {
+        let store = Mutex::new(Store::open(None).unwrap());
+        call(&store, "PUT", "/filters/f", "");
+
+        let (status, value) = call(&store, "POST", "/filters/f/keys", r#"{"key": "a"}"#);
+        assert_eq!(status, 200);
+        assert_eq!(value, json!({ "added": true }));
+        let (_, value) = call(&store, "POST", "/filters/f/keys", r#"{"keys": ["a", "b"]}"#);
+        assert_eq!(value, json!({ "added": [false, true] }));
+
+        let (_, value) = call(
+            &store,
+            "POST",
+            "/filters/f/check",
+            r#"{"keys": ["a", "c"]}"#,
+        );
+        assert_eq!(value, json!({ "present": [true, false] }));
+        let (_, value) = call(&store, "POST", "/filters/f/check", r#"{"key": "b"}"#);
+        assert_eq!(value, json!({ "present": true }));
+
+        let (_, value) = call(&store, "GET", "/filters/f", "");
+        assert_eq!(value["items"], 2);
+
+        assert_eq!(
+            call(&store, "POST", "/filters/f/keys", r#"{"keys": [1]}"#).0,
+            400
+        );
+        assert_eq!(
+            call(
+                &store,
+                "POST",
+                "/filters/f/keys",
+                r#"{"key": "a", "keys": []}"#
+            )
+            .0,
+            400
+        );
+        assert_eq!(
+            call(&store, "POST", "/filters/missing/check", r#"{"key": "a"}"#).0,
+            404
+        );
+    }
+
+    #[test]
+    fn test_bytes() {
+        let store = Mutex::new(Store::open(None).unwrap());
+        call(&store, "PUT", "/filters/f", "");
+        call(&store, "POST", "/filters/f/keys", r#"{"key": "a"}"#);
+
+        let request = Request {
+            method: "GET".to_string(),
+            segments: vec!["filters".to_string(), "f".to_string(), "bytes".to_string()],
+            body: Vec::new(),
+        };
+        let response = route(&store, &request);
+        assert_eq!(response.content_type, "application/octet-stream");
+        let bloom_filter = BloomFilter::from_bytes(&response.body).unwrap();
+        assert!(bloom_filter.lookup("a"));
+    }
+
+    #[test]
+    fn test_unknown_routes() {
+        let store = Mutex::new(Store::open(None).unwrap());
+        assert_eq!(call(&store, "GET", "/nope", "").0, 404);
+        assert_eq!(call(&store, "POST", "/filters", "").0, 405);
+        assert_eq!(call(&store, "GET", "/filters/f/keys", "").0, 405);
+        assert_eq!(call(&store, "POST", "/save", "").0, 200);
+    }
+}
@@ -0,0 +1,288 @@
+// Subset of RedisBloom commands over named filters
+// ref: https://redis.io/docs/latest/commands/?group=bf
+
+use crate::resp::Reply;
+use crate::store::Store;
+use std::sync::Mutex;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Action {
+    Continue,
+    Quit,
+    Shutdown,
+}
+
+pub fn execute(store: &Mutex<Store>, args: &[Vec<u8>]) -> (Reply, Action) {
+    let Some(name) = args.first() else {
+        return (Reply::error("empty command"), Action::Continue);
+    };
+    let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+    let args = &args[1..];
+    let arity_error = || Reply::error(format!("wrong number of arguments for '{}' command", name));
+
+    // Filter names are stored as strings, so refuse the ones that would not
+    // round-trip instead of mapping them onto another filter
+    let keys = match name.as_str() {
+        "del" => args,
+        _ if name.starts_with("bf.") => &args[..args.len().min(1)],
+        _ => &[],
+    };
+    if keys.iter().any(|key| std::str::from_utf8(key).is_err()) {
+        return (
+            Reply::error("invalid filter name, expected UTF-8"),
+            Action::Continue,
+        );
+    }
+    let mut store = store.lock().unwrap();
+
+    let reply = match (name.as_str(), args) {
+        ("ping", []) => Reply::Simple("PONG".to_string()),
+        ("ping", [message]) | ("echo", [message]) => Reply::Bulk(message.clone()),
+        ("quit", _) => return (Reply::ok(), Action::Quit),
+        // Sent by redis-cli on connect, no command docs to share
+        ("command", _) => Reply::Array(Vec::new()),
+        ("save", []) => match store.save() {
+            Ok(()) => Reply::ok(),
+            Err(err) => Reply::error(format!("{:#}", err)),
+        },
+        ("shutdown", [] | [_]) => {
+            let nosave = args
+                .first()
+                .is_some_and(|arg| arg.eq_ignore_ascii_case(b"nosave"));
+            if !nosave {
+                if let Err(err) = store.save() {
+                    return (Reply::error(format!("{:#}", err)), Action::Continue);
+                }
+            }
+            return (Reply::ok(), Action::Shutdown);
+        }
+        ("del", [_, ..]) => {
+            let removed = args
+                .iter()
+                .filter(|key| store.remove(filter_name(key)))
+                .count();
+            Reply::Integer(removed as i64)
+        }
+        ("bf.reserve", [key, error_rate, capacity, options @ ..]) => {
+            reserve(&mut store, key, error_rate, capacity, options)
+        }
+        ("bf.add", [key, item]) => {
+            let filter = store.get_or_create(filter_name(key));
+            Reply::bool(filter.insert(item))
+        }
+        ("bf.madd", [key, items @ ..]) if !items.is_empty() => {
+            let filter = store.get_or_create(filter_name(key));
+            Reply::Array(
+                items
+                    .iter()
+                    .map(|item| Reply::bool(filter.insert(item)))
+                    .collect(),
+            )
+        }
+        ("bf.exists", [key, item]) => {
+            let filter = store.get(filter_name(key));
+            Reply::bool(filter.is_some_and(|filter| filter.bloom_filter.lookup(item)))
+        }
+        ("bf.mexists", [key, items @ ..]) if !items.is_empty() => {
+            let filter = store.get(filter_name(key));
+            let exists = |item: &Vec<u8>| filter.is_some_and(|f| f.bloom_filter.lookup(item));
+            Reply::Array(items.iter().map(|item| Reply::bool(exists(item))).collect())
+        }
+        ("bf.info", [key] | [key, _]) => match store.get(filter_name(key)) {
+            None => Reply::error("not found"),
+            Some(filter) => {
+                let fields = [
+                    ("Capacity", Reply::Integer(filter.capacity as i64)),
+                    (
+                        "Size",
+                        Reply::Integer(filter.params().memory_bytes() as i64),
+                    ),
+                    ("Number of filters", Reply::Integer(1)),
+                    (
+                        "Number of items inserted",
+                        Reply::Integer(filter.items as i64),
+                    ),
+                    // Filters never scale
+                    ("Expansion rate", Reply::Nil),
+                ];
+                match args.get(1) {
+                    None => Reply::Array(
+                        fields
+                            .into_iter()
+                            .flat_map(|(name, value)| [Reply::Simple(name.to_string()), value])
+                            .collect(),
+                    ),
+                    Some(field) => info_field(fields, field),
+                }
+            }
+        },
+        (
+            "ping" | "echo" | "save" | "shutdown" | "del" | "bf.reserve" | "bf.add" | "bf.madd"
+            | "bf.exists" | "bf.mexists" | "bf.info",
+            _,
+        ) => arity_error(),
+        _ => Reply::error(format!("unknown command '{}'", name)),
+    };
+
+    (reply, Action::Continue)
+}
+
+fn reserve(
+    store: &mut Store,
+    key: &[u8],
+    error_rate: &[u8],
+    capacity: &[u8],
+    options: &[Vec<u8>],
+) -> Reply {
+    let Some(error_rate) = parse::<f64>(error_rate) else {
+        return Reply::error("bad error rate");
+    };
+    let Some(capacity) = parse::<usize>(capacity) else {
+        return Reply::error("bad capacity");
+    };
+    for option in options {
+        if !option.eq_ignore_ascii_case(b"nonscaling") {
+            let option = String::from_utf8_lossy(option);
+            return Reply::error(format!("unsupported option '{}'", option));
+        }
+    }
+
+    match store.reserve(filter_name(key), capacity, error_rate) {
+        Ok(()) => Reply::ok(),
+        Err(err) => Reply::error(format!("{:#}", err)),
+    }
+}
+
+fn info_field<const N: usize>(fields: [(&str, Reply); N], field: &[u8]) -> Reply {
+    let aliases = [
+        ("capacity", "Capacity"),
+        ("size", "Size"),
+        ("filters", "Number of filters"),
+        ("items", "Number of items inserted"),
+        ("expansion", "Expansion rate"),
+    ];
+    let field = String::from_utf8_lossy(field).to_ascii_lowercase();
+    let Some((_, name)) = aliases.iter().find(|(alias, _)| *alias == field) else {
+        return Reply::error("invalid information value");
+    };
+
+    fields
+        .into_iter()
+        .find(|(field_name, _)| field_name == name)
+        .map(|(_, value)| Reply::Array(vec![value]))
+        .unwrap_or(Reply::Nil)
+}
+
+// Only called on names checked by `execute`
+fn filter_name(key: &[u8]) -> &str {
+    std::str::from_utf8(key).unwrap_or_default()
+}
+
+fn parse<T: std::str::FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(store: &Mutex<Store>, command: &str) -> Reply {
+        let args: Vec<Vec<u8>> = command
+            .split_whitespace()
+            .map(|arg| arg.as_bytes().to_vec())
+            .collect();
+        execute(store, &args).0
+    }
+
+    fn integers(values: &[i64]) -> Reply {
+        Reply::Array(values.iter().map(|&value| Reply::Integer(value)).collect())
+    }
+
+    #[test]
+    fn test_add_exists() {
+        let store = Mutex::new(Store::open(None).unwrap());
+        assert_eq!(run(&store, "BF.EXISTS f a"), Reply::Integer(0));
+        assert_eq!(run(&store, "BF.ADD f a"), Reply::Integer(1));
+        assert_eq!(run(&store, "bf.add f a"), Reply::Integer(0));
+        assert_eq!(run(&store, "BF.EXISTS f a"), Reply::Integer(1));
+        assert_eq!(run(&store, "BF.MADD f a b c"), integers(&[0, 1, 1]));
+        assert_eq!(run(&store, "BF.MEXISTS f a b d"), integers(&[1, 1, 0]));
+        assert_eq!(run(&store, "BF.MEXISTS g a"), integers(&[0]));
+    }
+
+    #[test]
+    fn test_reserve_info() {
+        let store = Mutex::new(Store::open(None).unwrap());
+        assert_eq!(
+            run(&store, "BF.RESERVE f 0.001 1000 NONSCALING"),
+            Reply::ok()
+        );
+        assert_eq!(
+            run(&store, "BF.RESERVE f 0.001 1000"),
+            Reply::error("item exists")
+        );
+        assert_eq!(
+            run(&store, "BF.RESERVE g 0.01 10 EXPANSION 2"),
+            Reply::error("unsupported option 'EXPANSION'")
+        );
+        assert_eq!(
+            run(&store, "BF.RESERVE g x 10"),
+            Reply::error("bad error rate")
+        );
+        assert!(matches!(run(&store, "BF.RESERVE g 2 10"), Reply::Error(_)));
+
+        run(&store, "BF.MADD f a b");
+        assert_eq!(
+            run(&store, "BF.INFO f"),
+            Reply::Array(vec![
+                Reply::Simple("Capacity".to_string()),
+                Reply::Integer(1000),
+                Reply::Simple("Size".to_string()),
+                Reply::Integer(1798),
+                Reply::Simple("Number of filters".to_string()),
+                Reply::Integer(1),
+                Reply::Simple("Number of items inserted".to_string()),
+                Reply::Integer(2),
+                Reply::Simple("Expansion rate".to_string()),
+                Reply::Nil,
+            ])
+        );
+        assert_eq!(run(&store, "BF.INFO f items"), integers(&[2]));
+        assert!(matches!(run(&store, "BF.INFO f bogus"), Reply::Error(_)));
+        assert_eq!(run(&store, "BF.INFO missing"), Reply::error("not found"));
+    }
+
+    #[test]
+    fn test_misc_commands() {
+        let store = Mutex::new(Store::open(None).unwrap());
+        assert_eq!(run(&store, "PING"), Reply::Simple("PONG".to_string()));
+        assert_eq!(run(&store, "ECHO hi"), Reply::Bulk(b"hi".to_vec()));
+        assert_eq!(run(&store, "SAVE"), Reply::ok());
+        run(&store, "BF.ADD f a");
+        assert_eq!(run(&store, "DEL f g"), Reply::Integer(1));
+        assert_eq!(run(&store, "BF.EXISTS f a"), Reply::Integer(0));
+
+        assert_eq!(
+            run(&store, "BF.ADD f"),
+            Reply::error("wrong number of arguments for 'bf.add' command")
+        );
+        assert!(matches!(run(&store, "BF.MADD f"), Reply::Error(_)));
+        assert_eq!(
+            run(&store, "FLUSHALL"),
+            Reply::error("unknown command 'flushall'")
+        );
+
+        let args = [b"BF.ADD".to_vec(), b"f\xff".to_vec(), b"a".to_vec()];
+        assert_eq!(
+            execute(&store, &args).0,
+            Reply::error("invalid filter name, expected UTF-8")
+        );
+        let args = [b"DEL".to_vec(), b"f".to_vec(), b"\xfe".to_vec()];
+        assert!(matches!(execute(&store, &args).0, Reply::Error(_)));
+        assert!(store.lock().unwrap().get("f\u{fffd}").is_none());
+
+        let args = [b"SHUTDOWN".to_vec(), b"NOSAVE".to_vec()];
+        assert_eq!(execute(&store, &args), (Reply::ok(), Action::Shutdown));
+        assert_eq!(execute(&store, &[b"QUIT".to_vec()]).1, Action::Quit);
+    }
+}
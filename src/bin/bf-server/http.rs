@@ -0,0 +1,314 @@
+// Minimal HTTP/1.1: one request per connection, Content-Length bodies only
+
+use crate::api;
+use crate::store::Store;
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::io::{prelude::*, BufReader};
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Sanity limits for untrusted input, the body is only allocated as its
+// bytes arrive so a declared Content-Length alone costs nothing
+const MAX_HEADERS: usize = 100;
+const MAX_BODY_LEN: usize = 16 * 1024 * 1024;
+const MAX_LINE_LEN: usize = 8 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Request {
+    pub method: String,
+    // Percent-decoded path segments
+    pub segments: Vec<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(status: u16, value: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, serde_json::json!({ "error": message.into() }))
+    }
+
+    pub fn bytes(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/octet-stream",
+            body,
+        }
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len()
+        )?;
+        writer.write_all(&self.body)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+pub fn handle_connection(stream: TcpStream, store: &Mutex<Store>) -> Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let response = match read_request(&mut reader) {
+        Ok(request) => api::route(store, &request),
+        Err(err) => Response::error(400, format!("{:#}", err)),
+    };
+    response.write_to(&mut writer)
+}
+
+pub fn read_request(reader: &mut impl BufRead) -> Result<Request> {
+    let request_line = read_line(reader)?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        bail!("malformed request line");
+    };
+    if !version.starts_with("HTTP/1.") {
+        bail!("unsupported version {}", version);
+    }
+
+    let mut content_length = 0;
+    for header_count in 0.. {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if header_count == MAX_HEADERS {
+            bail!("too many headers");
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            bail!("malformed header");
+        };
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.trim().parse().context("invalid content length")?;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            bail!("transfer encoding is not supported");
+        }
+    }
+    if content_length > MAX_BODY_LEN {
+        bail!("body too large");
+    }
+
+    let mut body = Vec::new();
+    reader.take(content_length as u64).read_to_end(&mut body)?;
+    if body.len() != content_length {
+        bail!("unexpected end of stream");
+    }
+
+    // Query strings are not used by any endpoint
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(percent_decode)
+        .collect::<Result<_>>()?;
+
+    Ok(Request {
+        method: method.to_string(),
+        segments,
+        body,
+    })
+}
+
+// Line without its CRLF (or bare LF), at most MAX_LINE_LEN bytes
+fn read_line(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = Vec::new();
+    let limit = MAX_LINE_LEN as u64 + 2;
+    reader.take(limit).read_until(b'\n', &mut line)?;
+    if line.pop() != Some(b'\n') {
+        if line.len() + 1 == limit as usize {
+            bail!("line too long");
+        }
+        bail!("unexpected end of stream");
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line).context("invalid UTF-8 in request")
+}
+
+fn percent_decode(segment: &str) -> Result<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .context("invalid percent encoding")?;
+            let hex = std::str::from_utf8(hex)?;
+            decoded.push(u8::from_str_radix(hex, 16).context("invalid percent encoding")?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    Ok(String::from_utf8(decoded)?)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Server;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn request(addr: std::net::SocketAddr, raw: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(raw.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_read_request() {
+        let mut input: &[u8] = b"POST /filters/a%20b/keys?x=1 HTTP/1.1\r\n\
+            Host: localhost\r\n\
+            content-length: 4\r\n\
+            \r\n\
+            body";
+        let request = read_request(&mut input).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.segments, ["filters", "a b", "keys"]);
+        assert_eq!(request.body, b"body");
+    }
+
+    #[test]
+    fn test_read_invalid_request() {
+        let inputs: [&[u8]; 6] = [
+            b"",
+            b"GET /\r\n\r\n",
+            b"GET / HTTP/2\r\n\r\n",
+            b"GET / HTTP/1.1\r\nContent-Length: x\r\n\r\n",
+            b"GET / HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort",
+            b"GET /%zz HTTP/1.1\r\n\r\n",
+        ];
+        for mut input in inputs {
+            assert!(read_request(&mut input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_read_limits() {
+        // Declared lengths are not allocated up front
+        let mut input: &[u8] = b"POST / HTTP/1.1\r\nContent-Length: 16777216\r\n\r\nabc";
+        let err = read_request(&mut input).unwrap_err();
+        assert_eq!(err.to_string(), "unexpected end of stream");
+        let mut input: &[u8] = b"POST / HTTP/1.1\r\nContent-Length: 16777217\r\n\r\n";
+        let err = read_request(&mut input).unwrap_err();
+        assert_eq!(err.to_string(), "body too large");
+        let mut input: &[u8] = b"POST / HTTP/1.1\r\nContent-Length: 99999999999999999999\r\n\r\n";
+        assert!(read_request(&mut input).is_err());
+
+        // A header line of exactly MAX_LINE_LEN bytes is fine
+        let mut header = b"GET / HTTP/1.1\r\n".to_vec();
+        header.resize(header.len() + MAX_LINE_LEN, b'a');
+        header[16] = b'X';
+        header[17] = b':';
+        header.extend_from_slice(b"\r\n\r\n");
+        assert!(read_request(&mut header.as_slice()).is_ok());
+        let mut line = vec![b'a'; MAX_LINE_LEN + 2];
+        line.push(b'\n');
+        let err = read_request(&mut line.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "line too long");
+        let mut header = b"GET / HTTP/1.1\r\nX: ".to_vec();
+        header.resize(header.len() + 2 * MAX_LINE_LEN, b'a');
+        let err = read_request(&mut header.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "line too long");
+    }
+
+    #[test]
+    fn test_write_response() {
+        let mut output = Vec::new();
+        Response::error(404, "not found")
+            .write_to(&mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "HTTP/1.1 404 Not Found\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: 21\r\n\
+             Connection: close\r\n\
+             \r\n\
+             {\"error\":\"not found\"}"
+        );
+    }
+
+    #[test]
+    fn test_tcp_requests() {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let server = Server::bind("127.0.0.1:0", Arc::clone(&shutdown)).unwrap();
+        let addr = server.local_addr().unwrap();
+        let store = Mutex::new(Store::open(None).unwrap());
+        let handler = move |stream| handle_connection(stream, &store);
+        let handle = thread::spawn(move || server.run(handler, READ_TIMEOUT));
+
+        let response = request(addr, "PUT /filters/f HTTP/1.1\r\n\r\n");
+        assert!(
+            response.starts_with("HTTP/1.1 201 Created\r\n"),
+            "{}",
+            response
+        );
+        let body = r#"{"keys": ["a", "b"]}"#;
+        let response = request(
+            addr,
+            &format!(
+                "POST /filters/f/keys HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+        );
+        assert!(
+            response.ends_with(r#"{"added":[true,true]}"#),
+            "{}",
+            response
+        );
+
+        let response = request(addr, "garbage\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        shutdown.store(true, Ordering::SeqCst);
+        handle.join().unwrap().unwrap();
+    }
+}
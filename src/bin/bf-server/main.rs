@@ -0,0 +1,143 @@
+// Network service over named filters, speaking RESP2 (BF.* commands)
+// and/or HTTP with JSON bodies
+//
+// bf-server [--bind <addr>] [--http <addr>] [--snapshot <path> | --no-snapshot]
+//           [--save-interval <seconds>]
+
+mod api;
+mod commands;
+mod http;
+mod resp;
+mod server;
+mod store;
+
+use anyhow::{bail, Context, Result};
+use server::Server;
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use store::Store;
+
+const DEFAULT_BIND: &str = "127.0.0.1:6379";
+const DEFAULT_SNAPSHOT: &str = "dump.bfs";
+// Time given to in-flight HTTP requests on shutdown
+const HTTP_DRAIN: Duration = Duration::from_secs(5);
+const USAGE: &str = "Usage: bf-server [--bind <addr>] [--http <addr>] \
+    [--snapshot <path> | --no-snapshot] [--save-interval <seconds>]";
+
+struct Config {
+    resp: Option<String>,
+    http: Option<String>,
+    snapshot: Option<PathBuf>,
+    save_interval: Option<Duration>,
+}
+
+fn main() -> ExitCode {
+    match parse_args().and_then(run) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn parse_args() -> Result<Config> {
+    let mut config = Config {
+        resp: None,
+        http: None,
+        snapshot: Some(PathBuf::from(DEFAULT_SNAPSHOT)),
+        save_interval: None,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--bind" => config.resp = Some(value()?),
+            "--http" => config.http = Some(value()?),
+            "--snapshot" => config.snapshot = Some(PathBuf::from(value()?)),
+            "--no-snapshot" => config.snapshot = None,
+            "--save-interval" => {
+                let seconds = value()?;
+                let seconds: u64 = seconds
+                    .parse()
+                    .with_context(|| format!("invalid value for --save-interval: {}", seconds))?;
+                config.save_interval = Some(Duration::from_secs(seconds.max(1)));
+            }
+            "--help" | "-h" => bail!("{}", USAGE),
+            _ => bail!("unknown argument `{}`\n\n{}", arg, USAGE),
+        }
+    }
+    // RESP stays the default unless only HTTP is asked for
+    if config.resp.is_none() && config.http.is_none() {
+        config.resp = Some(DEFAULT_BIND.to_string());
+    }
+
+    Ok(config)
+}
+
+fn run(config: Config) -> Result<()> {
+    let store = Arc::new(Mutex::new(Store::open(config.snapshot)?));
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let mut servers = Vec::new();
+
+    if let Some(addr) = config.resp {
+        let server = Server::bind(&addr, Arc::clone(&shutdown))
+            .with_context(|| format!("failed to bind {}", addr))?;
+        eprintln!("RESP listening on {}", server.local_addr()?);
+        let store = Arc::clone(&store);
+        let flag = Arc::clone(&shutdown);
+        let handler = move |stream| resp::handle_connection(stream, &store, &flag);
+        servers.push(thread::spawn(move || server.run(handler, Duration::ZERO)));
+    }
+    if let Some(addr) = config.http {
+        let server = Server::bind(&addr, Arc::clone(&shutdown))
+            .with_context(|| format!("failed to bind {}", addr))?;
+        eprintln!("HTTP listening on {}", server.local_addr()?);
+        let store = Arc::clone(&store);
+        let handler = move |stream| http::handle_connection(stream, &store);
+        servers.push(thread::spawn(move || server.run(handler, HTTP_DRAIN)));
+    }
+    if let Some(interval) = config.save_interval {
+        let store = Arc::clone(&store);
+        let shutdown = Arc::clone(&shutdown);
+        thread::spawn(move || save_periodically(&store, &shutdown, interval));
+    }
+
+    // Ctrl-C / SIGTERM stop the listeners, the snapshot is saved once they
+    // have drained. SHUTDOWN over RESP saves on its own.
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flags = (Arc::clone(&interrupted), Arc::clone(&shutdown));
+    ctrlc::set_handler(move || {
+        flags.0.store(true, Ordering::SeqCst);
+        flags.1.store(true, Ordering::SeqCst);
+    })?;
+
+    for server in servers {
+        server.join().expect("server thread panicked")?;
+    }
+    if interrupted.load(Ordering::SeqCst) {
+        store.lock().unwrap().save()?;
+    }
+    Ok(())
+}
+
+fn save_periodically(store: &Mutex<Store>, shutdown: &AtomicBool, interval: Duration) {
+    let mut next_save = Instant::now() + interval;
+    while !shutdown.load(Ordering::SeqCst) {
+        if Instant::now() >= next_save {
+            if let Err(err) = store.lock().unwrap().save() {
+                eprintln!("error: {:#}", err);
+            }
+            next_save = Instant::now() + interval;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,373 @@
+// ref: https://redis.io/docs/latest/develop/reference/protocol-spec/ (RESP2)
+
+use crate::commands::{self, Action};
+use crate::store::Store;
+use anyhow::{bail, Result};
+use std::io::{prelude::*, BufReader};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// Sanity limits for untrusted input, memory is only allocated as the
+// bytes arrive so a declared length alone costs nothing
+const MAX_ARGS: usize = 64 * 1024;
+const MAX_BULK_LEN: usize = 16 * 1024 * 1024;
+const MAX_COMMAND_LEN: usize = 64 * 1024 * 1024;
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Reply {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Vec<u8>),
+    Nil,
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    pub fn ok() -> Self {
+        Self::Simple("OK".to_string())
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::Error(format!("ERR {}", message.into()))
+    }
+
+    pub fn bool(value: bool) -> Self {
+        Self::Integer(value as i64)
+    }
+
+    pub fn encode(&self, output: &mut Vec<u8>) {
+        match self {
+            Self::Simple(value) => write_line(output, b'+', value.as_bytes()),
+            Self::Error(message) => write_line(output, b'-', message.as_bytes()),
+            Self::Integer(value) => write_line(output, b':', value.to_string().as_bytes()),
+            Self::Bulk(value) => {
+                write_line(output, b'$', value.len().to_string().as_bytes());
+                output.extend_from_slice(value);
+                output.extend_from_slice(b"\r\n");
+            }
+            Self::Nil => output.extend_from_slice(b"$-1\r\n"),
+            Self::Array(values) => {
+                write_line(output, b'*', values.len().to_string().as_bytes());
+                for value in values {
+                    value.encode(output);
+                }
+            }
+        }
+    }
+}
+
+fn write_line(output: &mut Vec<u8>, prefix: u8, value: &[u8]) {
+    output.push(prefix);
+    output.extend_from_slice(value);
+    output.extend_from_slice(b"\r\n");
+}
+
+// Serves commands until the client quits or disconnects
+pub fn handle_connection(
+    stream: TcpStream,
+    store: &Mutex<Store>,
+    shutdown: &AtomicBool,
+) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut output = Vec::new();
+
+    loop {
+        let args = match read_command(&mut reader) {
+            Ok(Some(args)) => args,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                // Connection state is unknown after a protocol error
+                output.clear();
+                Reply::error(format!("Protocol error: {}", err)).encode(&mut output);
+                writer.write_all(&output)?;
+                return Ok(());
+            }
+        };
+
+        let (reply, action) = commands::execute(store, &args);
+        output.clear();
+        reply.encode(&mut output);
+        writer.write_all(&output)?;
+
+        match action {
+            Action::Continue => {}
+            Action::Quit => return Ok(()),
+            Action::Shutdown => {
+                shutdown.store(true, Ordering::SeqCst);
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Reads one command, either a RESP array of bulk strings or an inline
+// command as sent by telnet. Returns None on a clean end of stream.
+pub fn read_command(reader: &mut impl BufRead) -> Result<Option<Vec<Vec<u8>>>> {
+    loop {
+        let Some(line) = read_line(reader)? else {
+            return Ok(None);
+        };
+        let Some(count) = line.strip_prefix(b"*") else {
+            let args: Vec<Vec<u8>> = line
+                .split(|byte| byte.is_ascii_whitespace())
+                .filter(|arg| !arg.is_empty())
+                .map(<[u8]>::to_vec)
+                .collect();
+            if args.is_empty() {
+                continue;
+            }
+            return Ok(Some(args));
+        };
+
+        let count = parse_len(count, MAX_ARGS)?;
+        let mut args = Vec::new();
+        let mut total = 0;
+        for _ in 0..count {
+            let Some(line) = read_line(reader)? else {
+                bail!("unexpected end of stream");
+            };
+            let Some(len) = line.strip_prefix(b"$") else {
+                bail!("expected bulk string");
+            };
+            let len = parse_len(len, MAX_BULK_LEN)?;
+            total += len;
+            if total > MAX_COMMAND_LEN {
+                bail!("command too long");
+            }
+
+            let mut arg = Vec::new();
+            reader.take(len as u64 + 2).read_to_end(&mut arg)?;
+            if arg.len() < len + 2 {
+                bail!("unexpected end of stream");
+            }
+            if !arg.ends_with(b"\r\n") {
+                bail!("bulk string not terminated by CRLF");
+            }
+            arg.truncate(len);
+            args.push(arg);
+        }
+        return Ok(Some(args));
+    }
+}
+
+// Line without its CRLF (or bare LF), at most MAX_LINE_LEN bytes
+fn read_line(reader: &mut impl BufRead) -> Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let limit = MAX_LINE_LEN as u64 + 2;
+    if reader.take(limit).read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.pop() != Some(b'\n') {
+        if line.len() + 1 == limit as usize {
+            bail!("line too long");
+        }
+        bail!("unexpected end of stream");
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(Some(line))
+}
+
+fn parse_len(bytes: &[u8], max: usize) -> Result<usize> {
+    match std::str::from_utf8(bytes).ok().and_then(|s| s.parse().ok()) {
+        Some(len) if len <= max => Ok(len),
+        _ => bail!("invalid length"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::Server;
+    use crate::store::tests::tmp_path;
+    use std::fs;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+    use std::time::Duration;
+
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl Client {
+        fn connect(addr: SocketAddr) -> Self {
+            let stream = TcpStream::connect(addr).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            Self {
+                writer: stream.try_clone().unwrap(),
+                reader: BufReader::new(stream),
+            }
+        }
+
+        // Sends a RESP array and returns the raw reply lines
+        fn send(&mut self, args: &[&str], reply_lines: usize) -> String {
+            let mut request = format!("*{}\r\n", args.len());
+            for arg in args {
+                request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+            }
+            self.send_raw(request.as_bytes(), reply_lines)
+        }
+
+        fn send_raw(&mut self, request: &[u8], reply_lines: usize) -> String {
+            self.writer.write_all(request).unwrap();
+            let mut reply = String::new();
+            for _ in 0..reply_lines {
+                self.reader.read_line(&mut reply).unwrap();
+            }
+            reply
+        }
+    }
+
+    fn spawn_server(store: Store) -> (SocketAddr, Arc<AtomicBool>, JoinHandle<Result<()>>) {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let server = Server::bind("127.0.0.1:0", Arc::clone(&shutdown)).unwrap();
+        let addr = server.local_addr().unwrap();
+        let store = Mutex::new(store);
+        let flag = Arc::clone(&shutdown);
+        let handler = move |stream| handle_connection(stream, &store, &flag);
+        let handle = thread::spawn(move || server.run(handler, Duration::ZERO));
+        (addr, shutdown, handle)
+    }
+
+    fn encoded(reply: Reply) -> Vec<u8> {
+        let mut output = Vec::new();
+        reply.encode(&mut output);
+        output
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encoded(Reply::ok()), b"+OK\r\n");
+        assert_eq!(encoded(Reply::error("oops")), b"-ERR oops\r\n");
+        assert_eq!(encoded(Reply::bool(true)), b":1\r\n");
+        assert_eq!(encoded(Reply::Bulk(b"ab".to_vec())), b"$2\r\nab\r\n");
+        assert_eq!(encoded(Reply::Nil), b"$-1\r\n");
+        assert_eq!(
+            encoded(Reply::Array(vec![Reply::Integer(-3), Reply::Nil])),
+            b"*2\r\n:-3\r\n$-1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_read_command() {
+        let mut input: &[u8] = b"*2\r\n$4\r\nPING\r\n$3\r\na\r\n\r\n\r\nECHO  hi\n*0\r\n";
+        assert_eq!(
+            read_command(&mut input).unwrap(),
+            Some(vec![b"PING".to_vec(), b"a\r\n".to_vec()])
+        );
+        assert_eq!(
+            read_command(&mut input).unwrap(),
+            Some(vec![b"ECHO".to_vec(), b"hi".to_vec()])
+        );
+        assert_eq!(read_command(&mut input).unwrap(), Some(vec![]));
+        assert_eq!(read_command(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn test_tcp_session() {
+        let snapshot = tmp_path("resp_session.bfs");
+        let _ = fs::remove_file(&snapshot);
+        let (addr, _, handle) = spawn_server(Store::open(Some(snapshot.clone())).unwrap());
+
+        let mut client = Client::connect(addr);
+        assert_eq!(client.send(&["PING"], 1), "+PONG\r\n");
+        assert_eq!(
+            client.send(&["BF.RESERVE", "f", "0.01", "100"], 1),
+            "+OK\r\n"
+        );
+        assert_eq!(
+            client.send(&["BF.MADD", "f", "a", "b"], 3),
+            "*2\r\n:1\r\n:1\r\n"
+        );
+        assert_eq!(client.send(&["BF.EXISTS", "f", "a"], 1), ":1\r\n");
+
+        // Inline commands from a plain TCP client
+        let mut other = Client::connect(addr);
+        assert_eq!(other.send_raw(b"BF.EXISTS f c\r\n", 1), ":0\r\n");
+
+        assert_eq!(client.send(&["SAVE"], 1), "+OK\r\n");
+        assert_eq!(client.send(&["BF.ADD", "f", "c"], 1), ":1\r\n");
+        assert_eq!(client.send(&["SHUTDOWN"], 1), "+OK\r\n");
+        handle.join().unwrap().unwrap();
+
+        // Shutdown saved the filter after the explicit SAVE
+        let store = Store::open(Some(snapshot.clone())).unwrap();
+        let filter = store.get("f").unwrap();
+        assert_eq!(filter.items, 3);
+        assert!(filter.bloom_filter.lookup("c"));
+
+        fs::remove_file(snapshot).unwrap();
+    }
+
+    #[test]
+    fn test_protocol_error() {
+        let (addr, shutdown, handle) = spawn_server(Store::open(None).unwrap());
+
+        let mut client = Client::connect(addr);
+        assert_eq!(
+            client.send_raw(b"*1\r\n:1\r\n", 1),
+            "-ERR Protocol error: expected bulk string\r\n"
+        );
+
+        shutdown.store(true, Ordering::SeqCst);
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_read_invalid_command() {
+        let inputs: [&[u8]; 5] = [
+            b"*x\r\n",
+            b"*1\r\n:1\r\n",
+            b"*1\r\n$3\r\nabcd\r\n",
+            b"*2\r\n$1\r\na\r\n",
+            b"*1\r\n$1",
+        ];
+        for mut input in inputs {
+            assert!(read_command(&mut input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_read_limits() {
+        // Declared lengths are not allocated up front
+        let mut input: &[u8] = b"*1\r\n$16777216\r\nabc";
+        let err = read_command(&mut input).unwrap_err();
+        assert_eq!(err.to_string(), "unexpected end of stream");
+        let mut input: &[u8] = b"*1\r\n$16777217\r\n";
+        assert_eq!(
+            read_command(&mut input).unwrap_err().to_string(),
+            "invalid length"
+        );
+        let mut input: &[u8] = b"*65537\r\n";
+        assert!(read_command(&mut input).is_err());
+
+        let mut command = b"*5\r\n".to_vec();
+        for _ in 0..5 {
+            command.extend_from_slice(b"$16777216\r\n");
+            command.resize(command.len() + MAX_BULK_LEN, b'a');
+            command.extend_from_slice(b"\r\n");
+        }
+        let err = read_command(&mut command.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "command too long");
+
+        let mut line = vec![b'a'; MAX_LINE_LEN];
+        line.extend_from_slice(b"\r\n");
+        assert_eq!(
+            read_command(&mut line.as_slice()).unwrap().unwrap().len(),
+            1
+        );
+        let mut line = vec![b'a'; MAX_LINE_LEN + 2];
+        line.push(b'\n');
+        let err = read_command(&mut line.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "line too long");
+    }
+}
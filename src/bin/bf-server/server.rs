@@ -0,0 +1,104 @@
+use anyhow::Result;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// Accept loop spawning a thread per connection until the shared shutdown
+// flag is set
+pub struct Server {
+    listener: TcpListener,
+    shutdown: Arc<AtomicBool>,
+    active: Arc<AtomicUsize>,
+}
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs, shutdown: Arc<AtomicBool>) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        // Polled so the accept loop notices shutdown requests
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            listener,
+            shutdown,
+            active: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    // After shutdown, waits up to `drain` for open connections to finish
+    pub fn run<F>(&self, handler: F, drain: Duration) -> Result<()>
+    where
+        F: Fn(TcpStream) -> Result<()> + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+
+        while !self.shutdown.load(Ordering::SeqCst) {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    let handler = Arc::clone(&handler);
+                    let active = Arc::clone(&self.active);
+                    active.fetch_add(1, Ordering::SeqCst);
+                    thread::spawn(move || {
+                        if let Err(err) = handler(stream) {
+                            eprintln!("connection error: {:#}", err);
+                        }
+                        active.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let deadline = Instant::now() + drain;
+        while self.active.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+            thread::sleep(POLL_INTERVAL);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::prelude::*;
+
+    #[test]
+    fn test_run_until_shutdown() {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let server = Server::bind("127.0.0.1:0", Arc::clone(&shutdown)).unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let echo = |mut stream: TcpStream| {
+                let mut buffer = [0; 4];
+                stream.read_exact(&mut buffer)?;
+                // Slow handler, must be drained before run returns
+                thread::sleep(Duration::from_millis(200));
+                stream.write_all(&buffer)?;
+                Ok(())
+            };
+            server.run(echo, Duration::from_secs(5))
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"ping").unwrap();
+        thread::sleep(Duration::from_millis(100));
+        shutdown.store(true, Ordering::SeqCst);
+        handle.join().unwrap().unwrap();
+
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).unwrap();
+        assert_eq!(reply, b"ping");
+    }
+}
@@ -0,0 +1,272 @@
+use anyhow::{bail, Context, Result};
+use bloom_filter_yss::error::BloomFilterError;
+use bloom_filter_yss::params::Params;
+use bloom_filter_yss::{BloomFilter, BloomFilterBuilder};
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+// RedisBloom defaults for filters created implicitly by BF.ADD
+pub const DEFAULT_CAPACITY: usize = 100;
+pub const DEFAULT_ERROR_RATE: f64 = 0.01;
+
+const SNAPSHOT_MAGIC: &[u8; 4] = b"BFSS";
+const SNAPSHOT_VERSION: u8 = 1;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct NamedFilter {
+    pub bloom_filter: BloomFilter,
+    pub capacity: usize,
+    pub error_rate: f64,
+    // Inserts that changed the filter
+    pub items: usize,
+}
+
+impl NamedFilter {
+    pub fn new(capacity: usize, error_rate: f64) -> Result<Self> {
+        let builder = BloomFilterBuilder::new(capacity).false_positive_rate(error_rate);
+        builder.params()?;
+
+        Ok(Self {
+            bloom_filter: builder.build(),
+            capacity,
+            error_rate,
+            items: 0,
+        })
+    }
+
+    pub fn insert(&mut self, key: &[u8]) -> bool {
+        let added = self.bloom_filter.insert(key);
+        if added {
+            self.items += 1;
+        }
+        added
+    }
+
+    pub fn params(&self) -> Params {
+        Params {
+            items: self.capacity,
+            bits: self.bloom_filter.bit_array.size,
+            hash_count: self.bloom_filter.hash_count,
+            false_positive_rate: self.error_rate,
+        }
+    }
+}
+
+// Named filters, persisted together in a snapshot file. Each filter is
+// stored in the crate file format next to its name and counters.
+pub struct Store {
+    filters: BTreeMap<String, NamedFilter>,
+    snapshot: Option<PathBuf>,
+}
+
+impl Store {
+    pub fn open(snapshot: Option<PathBuf>) -> Result<Self> {
+        let filters = match &snapshot {
+            Some(path) if path.exists() => {
+                let bytes =
+                    fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+                decode_snapshot(&bytes)
+                    .with_context(|| format!("failed to load {}", path.display()))?
+            }
+            _ => BTreeMap::new(),
+        };
+
+        Ok(Self { filters, snapshot })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NamedFilter> {
+        self.filters.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut NamedFilter> {
+        self.filters.get_mut(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.filters.keys().map(String::as_str)
+    }
+
+    pub fn reserve(&mut self, name: &str, capacity: usize, error_rate: f64) -> Result<()> {
+        match self.filters.entry(name.to_string()) {
+            Entry::Occupied(_) => bail!("item exists"),
+            Entry::Vacant(entry) => {
+                entry.insert(NamedFilter::new(capacity, error_rate)?);
+                Ok(())
+            }
+        }
+    }
+
+    // Creates the filter with default parameters when missing
+    pub fn get_or_create(&mut self, name: &str) -> &mut NamedFilter {
+        self.filters.entry(name.to_string()).or_insert_with(|| {
+            NamedFilter::new(DEFAULT_CAPACITY, DEFAULT_ERROR_RATE)
+                .expect("default parameters are valid")
+        })
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.filters.remove(name).is_some()
+    }
+
+    // Atomic write of every filter, no-op without a snapshot path
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.snapshot else {
+            return Ok(());
+        };
+        let tmp_path = path.with_extension("tmp");
+        let result = (|| -> Result<()> {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&encode_snapshot(&self.filters))?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, path)?;
+            Ok(())
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        result.with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+fn encode_snapshot(filters: &BTreeMap<String, NamedFilter>) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    encoded.extend_from_slice(SNAPSHOT_MAGIC);
+    encoded.push(SNAPSHOT_VERSION);
+    encoded.extend_from_slice(&(filters.len() as u64).to_be_bytes());
+
+    for (name, filter) in filters {
+        let bytes = filter.bloom_filter.to_bytes();
+        encoded.extend_from_slice(&(name.len() as u64).to_be_bytes());
+        encoded.extend_from_slice(name.as_bytes());
+        encoded.extend_from_slice(&(filter.capacity as u64).to_be_bytes());
+        encoded.extend_from_slice(&filter.error_rate.to_be_bytes());
+        encoded.extend_from_slice(&(filter.items as u64).to_be_bytes());
+        encoded.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+        encoded.extend_from_slice(&bytes);
+    }
+
+    encoded
+}
+
+fn decode_snapshot(bytes: &[u8]) -> Result<BTreeMap<String, NamedFilter>> {
+    let mut reader = Reader { bytes };
+    if reader.take(SNAPSHOT_MAGIC.len())? != SNAPSHOT_MAGIC {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+    let version = reader.take(1)?[0];
+    if version != SNAPSHOT_VERSION {
+        return Err(BloomFilterError::UnsupportedVersion(version).into());
+    }
+
+    let mut filters = BTreeMap::new();
+    for _ in 0..reader.u64()? {
+        let name_len = reader.u64()? as usize;
+        let name = String::from_utf8(reader.take(name_len)?.to_vec())?;
+        let capacity = reader.u64()? as usize;
+        let error_rate = f64::from_bits(reader.u64()?);
+        let items = reader.u64()? as usize;
+        let filter_len = reader.u64()? as usize;
+        let bloom_filter = BloomFilter::from_bytes(reader.take(filter_len)?)?;
+
+        let filter = NamedFilter {
+            bloom_filter,
+            capacity,
+            error_rate,
+            items,
+        };
+        filters.insert(name, filter);
+    }
+    if !reader.bytes.is_empty() {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+
+    Ok(filters)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::path::Path;
+
+    pub fn tmp_path(name: &str) -> PathBuf {
+        let tmp_dir = Path::new("tmp");
+        if !tmp_dir.exists() {
+            fs::create_dir(tmp_dir).unwrap();
+        }
+        tmp_dir.join(format!("bf_server_{}", name))
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut store = Store::open(None).unwrap();
+        store.reserve("a", 1000, 0.001).unwrap();
+        assert!(store.reserve("a", 10, 0.1).is_err());
+        assert!(store.reserve("b", 0, 0.1).is_err());
+        assert!(store.reserve("b", 10, 1.0).is_err());
+
+        let filter = store.get("a").unwrap();
+        assert_eq!(filter.capacity, 1000);
+        assert_eq!(filter.bloom_filter.hash_count, 10);
+        assert!(store.get("b").is_none());
+    }
+
+    #[test]
+    fn test_get_or_create() {
+        let mut store = Store::open(None).unwrap();
+        assert!(store.get_or_create("a").insert(b"x"));
+        assert!(!store.get_or_create("a").insert(b"x"));
+
+        let filter = store.get("a").unwrap();
+        assert_eq!(filter.capacity, DEFAULT_CAPACITY);
+        assert_eq!(filter.items, 1);
+        assert!(store.get_mut("a").unwrap().insert(b"y"));
+        assert_eq!(store.names().collect::<Vec<_>>(), ["a"]);
+        assert!(store.remove("a"));
+        assert!(!store.remove("a"));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let path = tmp_path("store_snapshot.bfs");
+        let _ = fs::remove_file(&path);
+
+        let mut store = Store::open(Some(path.clone())).unwrap();
+        store.reserve("first", 1000, 0.001).unwrap();
+        store.get_or_create("first").insert(b"one");
+        store.get_or_create("second \u{1f600}").insert(&[0xff, 0]);
+        store.save().unwrap();
+
+        let loaded = Store::open(Some(path.clone())).unwrap();
+        assert_eq!(loaded.filters, store.filters);
+        let first = loaded.get("first").unwrap();
+        assert_eq!(first.items, 1);
+        assert_eq!(first.error_rate, 0.001);
+        assert!(first.bloom_filter.lookup(b"one"));
+
+        fs::write(&path, &fs::read(&path).unwrap()[..20]).unwrap();
+        assert!(Store::open(Some(path.clone())).is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use crate::format::{FilterKind, Persistable};
+use crate::hash::fmix64;
+use crate::rng::SplitMix64;
+use crate::xor_filter::{self, Fingerprint};
+use anyhow::Result;
+
+const SEED: u64 = 0x9e2c_1a4f_57b3_8d61;
+const MAX_ATTEMPTS: usize = 100;
+pub(crate) const MAX_SEGMENT_LENGTH: usize = 1 << 18;
+
+// ref: Graf & Lemire (2022), "Binary Fuse Filters: Fast and Smaller Than
+// Xor Filters"
+// ref: https://github.com/FastFilter/xor_singleheader/blob/master/include/binaryfusefilter.h
+//
+// Same lookup as `XorFilter`, but the three slots fall in consecutive
+// segments of a window, which peels at a lower load overhead (about 1.125x
+// instead of 1.23x) and keeps the slots of a key close together.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BinaryFuseFilter<F: Fingerprint> {
+    pub(crate) seed: u64,
+    pub(crate) segment_length: usize,
+    pub(crate) segment_count: usize,
+    pub(crate) fingerprints: Vec<F>,
+}
+
+pub type BinaryFuse8Filter = BinaryFuseFilter<u8>;
+pub type BinaryFuse16Filter = BinaryFuseFilter<u16>;
+
+impl<F: Fingerprint> BinaryFuseFilter<F> {
+    // Duplicate keys are allowed. Fails with
+    // `BloomFilterError::ConstructionFailed` when no seed peels every key.
+    pub fn new<I, K>(keys: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        let hashes = xor_filter::key_hashes(keys);
+        let (segment_length, segment_count) = sizing(hashes.len());
+        let mut filter = Self {
+            seed: 0,
+            segment_length,
+            segment_count,
+            fingerprints: vec![F::default(); (segment_count + 2) * segment_length],
+        };
+
+        let mut seeds = SplitMix64::new(SEED);
+        for _ in 0..MAX_ATTEMPTS {
+            filter.seed = seeds.next_u64();
+            let order = xor_filter::peel(&hashes, filter.fingerprints.len(), |hash| {
+                filter.positions(fmix64(hash.wrapping_add(filter.seed)))
+            });
+            if let Some(order) = order {
+                filter.assign(&order);
+                return Ok(filter);
+            }
+        }
+        Err(xor_filter::construction_failed(hashes.len()))
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        let hash = fmix64(xor_filter::key_hash(key.as_ref()).wrapping_add(self.seed));
+        let [h0, h1, h2] = self.positions(hash);
+        F::from_hash(hash) == self.fingerprints[h0] ^ self.fingerprints[h1] ^ self.fingerprints[h2]
+    }
+
+    // Fingerprint bits per key for `len` keys
+    pub fn bits_per_key(&self, len: usize) -> f64 {
+        (self.fingerprints.len() * F::BITS as usize) as f64 / len as f64
+    }
+
+    // The high bits pick the first segment, the low ones an offset in each
+    fn positions(&self, hash: u64) -> [usize; 3] {
+        let mask = self.segment_length as u64 - 1;
+        let span = (self.segment_count * self.segment_length) as u64;
+        let h0 = ((hash as u128 * span as u128) >> 64) as u64;
+        let h1 = (h0 + self.segment_length as u64) ^ ((hash >> 18) & mask);
+        let h2 = (h0 + 2 * self.segment_length as u64) ^ (hash & mask);
+        [h0 as usize, h1 as usize, h2 as usize]
+    }
+
+    fn assign(&mut self, order: &[(u64, usize)]) {
+        for &(hash, index) in order.iter().rev() {
+            let hash = fmix64(hash.wrapping_add(self.seed));
+            let [h0, h1, h2] = self.positions(hash);
+            self.fingerprints[index] = F::from_hash(hash)
+                ^ self.fingerprints[h0]
+                ^ self.fingerprints[h1]
+                ^ self.fingerprints[h2];
+        }
+    }
+}
+
+impl<F: Fingerprint> Persistable for BinaryFuseFilter<F> {
+    fn kind() -> FilterKind {
+        match F::BITS {
+            8 => FilterKind::BinaryFuse8,
+            _ => FilterKind::BinaryFuse16,
+        }
+    }
+}
+
+// `binary_fuse_allocate` for arity 3: segment length and count of the
+// window starts, the array spanning two more segments
+fn sizing(len: usize) -> (usize, usize) {
+    let segment_length = match len {
+        0 => 4,
+        _ => {
+            let exponent = ((len as f64).ln() / 3.33f64.ln() + 2.25).floor() as u32;
+            (1usize << exponent).min(MAX_SEGMENT_LENGTH)
+        }
+    };
+    let capacity = match len {
+        0 | 1 => 0,
+        _ => {
+            let size_factor = (0.875 + 0.25 * 1e6f64.ln() / (len as f64).ln()).max(1.125);
+            (len as f64 * size_factor).round() as usize
+        }
+    };
+    let segment_count = capacity.div_ceil(segment_length).saturating_sub(2).max(1);
+    (segment_length, segment_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encodable;
+    use crate::test_util::keys;
+    use crate::Xor8Filter;
+
+    #[test]
+    fn test_sizing() {
+        assert_eq!(sizing(0), (4, 1));
+        assert_eq!(sizing(1), (4, 1));
+        assert_eq!(sizing(100), (64, 1));
+        assert_eq!(sizing(10_000), (512, 23));
+        assert_eq!(sizing(1_000_000), (8192, 136));
+        assert_eq!(sizing(1 << 40).0, MAX_SEGMENT_LENGTH);
+    }
+
+    #[test]
+    fn test_binary_fuse8() {
+        let filter = BinaryFuse8Filter::new(keys(0..100_000)).unwrap();
+        assert!(keys(0..100_000).iter().all(|key| filter.lookup(key)));
+
+        let false_positives = keys(100_000..200_000)
+            .iter()
+            .filter(|key| filter.lookup(key))
+            .count();
+        assert!((250..550).contains(&false_positives), "{}", false_positives);
+
+        let xor_filter = Xor8Filter::new(keys(0..100_000)).unwrap();
+        assert!(filter.bits_per_key(100_000) < xor_filter.bits_per_key(100_000));
+        assert!(filter.bits_per_key(100_000) < 9.6);
+    }
+
+    #[test]
+    fn test_binary_fuse16() {
+        let filter = BinaryFuse16Filter::new(keys(0..10_000)).unwrap();
+        assert!(keys(0..10_000).iter().all(|key| filter.lookup(key)));
+        let false_positives = keys(10_000..110_000)
+            .iter()
+            .filter(|key| filter.lookup(key))
+            .count();
+        assert!(false_positives < 10, "{}", false_positives);
+    }
+
+    #[test]
+    fn test_small() {
+        for n in 0..20 {
+            let filter = BinaryFuse8Filter::new(keys(0..n)).unwrap();
+            assert!(keys(0..n).iter().all(|key| filter.lookup(key)), "{}", n);
+        }
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        let filter = BinaryFuse8Filter::new(keys(0..100)).unwrap();
+        let bytes = filter.to_bytes();
+        assert_eq!(&bytes[..6], b"BFYS\x04\x05");
+        assert_eq!(&bytes[6..], filter.encode());
+        assert_eq!(BinaryFuse8Filter::from_bytes(&bytes).unwrap(), filter);
+        assert!(BinaryFuse16Filter::from_bytes(&bytes).is_err());
+        assert!(Xor8Filter::from_bytes(&bytes).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+// Bitcoin connection Bloom filtering, BIP37
+// ref: https://github.com/bitcoin/bips/blob/master/bip-0037.mediawiki
+// ref: https://github.com/bitcoin/bitcoin/blob/master/src/common/bloom.cpp
+//
+// Hash function i is murmur3 seeded with i * 0xfba4c795 + nTweak, reduced
+// modulo the filter bits. Bits are numbered from the least significant one
+// of each byte, bit x is bit x ^ 7 of the crate's `BitArray`.
+//
+// filterload payload, little endian:
+//
+//   compact size length, filter bytes, u32 nHashFuncs, u32 nTweak, u8 nFlags
+
+use crate::bloom_filter::{BitArray, BloomFilter, CompressMode, HashScheme, Layout};
+use crate::error::BloomFilterError;
+use crate::hash::murmur3;
+use anyhow::Result;
+use std::f64::consts::LN_2;
+
+pub const MAX_FILTER_SIZE: usize = 36_000;
+pub const MAX_HASH_FUNCS: usize = 50;
+
+const SEED_MULTIPLIER: u32 = 0xfba4_c795;
+
+// nFlags, how a node updates the filter when a transaction matches
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UpdateFlags {
+    None,
+    All,
+    P2PubKeyOnly,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bip37Filter {
+    pub bloom_filter: BloomFilter,
+    pub flags: UpdateFlags,
+}
+
+impl Bip37Filter {
+    // Sized like Bitcoin Core's CBloomFilter, within BIP37's limits
+    pub fn new(
+        elements: u32,
+        false_positive_rate: f64,
+        tweak: u32,
+        flags: UpdateFlags,
+    ) -> Result<Self> {
+        if elements == 0 {
+            let reason = "elements must be positive".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            let reason = "false positive rate must be in (0, 1)".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+
+        let bits = (-1.0 / (LN_2 * LN_2) * elements as f64 * false_positive_rate.ln()) as u32;
+        let bytes = (bits as usize / 8).min(MAX_FILTER_SIZE);
+        // Integer division first, as in CBloomFilter
+        let hash_count = (((bytes * 8) as u32 / elements) as f64 * LN_2) as usize;
+        let hash_count = hash_count.min(MAX_HASH_FUNCS);
+        // BIP37 nodes match everything with either of them zero
+        if bytes == 0 || hash_count == 0 {
+            let reason = format!(
+                "{} bytes and {} hash functions, too few for {} elements at {}",
+                bytes, hash_count, elements, false_positive_rate
+            );
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+
+        let bloom_filter = BloomFilter {
+            bit_array: BitArray::new(bytes * 8),
+            hash_count,
+            compress_mode: CompressMode::None,
+            hash_scheme: HashScheme::Bip37 { tweak },
+            layout: Layout::Standard,
+        };
+        Ok(Self {
+            bloom_filter,
+            flags,
+        })
+    }
+
+    // Payload of a filterload message
+    pub fn to_filterload(&self) -> Result<Vec<u8>> {
+        let HashScheme::Bip37 { tweak } = self.bloom_filter.hash_scheme else {
+            let reason = format!(
+                "{:?} hashing, BIP37 filters use BIP37 hashing",
+                self.bloom_filter.hash_scheme
+            );
+            return Err(BloomFilterError::Unsupported(reason).into());
+        };
+        let bit_array = &self.bloom_filter.bit_array;
+        if bit_array.size != bit_array.byte_array.len() * 8 {
+            let reason = format!("{} bits, BIP37 filters hold whole bytes", bit_array.size);
+            return Err(BloomFilterError::Unsupported(reason).into());
+        }
+        check_limits(bit_array.byte_array.len(), self.bloom_filter.hash_count)?;
+
+        let mut payload = Vec::with_capacity(3 + bit_array.byte_array.len() + 9);
+        write_compact_size(bit_array.byte_array.len() as u64, &mut payload);
+        payload.extend_from_slice(&bit_array.byte_array);
+        payload.extend_from_slice(&(self.bloom_filter.hash_count as u32).to_le_bytes());
+        payload.extend_from_slice(&tweak.to_le_bytes());
+        payload.push(self.flags.into());
+        Ok(payload)
+    }
+
+    pub fn from_filterload(payload: &[u8]) -> Result<Self> {
+        let (len, payload) = read_compact_size(payload)?;
+        let len = usize::try_from(len).map_err(|_| BloomFilterError::InvalidFormat)?;
+        if payload.len() != len.saturating_add(9) {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let (data, rest) = payload.split_at(len);
+        let hash_count = u32::from_le_bytes(rest[..4].try_into()?) as usize;
+        let tweak = u32::from_le_bytes(rest[4..8].try_into()?);
+        let flags = UpdateFlags::try_from(rest[8])?;
+        check_limits(data.len(), hash_count)?;
+
+        let bloom_filter = BloomFilter {
+            bit_array: BitArray {
+                byte_array: data.to_vec(),
+                size: data.len() * 8,
+            },
+            hash_count,
+            compress_mode: CompressMode::None,
+            hash_scheme: HashScheme::Bip37 { tweak },
+            layout: Layout::Standard,
+        };
+        Ok(Self {
+            bloom_filter,
+            flags,
+        })
+    }
+}
+
+impl From<UpdateFlags> for u8 {
+    fn from(flags: UpdateFlags) -> Self {
+        match flags {
+            UpdateFlags::None => 0,
+            UpdateFlags::All => 1,
+            UpdateFlags::P2PubKeyOnly => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for UpdateFlags {
+    type Error = anyhow::Error;
+
+    fn try_from(flags: u8) -> Result<Self> {
+        match flags {
+            0 => Ok(Self::None),
+            1 => Ok(Self::All),
+            2 => Ok(Self::P2PubKeyOnly),
+            _ => Err(BloomFilterError::InvalidFormat.into()),
+        }
+    }
+}
+
+pub(crate) fn hashing(bytes: &[u8], hash_count: usize, bit_count: usize, tweak: u32) -> Vec<usize> {
+    (0..hash_count as u32)
+        .map(|i| {
+            let seed = i.wrapping_mul(SEED_MULTIPLIER).wrapping_add(tweak);
+            (murmur3(bytes, seed) as usize % bit_count) ^ 7
+        })
+        .collect()
+}
+
+// Empty filters and filters without hash functions match everything
+fn check_limits(bytes: usize, hash_count: usize) -> Result<()> {
+    if bytes == 0 || bytes > MAX_FILTER_SIZE {
+        let reason = format!(
+            "{} byte filter, BIP37 allows 1 to {} bytes",
+            bytes, MAX_FILTER_SIZE
+        );
+        return Err(BloomFilterError::Unsupported(reason).into());
+    }
+    if hash_count == 0 || hash_count > MAX_HASH_FUNCS {
+        let reason = format!(
+            "{} hash functions, BIP37 allows 1 to {}",
+            hash_count, MAX_HASH_FUNCS
+        );
+        return Err(BloomFilterError::Unsupported(reason).into());
+    }
+    Ok(())
+}
+
+// ref: https://en.bitcoin.it/wiki/Protocol_documentation#Variable_length_integer
+pub(crate) fn write_compact_size(value: u64, output: &mut Vec<u8>) {
+    match value {
+        0..0xfd => output.push(value as u8),
+        0xfd..=0xffff => {
+            output.push(0xfd);
+            output.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            output.push(0xfe);
+            output.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        _ => {
+            output.push(0xff);
+            output.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+// Rejects non canonical encodings, as Bitcoin nodes do
+pub(crate) fn read_compact_size(bytes: &[u8]) -> Result<(u64, &[u8])> {
+    let (&prefix, rest) = bytes.split_first().ok_or(BloomFilterError::InvalidFormat)?;
+    let (width, min) = match prefix {
+        0xfd => (2, 0xfd),
+        0xfe => (4, 0x1_0000),
+        0xff => (8, 0x1_0000_0000),
+        _ => return Ok((prefix as u64, rest)),
+    };
+    if rest.len() < width {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+    let mut buffer = [0; 8];
+    buffer[..width].copy_from_slice(&rest[..width]);
+    let value = u64::from_le_bytes(buffer);
+    if value < min {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+    Ok((value, &rest[width..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+
+    // Bitcoin Core bloom_tests.cpp, bloom_create_insert_serialize
+    fn core_filter(tweak: u32) -> Bip37Filter {
+        let mut filter = Bip37Filter::new(3, 0.01, tweak, UpdateFlags::All).unwrap();
+        filter
+            .bloom_filter
+            .insert(&hex("99108ad8ed9bb6274d3980bab5a85c048f0950c8"));
+        assert!(filter
+            .bloom_filter
+            .lookup(&hex("99108ad8ed9bb6274d3980bab5a85c048f0950c8")));
+        assert!(!filter
+            .bloom_filter
+            .lookup(&hex("19108ad8ed9bb6274d3980bab5a85c048f0950c8")));
+        filter
+            .bloom_filter
+            .insert(&hex("b5a2c786d9ef4658287ced5914b37a1b4aa32eee"));
+        filter
+            .bloom_filter
+            .insert(&hex("b9300670b4c5366e95b2699e8b18bc75e5f729c5"));
+        filter
+    }
+
+    #[test]
+    fn test_core_vectors() {
+        let filter = core_filter(0);
+        assert_eq!(
+            filter.to_filterload().unwrap(),
+            hex("03614e9b050000000000000001")
+        );
+
+        // bloom_create_insert_serialize_with_tweak
+        let filter = core_filter(2147483649);
+        assert_eq!(
+            filter.to_filterload().unwrap(),
+            hex("03ce4299050000000100008001")
+        );
+    }
+
+    #[test]
+    fn test_filterload_round_trip() {
+        let mut filter = Bip37Filter::new(1000, 0.0001, 7, UpdateFlags::P2PubKeyOnly).unwrap();
+        assert_eq!(filter.bloom_filter.bit_array.byte_array.len(), 2396);
+        assert_eq!(filter.bloom_filter.hash_count, 13);
+        for i in 0..1000 {
+            filter.bloom_filter.insert(&i.to_string());
+        }
+
+        let payload = filter.to_filterload().unwrap();
+        assert_eq!(&payload[..3], [0xfd, 0x5c, 0x09]);
+        let loaded = Bip37Filter::from_filterload(&payload).unwrap();
+        assert_eq!(loaded, filter);
+        assert!((0..1000).all(|i| loaded.bloom_filter.lookup(&i.to_string())));
+
+        // Kept through the crate file format
+        let bloom_filter = BloomFilter::from_bytes(&filter.bloom_filter.to_bytes()).unwrap();
+        assert_eq!(bloom_filter, filter.bloom_filter);
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let filter = Bip37Filter::new(100_000, 0.0001, 0, UpdateFlags::None).unwrap();
+        assert_eq!(
+            filter.bloom_filter.bit_array.byte_array.len(),
+            MAX_FILTER_SIZE
+        );
+        assert_eq!(filter.bloom_filter.hash_count, 1);
+        let filter = Bip37Filter::new(1, 1e-30, 0, UpdateFlags::None).unwrap();
+        assert_eq!(filter.bloom_filter.hash_count, MAX_HASH_FUNCS);
+
+        assert!(Bip37Filter::new(0, 0.01, 0, UpdateFlags::None).is_err());
+        assert!(Bip37Filter::new(1, 0.9, 0, UpdateFlags::None).is_err());
+        assert!(Bip37Filter::new(1, 1.0, 0, UpdateFlags::None).is_err());
+    }
+
+    #[test]
+    fn test_invalid_filterload() {
+        let payload = hex("03614e9b050000000000000001");
+        assert!(Bip37Filter::from_filterload(&payload).is_ok());
+        assert!(Bip37Filter::from_filterload(&payload[..12]).is_err());
+        assert!(Bip37Filter::from_filterload(&[&payload[..], &[0]].concat()).is_err());
+        assert!(Bip37Filter::from_filterload(&[]).is_err());
+
+        let mut unknown_flags = payload.clone();
+        unknown_flags[12] = 3;
+        assert!(Bip37Filter::from_filterload(&unknown_flags).is_err());
+
+        let mut too_many_hashes = payload.clone();
+        too_many_hashes[4] = 51;
+        assert_eq!(
+            Bip37Filter::from_filterload(&too_many_hashes)
+                .unwrap_err()
+                .to_string(),
+            "Unsupported filter: 51 hash functions, BIP37 allows 1 to 50"
+        );
+
+        let mut too_large = vec![0xfd, 0xa1, 0x8c];
+        too_large.extend(vec![0; 36_001 + 9]);
+        assert_eq!(
+            Bip37Filter::from_filterload(&too_large)
+                .unwrap_err()
+                .to_string(),
+            "Unsupported filter: 36001 byte filter, BIP37 allows 1 to 36000 bytes"
+        );
+
+        // Non canonical compact size
+        let mut non_canonical = vec![0xfd, 0x03, 0x00];
+        non_canonical.extend_from_slice(&payload[1..]);
+        assert!(Bip37Filter::from_filterload(&non_canonical).is_err());
+    }
+
+    #[test]
+    fn test_to_filterload_mismatch() {
+        let filter = Bip37Filter {
+            bloom_filter: BloomFilter::new(10),
+            flags: UpdateFlags::None,
+        };
+        assert_eq!(
+            filter.to_filterload().unwrap_err().to_string(),
+            "Unsupported filter: Murmur3Fnv hashing, BIP37 filters use BIP37 hashing"
+        );
+    }
+}
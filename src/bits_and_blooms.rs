@@ -0,0 +1,254 @@
+// Go bits-and-blooms/bloom v3 layout, `BloomFilter.WriteTo` / `ReadFrom`
+// ref: https://github.com/bits-and-blooms/bloom/blob/master/bloom.go
+// ref: https://github.com/bits-and-blooms/bitset/blob/master/bitset.go
+//
+// Written from the Go sources. The golden files' generator is
+// tests/data/bits_and_blooms/golden.go, but the checked in ones still come
+// from golden.py, its Python transcription, until they are rerun with Go.
+//
+// Layout, big endian:
+//
+//   u64 m, u64 k, u64 bitset length (m), then the bitset as u64 words
+//
+// Bit x is bit x % 64 of word x / 64, counting from the least significant
+// one. As m need not be a multiple of 64, words are converted to and from
+// the crate's `BitArray` order instead of remapping indexes.
+//
+// Keys are the bytes Go hashes: `AddString(s)` and `Add([]byte(s))` agree
+// with `insert` on the UTF-8 string.
+
+use crate::bloom_filter::{BitArray, BloomFilter, CompressMode, HashScheme, Layout};
+use crate::error::BloomFilterError;
+use crate::hash::murmur3_x64_128;
+use anyhow::Result;
+use std::f64::consts::LN_2;
+
+const HEADER_SIZE: usize = 3 * 8;
+
+// Sized like `bloom.NewWithEstimates(n, fp)`
+pub fn create(n: u64, fp: f64) -> Result<BloomFilter> {
+    if !(fp > 0.0 && fp < 1.0) {
+        let reason = "false positive rate must be in (0, 1)".to_string();
+        return Err(BloomFilterError::InvalidParams(reason).into());
+    }
+    let m = (-(n as f64) * fp.ln() / LN_2.powi(2)).ceil() as u64;
+    let k = (LN_2 * m as f64 / n as f64).ceil() as u64;
+    Ok(new(m, k))
+}
+
+// Same as `bloom.New(m, k)`
+pub fn new(m: u64, k: u64) -> BloomFilter {
+    BloomFilter {
+        bit_array: BitArray::new(m.max(1) as usize),
+        hash_count: k.max(1) as usize,
+        compress_mode: CompressMode::None,
+        hash_scheme: HashScheme::BitsAndBlooms,
+        layout: Layout::Standard,
+    }
+}
+
+// Filter written by Go's `WriteTo`
+pub fn from_bytes(bytes: &[u8]) -> Result<BloomFilter> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+    let m = u64::from_be_bytes(bytes[..8].try_into()?);
+    let k = u64::from_be_bytes(bytes[8..16].try_into()?);
+    let length = u64::from_be_bytes(bytes[16..HEADER_SIZE].try_into()?);
+    let data = &bytes[HEADER_SIZE..];
+    if m == 0 || k == 0 || length != m || data.len() as u64 != m.div_ceil(64) * 8 {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+
+    let mut bit_array = BitArray {
+        byte_array: convert_words(data),
+        size: data.len() * 8,
+    };
+    // Bits past m belong to no location
+    if (m as usize..bit_array.size).any(|i| bit_array[i]) {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+    bit_array.size = m as usize;
+    bit_array.byte_array.truncate(bit_array.size.div_ceil(8));
+
+    Ok(BloomFilter {
+        bit_array,
+        hash_count: k as usize,
+        compress_mode: CompressMode::None,
+        hash_scheme: HashScheme::BitsAndBlooms,
+        layout: Layout::Standard,
+    })
+}
+
+// Filter readable by Go's `ReadFrom`
+pub fn to_bytes(bloom_filter: &BloomFilter) -> Result<Vec<u8>> {
+    if bloom_filter.hash_scheme != HashScheme::BitsAndBlooms {
+        let reason = format!(
+            "{:?} hashing, bits-and-blooms filters use bits-and-blooms hashing",
+            bloom_filter.hash_scheme
+        );
+        return Err(BloomFilterError::Unsupported(reason).into());
+    }
+    let bit_array = &bloom_filter.bit_array;
+    if bit_array.size == 0 || bit_array.byte_array.len() != bit_array.size.div_ceil(8) {
+        let reason = format!(
+            "{} bits in {} bytes",
+            bit_array.size,
+            bit_array.byte_array.len()
+        );
+        return Err(BloomFilterError::Unsupported(reason).into());
+    }
+
+    let mut words = bit_array.byte_array.clone();
+    words.resize(bit_array.size.div_ceil(64) * 8, 0);
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + words.len());
+    bytes.extend_from_slice(&(bit_array.size as u64).to_be_bytes());
+    bytes.extend_from_slice(&(bloom_filter.hash_count as u64).to_be_bytes());
+    bytes.extend_from_slice(&(bit_array.size as u64).to_be_bytes());
+    bytes.extend_from_slice(&convert_words(&words));
+    Ok(bytes)
+}
+
+// Between big endian words numbered from their least significant bit and
+// bytes numbered from their most significant one, both ways
+fn convert_words(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .chunks_exact(8)
+        .flat_map(|word| {
+            u64::from_be_bytes(word.try_into().unwrap())
+                .reverse_bits()
+                .to_be_bytes()
+        })
+        .collect()
+}
+
+// `location(h, i)`: four 64 bit hashes, murmur3 128 of the key and of the
+// key followed by a 1 byte, combined as h[i % 2] + i * h[2 + ((i + i % 2) % 4) / 2]
+pub(crate) fn hashing(bytes: &[u8], hash_count: usize, bit_count: usize) -> Vec<usize> {
+    let (h0, h1) = murmur3_x64_128(bytes, 0);
+    let (h2, h3) = murmur3_x64_128(&[bytes, &[1]].concat(), 0);
+    let h = [h0, h1, h2, h3];
+
+    (0..hash_count as u64)
+        .map(|i| {
+            let step = h[2 + ((i + i % 2) % 4) as usize / 2];
+            let location = h[i as usize % 2].wrapping_add(i.wrapping_mul(step));
+            (location % bit_count as u64) as usize
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    // tests/data/bits_and_blooms/golden.go: key-0..key-{n - 1}, "" and
+    // "été ☃" added, along with the probes Test reports
+    const GOLDEN_1000: &[u8] = include_bytes!("../tests/data/bits_and_blooms/bloom_1000_0.01.bin");
+    const GOLDEN_1000_PRESENT: &str =
+        include_str!("../tests/data/bits_and_blooms/bloom_1000_0.01.txt");
+    const GOLDEN_100: &[u8] = include_bytes!("../tests/data/bits_and_blooms/bloom_100_0.001.bin");
+    const GOLDEN_100_PRESENT: &str =
+        include_str!("../tests/data/bits_and_blooms/bloom_100_0.001.txt");
+    const GOLDEN_200: &[u8] = include_bytes!("../tests/data/bits_and_blooms/bloom_200_3.bin");
+    const GOLDEN_200_PRESENT: &str = include_str!("../tests/data/bits_and_blooms/bloom_200_3.txt");
+
+    fn golden_filter(mut bloom_filter: BloomFilter, inserted: usize) -> BloomFilter {
+        for i in 0..inserted {
+            bloom_filter.insert(&format!("key-{}", i));
+        }
+        bloom_filter.insert("");
+        bloom_filter.insert("été ☃");
+        bloom_filter
+    }
+
+    fn check_lookups(bloom_filter: &BloomFilter, inserted: usize, present: &str) {
+        let present: HashSet<usize> = present.lines().map(|i| i.parse().unwrap()).collect();
+        for i in 0..inserted + 10_000 {
+            let key = format!("key-{}", i);
+            assert_eq!(
+                bloom_filter.lookup(&key),
+                i < inserted || present.contains(&i),
+                "{}",
+                key
+            );
+        }
+        assert!(bloom_filter.lookup(""));
+        assert!(bloom_filter.lookup("été ☃"));
+    }
+
+    #[test]
+    fn test_create() {
+        let bloom_filter = create(1000, 0.01).unwrap();
+        assert_eq!(bloom_filter.bit_array.size, 9586);
+        assert_eq!(bloom_filter.hash_count, 7);
+        let bloom_filter = new(0, 0);
+        assert_eq!(bloom_filter.bit_array.size, 1);
+        assert_eq!(bloom_filter.hash_count, 1);
+        assert!(create(100, 0.0).is_err());
+        assert!(create(100, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_read_golden() {
+        let bloom_filter = from_bytes(GOLDEN_1000).unwrap();
+        assert_eq!(bloom_filter.hash_scheme, HashScheme::BitsAndBlooms);
+        assert_eq!(bloom_filter.bit_array.size, 9586);
+        assert_eq!(bloom_filter.hash_count, 7);
+        check_lookups(&bloom_filter, 1000, GOLDEN_1000_PRESENT);
+
+        let bloom_filter = from_bytes(GOLDEN_100).unwrap();
+        assert_eq!(bloom_filter.bit_array.size, 1438);
+        assert_eq!(bloom_filter.hash_count, 10);
+        check_lookups(&bloom_filter, 100, GOLDEN_100_PRESENT);
+
+        let bloom_filter = from_bytes(GOLDEN_200).unwrap();
+        check_lookups(&bloom_filter, 20, GOLDEN_200_PRESENT);
+    }
+
+    #[test]
+    fn test_write_golden() {
+        let bloom_filter = golden_filter(create(1000, 0.01).unwrap(), 1000);
+        assert_eq!(to_bytes(&bloom_filter).unwrap(), GOLDEN_1000);
+        let bloom_filter = golden_filter(create(100, 0.001).unwrap(), 100);
+        assert_eq!(to_bytes(&bloom_filter).unwrap(), GOLDEN_100);
+        let bloom_filter = golden_filter(new(200, 3), 20);
+        assert_eq!(to_bytes(&bloom_filter).unwrap(), GOLDEN_200);
+    }
+
+    #[test]
+    fn test_crate_file_format() {
+        let bloom_filter = from_bytes(GOLDEN_100).unwrap();
+        let loaded = BloomFilter::from_bytes(&bloom_filter.to_bytes()).unwrap();
+        assert_eq!(loaded, bloom_filter);
+        assert_eq!(to_bytes(&loaded).unwrap(), GOLDEN_100);
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        assert!(from_bytes(&GOLDEN_200[..GOLDEN_200.len() - 1]).is_err());
+        assert!(from_bytes(&[GOLDEN_200, &[0]].concat()).is_err());
+        assert!(from_bytes(&GOLDEN_200[..HEADER_SIZE - 1]).is_err());
+
+        let mut length_mismatch = GOLDEN_200.to_vec();
+        length_mismatch[23] = 199;
+        assert!(from_bytes(&length_mismatch).is_err());
+        let mut no_hashes = GOLDEN_200.to_vec();
+        no_hashes[15] = 0;
+        assert!(from_bytes(&no_hashes).is_err());
+        // Bit 200, past m, in the big endian last word
+        let mut past_m = GOLDEN_200.to_vec();
+        past_m[HEADER_SIZE + 3 * 8 + 6] |= 1;
+        assert!(from_bytes(&past_m).is_err());
+    }
+
+    #[test]
+    fn test_write_mismatch() {
+        let bloom_filter = BloomFilter::new(100);
+        assert_eq!(
+            to_bytes(&bloom_filter).unwrap_err().to_string(),
+            "Unsupported filter: Murmur3Fnv hashing, bits-and-blooms filters use bits-and-blooms hashing"
+        );
+    }
+}
@@ -1,17 +1,26 @@
 mod bit_array;
 mod builder;
+mod similarity;
 
 use crate::decoder::Decodable;
 use crate::encoder::Encodable;
+use crate::error::BloomFilterError;
+use crate::format::{self, FileFormat, FilterKind};
 use crate::hash::{fnv, murmur3};
+use crate::params::Params;
+use crate::{bip37, bits_and_blooms, guava, redisbloom};
 use anyhow::Result;
 pub use bit_array::BitArray;
 pub use builder::BloomFilterBuilder;
+pub use similarity::{Estimate, Similarity};
 use std::fs::File;
 use std::io::prelude::*;
 
 const MURMUR3_SEED: u32 = 0xdead_cafe;
-const FALSE_POSITIVE_RATE: f32 = 0.01;
+pub(crate) const FALSE_POSITIVE_RATE: f64 = 0.01;
+// Well past any useful filter, a false positive rate of 2^-1024 at best.
+// Bounds the probes a decoded filter allocates for every lookup.
+const MAX_HASH_COUNT: usize = 1024;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum CompressMode {
@@ -19,61 +28,225 @@ pub enum CompressMode {
     Lzw,
 }
 
-#[derive(Clone)]
+// How keys map to bit indexes. Filters only agree on lookups, and can only
+// be merged, when they use the same scheme.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HashScheme {
+    // murmur3 and fnv double hashing, the crate default
+    Murmur3Fnv,
+    // MurmurHash64A double hashing with RedisBloom's bit order, see `redisbloom`
+    RedisBloom,
+    // Guava's MURMUR128_MITZ_64 strategy and bit order, see `guava`
+    Guava,
+    // BIP37 murmur3 seeding and bit order, see `bip37`
+    Bip37 { tweak: u32 },
+    // Go bits-and-blooms/bloom location hashing, see `bits_and_blooms`
+    BitsAndBlooms,
+}
+
+// Where the k probes of a key may land. Only the crate's own Murmur3Fnv
+// scheme can be partitioned, the interop schemes keep their libraries' bit
+// order.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Layout {
+    // Any probe anywhere in the bit array
+    Standard,
+    // The bit array split into k equal slices, probe i landing in slice i.
+    // ref: Chang, Feng & Li (2004), "Approximate caches for packet
+    // classification", and the slices assumed by Almeida et al. (2007),
+    // "Scalable Bloom Filters"
+    Partitioned,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct BloomFilter {
     pub bit_array: BitArray,
     pub hash_count: usize,
     pub compress_mode: CompressMode,
+    pub hash_scheme: HashScheme,
+    pub layout: Layout,
 }
 
 impl BloomFilter {
     pub fn new(max_items: usize) -> Self {
-        let ln_rate = FALSE_POSITIVE_RATE.ln();
-        let ln_2 = 2_f32.ln();
+        Self::with_false_positive_rate(max_items, FALSE_POSITIVE_RATE)
+    }
 
-        let size = (-(max_items as f32) * ln_rate / ln_2.powi(2)).ceil() as usize;
-        let hash_count = (-ln_rate / ln_2).ceil() as usize;
+    // Out of range parameters are clamped, see `Params::clamped`
+    pub fn with_false_positive_rate(max_items: usize, false_positive_rate: f64) -> Self {
+        Self::from_params(&Params::clamped(max_items, false_positive_rate))
+    }
 
+    pub fn from_params(params: &Params) -> Self {
         Self {
-            bit_array: BitArray::new(size),
-            hash_count,
+            bit_array: BitArray::new(params.bits),
+            hash_count: params.hash_count,
             compress_mode: CompressMode::None,
+            hash_scheme: HashScheme::Murmur3Fnv,
+            layout: Layout::Standard,
         }
     }
 
-    pub fn lookup(&self, key: &str) -> bool {
-        self.hashing(key).iter().all(|&i| self.bit_array.get_bit(i))
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        self.hashing(key.as_ref())
+            .iter()
+            .all(|&i| self.bit_array.get_bit(i))
     }
 
-    pub fn insert(&mut self, key: &str) -> bool {
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> bool {
         if self.lookup(key) {
             false
         } else {
-            for i in self.hashing(key) {
+            for i in self.hashing(key.as_ref()) {
                 self.bit_array.set(i, true)
             }
             true
         }
     }
 
-    // double-hashing
-    fn hashing(&self, key: &str) -> Vec<usize> {
-        let bitsize = self.bit_array.size;
-        let bytes: &[u8] = key.as_bytes();
-        let h1 = (murmur3(bytes, MURMUR3_SEED) as usize) % bitsize;
-        let h2 = (fnv(bytes) as usize) % bitsize;
-        let mut hash_table = vec![0; self.hash_count];
+    pub fn fill_ratio(&self) -> f64 {
+        self.bit_array.count_ones() as f64 / self.bit_array.size as f64
+    }
+
+    // Bitwise OR of both filters, other must use the same size and hashing
+    pub fn union(&mut self, other: &Self) -> Result<()> {
+        self.check_compatible(other)?;
+
+        for (byte, other_byte) in self
+            .bit_array
+            .byte_array
+            .iter_mut()
+            .zip(&other.bit_array.byte_array)
+        {
+            *byte |= other_byte;
+        }
+        Ok(())
+    }
+
+    // ref: Swamidass & Baldi (2007), n* = -(m / k) * ln(1 - X / m)
+    pub fn estimated_count(&self) -> f64 {
+        estimate_count(
+            self.bit_array.size,
+            self.hash_count,
+            self.bit_array.count_ones(),
+        )
+    }
 
-        for (idx, hash_val) in hash_table.iter_mut().enumerate() {
-            *hash_val = (h1 + idx.wrapping_mul(h2)) % bitsize
+    // Same size, hash count, hash scheme and layout, so a key sets the same
+    // bits in both
+    fn check_compatible(&self, other: &Self) -> Result<()> {
+        if self.bit_array.size != other.bit_array.size {
+            let reason = format!(
+                "size {} bits != {} bits",
+                self.bit_array.size, other.bit_array.size
+            );
+            return Err(BloomFilterError::Incompatible(reason).into());
+        }
+        if self.hash_count != other.hash_count {
+            let reason = format!("hash count {} != {}", self.hash_count, other.hash_count);
+            return Err(BloomFilterError::Incompatible(reason).into());
         }
+        if self.hash_scheme != other.hash_scheme {
+            let reason = format!(
+                "hash scheme {:?} != {:?}",
+                self.hash_scheme, other.hash_scheme
+            );
+            return Err(BloomFilterError::Incompatible(reason).into());
+        }
+        if self.layout != other.layout {
+            let reason = format!("layout {:?} != {:?}", self.layout, other.layout);
+            return Err(BloomFilterError::Incompatible(reason).into());
+        }
+        Ok(())
+    }
 
-        hash_table
+    fn hashing(&self, bytes: &[u8]) -> Vec<usize> {
+        match self.hash_scheme {
+            HashScheme::Murmur3Fnv => match self.layout {
+                Layout::Standard => {
+                    murmur3_fnv_hashing(bytes, self.hash_count, self.bit_array.size)
+                }
+                Layout::Partitioned => self.partitioned_hashing(bytes),
+            },
+            HashScheme::RedisBloom => {
+                redisbloom::hashing(bytes, self.hash_count, self.bit_array.size)
+            }
+            HashScheme::Guava => guava::hashing(bytes, self.hash_count, self.bit_array.size),
+            HashScheme::Bip37 { tweak } => {
+                bip37::hashing(bytes, self.hash_count, self.bit_array.size, tweak)
+            }
+            HashScheme::BitsAndBlooms => {
+                bits_and_blooms::hashing(bytes, self.hash_count, self.bit_array.size)
+            }
+        }
+    }
+
+    // Same double hashing within slices of size / k bits, the bits past the
+    // last whole slice are never set
+    fn partitioned_hashing(&self, bytes: &[u8]) -> Vec<usize> {
+        let slice = self.bit_array.size / self.hash_count;
+        let h1 = (murmur3(bytes, MURMUR3_SEED) as usize) % slice;
+        let h2 = (fnv(bytes) as usize) % slice;
+
+        (0..self.hash_count)
+            .map(|idx| idx * slice + (h1 + idx.wrapping_mul(h2)) % slice)
+            .collect()
+    }
+
+    // File format: versioned header, then the filter encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        format::write_header(&mut bytes, FilterKind::Bloom);
+        bytes.extend_from_slice(&self.encode());
+        bytes
+    }
+
+    // Accepts current, older and legacy (headerless) files. Older files
+    // lack fields the encoding has since gained: V1 and legacy files always
+    // use the default hash scheme, files before V4 the standard layout.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (format, payload) = format::strip_header_for(bytes, FilterKind::Bloom)?;
+        let defaults = |hash_scheme: &[u8], rest: &[u8]| {
+            [hash_scheme, &Layout::Standard.encode(), rest].concat()
+        };
+        match format {
+            FileFormat::Legacy | FileFormat::V1 => {
+                Self::decode(&defaults(&HashScheme::Murmur3Fnv.encode(), payload))
+            }
+            FileFormat::V2 | FileFormat::V3 => {
+                let scheme_len = HashScheme::decode(payload)?.encode().len();
+                Self::decode(&defaults(&payload[..scheme_len], &payload[scheme_len..]))
+            }
+            FileFormat::V4 => Self::decode(payload),
+        }
+    }
+
+    // Checks a decoded filter can answer lookups: bits to probe, a bit
+    // array holding all of them, and a sane number of probes
+    pub(crate) fn validate(&self) -> Result<()> {
+        let size = self.bit_array.size;
+        if size == 0 || size.div_ceil(8) > self.bit_array.byte_array.len() {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        if self.hash_count == 0 || self.hash_count > MAX_HASH_COUNT {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        if self.layout == Layout::Standard {
+            return Ok(());
+        }
+        if self.hash_scheme != HashScheme::Murmur3Fnv {
+            let reason = format!("{:?} hash scheme can't be partitioned", self.hash_scheme);
+            return Err(BloomFilterError::Unsupported(reason).into());
+        }
+        if size < self.hash_count {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(())
     }
 
     pub fn to_file(&self, path: &str) {
         let mut file = File::create(path).unwrap();
-        file.write_all(&self.encode()).unwrap();
+        file.write_all(&self.to_bytes()).unwrap();
     }
 
     pub fn from_file(path: &str) -> Result<Self> {
@@ -81,24 +254,37 @@ impl BloomFilter {
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
 
-        Self::decode(&buffer)
+        Self::from_bytes(&buffer)
     }
 }
 
+// Swamidass & Baldi's estimate from the number of set bits
+fn estimate_count(size: usize, hash_count: usize, ones: usize) -> f64 {
+    let size = size as f64;
+    // ln(m / (m - X)) rather than -ln(1 - X / m), which yields -0.0
+    size / hash_count as f64 * (size / (size - ones as f64)).ln()
+}
+
+// double-hashing, shared with `StableBloomFilter` and `CountMinSketch`
+pub(crate) fn murmur3_fnv_hashing(bytes: &[u8], hash_count: usize, bitsize: usize) -> Vec<usize> {
+    let h1 = (murmur3(bytes, MURMUR3_SEED) as usize) % bitsize;
+    let h2 = (fnv(bytes) as usize) % bitsize;
+    let mut hash_table = vec![0; hash_count];
+
+    for (idx, hash_val) in hash_table.iter_mut().enumerate() {
+        *hash_val = (h1 + idx.wrapping_mul(h2)) % bitsize
+    }
+
+    hash_table
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::prepare_tmp_dir;
     use std::fs;
     use std::path::Path;
 
-    fn prepare_tmp_dir() {
-        let tmp_dir = Path::new("tmp");
-
-        if !tmp_dir.exists() {
-            fs::create_dir(tmp_dir).unwrap();
-        }
-    }
-
     #[test]
     fn test_bloom_filter() {
         let mut bloom_filter = BloomFilterBuilder::new(100).build();
@@ -115,6 +301,18 @@ mod tests {
         assert!(!bloom_filter.lookup("dnuoba"));
     }
 
+    #[test]
+    fn test_bytes_keys() {
+        let mut bloom_filter = BloomFilterBuilder::new(100).build();
+        bloom_filter.insert(&[0xff, 0x00, 0xfe]);
+        bloom_filter.insert(&String::from("owned"));
+
+        assert!(bloom_filter.lookup(&[0xff, 0x00, 0xfe]));
+        assert!(bloom_filter.lookup(b"owned"));
+        assert!(bloom_filter.lookup("owned"));
+        assert!(!bloom_filter.lookup(&[0xff, 0x00]));
+    }
+
     #[test]
     fn test_bloom_filter_spec() {
         let bloom_filter = BloomFilterBuilder::new(2).build();
@@ -124,6 +322,161 @@ mod tests {
         assert_eq!(bloom_filter.compress_mode, CompressMode::Lzw);
     }
 
+    #[test]
+    fn test_out_of_range_params_clamped() {
+        let bloom_filter = BloomFilter::with_false_positive_rate(100, 1.5);
+        assert_eq!(bloom_filter.hash_count, 1);
+        let bloom_filter = BloomFilter::new(0);
+        assert_eq!(bloom_filter.bit_array.size, 10);
+        assert!(!bloom_filter.lookup("key"));
+    }
+
+    #[test]
+    fn test_fill_ratio_and_estimated_count() {
+        let mut bloom_filter = BloomFilterBuilder::new(1_000).build();
+        assert_eq!(bloom_filter.fill_ratio(), 0.0);
+        assert_eq!(bloom_filter.estimated_count(), 0.0);
+
+        for i in 0..500 {
+            bloom_filter.insert(&i.to_string());
+        }
+        assert!(bloom_filter.fill_ratio() > 0.25 && bloom_filter.fill_ratio() < 0.5);
+        assert!((bloom_filter.estimated_count() - 500.0).abs() < 25.0);
+    }
+
+    #[test]
+    fn test_union() {
+        let mut bloom_filter = BloomFilterBuilder::new(100).build();
+        let mut other = BloomFilterBuilder::new(100).no_compress().build();
+        bloom_filter.insert("left");
+        other.insert("right");
+
+        bloom_filter.union(&other).unwrap();
+        assert!(bloom_filter.lookup("left"));
+        assert!(bloom_filter.lookup("right"));
+        assert!(!bloom_filter.lookup("middle"));
+        assert_eq!(bloom_filter.compress_mode, CompressMode::Lzw);
+
+        let smaller = BloomFilterBuilder::new(10).build();
+        assert!(bloom_filter.union(&smaller).is_err());
+        let stricter = BloomFilterBuilder::new(100)
+            .false_positive_rate(0.001)
+            .build();
+        assert!(bloom_filter.union(&stricter).is_err());
+        let mut redisbloom = BloomFilterBuilder::new(100).build();
+        redisbloom.hash_scheme = HashScheme::RedisBloom;
+        assert!(bloom_filter.union(&redisbloom).is_err());
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        let mut bloom_filter = BloomFilterBuilder::new(10).build();
+        bloom_filter.insert("test");
+
+        let bytes = bloom_filter.to_bytes();
+        assert_eq!(&bytes[..8], b"BFYS\x04\x00\x00\x00");
+        assert_eq!(&bytes[6..], bloom_filter.encode());
+        assert_eq!(BloomFilter::from_bytes(&bytes).unwrap(), bloom_filter);
+
+        // V3 files lack the layout, V2 files the filter kind too, V1 files
+        // the hash scheme too, legacy files hold the bare rest
+        let body = &bloom_filter.encode()[2..];
+        let mut v3 = b"BFYS\x03\x00\x00".to_vec();
+        v3.extend_from_slice(body);
+        assert_eq!(BloomFilter::from_bytes(&v3).unwrap(), bloom_filter);
+        let mut v2 = b"BFYS\x02".to_vec();
+        v2.extend_from_slice(&v3[6..]);
+        assert_eq!(BloomFilter::from_bytes(&v2).unwrap(), bloom_filter);
+        let mut v1 = b"BFYS\x01".to_vec();
+        v1.extend_from_slice(body);
+        assert_eq!(BloomFilter::from_bytes(&v1).unwrap(), bloom_filter);
+        assert_eq!(BloomFilter::from_bytes(body).unwrap(), bloom_filter);
+
+        bloom_filter.hash_scheme = HashScheme::RedisBloom;
+        let bytes = bloom_filter.to_bytes();
+        assert_eq!(bytes[6], 1);
+        assert_eq!(BloomFilter::from_bytes(&bytes).unwrap(), bloom_filter);
+
+        bloom_filter.hash_scheme = HashScheme::Bip37 { tweak: 0x01020304 };
+        let bytes = bloom_filter.to_bytes();
+        assert_eq!(&bytes[6..11], [3, 4, 3, 2, 1]);
+        assert_eq!(BloomFilter::from_bytes(&bytes).unwrap(), bloom_filter);
+        assert!(BloomFilter::from_bytes(&bytes[..9]).is_err());
+
+        bloom_filter.hash_scheme = HashScheme::BitsAndBlooms;
+        let bytes = bloom_filter.to_bytes();
+        assert_eq!(bytes[6], 4);
+        assert_eq!(BloomFilter::from_bytes(&bytes).unwrap(), bloom_filter);
+
+        let mut unknown_scheme = bytes.clone();
+        unknown_scheme[6] = 0xff;
+        assert!(BloomFilter::from_bytes(&unknown_scheme).is_err());
+        let mut other_kind = bytes.clone();
+        other_kind[5] = 1;
+        assert!(BloomFilter::from_bytes(&other_kind).is_err());
+        assert!(BloomFilter::from_bytes(b"BFYS\x02").is_err());
+        assert!(BloomFilter::from_bytes(b"BFYS\x03\x00").is_err());
+        assert!(BloomFilter::from_bytes(b"BFYS\x04\x00\x00").is_err());
+        assert!(BloomFilter::from_bytes(b"BFYS\x05").is_err());
+        assert!(BloomFilter::from_bytes(b"").is_err());
+    }
+
+    #[test]
+    fn test_partitioned() {
+        let mut bloom_filter = BloomFilterBuilder::new(1_000).partitioned().build();
+        let slice = bloom_filter.bit_array.size / bloom_filter.hash_count;
+        assert_eq!(bloom_filter.bit_array.size % bloom_filter.hash_count, 0);
+
+        // One probe per slice, in slice order
+        let probes = bloom_filter.hashing(b"key");
+        for (idx, probe) in probes.iter().enumerate() {
+            assert_eq!(probe / slice, idx);
+        }
+
+        for i in 0..1_000 {
+            bloom_filter.insert(&i.to_string());
+        }
+        assert!((0..1_000).all(|i| bloom_filter.lookup(&i.to_string())));
+        let false_positives = (1_000..101_000)
+            .filter(|i| bloom_filter.lookup(&i.to_string()))
+            .count();
+        assert!(
+            (500..1_500).contains(&false_positives),
+            "{}",
+            false_positives
+        );
+        assert!((bloom_filter.estimated_count() - 1_000.0).abs() < 50.0);
+
+        let mut standard = BloomFilterBuilder::new(1_000).build();
+        standard.bit_array = BitArray::new(bloom_filter.bit_array.size);
+        assert!(standard.union(&bloom_filter).is_err());
+        let mut other = BloomFilterBuilder::new(1_000).partitioned().build();
+        other.insert("other");
+        other.union(&bloom_filter).unwrap();
+        assert!(other.lookup("other") && other.lookup("1"));
+    }
+
+    #[test]
+    fn test_partitioned_bytes_format() {
+        let mut bloom_filter = BloomFilterBuilder::new(10).partitioned().build();
+        bloom_filter.insert("test");
+
+        let bytes = bloom_filter.to_bytes();
+        assert_eq!(&bytes[..8], b"BFYS\x04\x00\x00\x01");
+        assert_eq!(BloomFilter::from_bytes(&bytes).unwrap(), bloom_filter);
+
+        let mut unknown_layout = bytes.clone();
+        unknown_layout[7] = 2;
+        assert!(BloomFilter::from_bytes(&unknown_layout).is_err());
+        bloom_filter.hash_scheme = HashScheme::Guava;
+        assert_eq!(
+            BloomFilter::from_bytes(&bloom_filter.to_bytes())
+                .unwrap_err()
+                .to_string(),
+            "Unsupported filter: Guava hash scheme can't be partitioned"
+        );
+    }
+
     #[test]
     fn test_persist_local_file() {
         prepare_tmp_dir();
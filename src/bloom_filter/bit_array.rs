@@ -1,6 +1,6 @@
 use std::ops::Index;
 
-#[derive(Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct BitArray {
     pub byte_array: Vec<u8>,
     pub size: usize,
@@ -8,7 +8,7 @@ pub struct BitArray {
 
 impl BitArray {
     pub fn new(size: usize) -> Self {
-        let num_bytes = (size + 7) / 8;
+        let num_bytes = size.div_ceil(8);
         Self {
             byte_array: vec![0; num_bytes],
             size,
@@ -30,6 +30,13 @@ impl BitArray {
         self[index]
     }
 
+    pub fn count_ones(&self) -> usize {
+        self.byte_array
+            .iter()
+            .map(|b| b.count_ones() as usize)
+            .sum()
+    }
+
     pub fn get_byte_position(&self, bit_index: usize) -> (usize, usize) {
         if bit_index >= self.size {
             panic!("Index out of bounds: must less than {}", self.size);
@@ -89,6 +96,16 @@ mod tests {
         assert!(!bit_array[5]);
     }
 
+    #[test]
+    fn test_count_ones() {
+        let mut bit_array = BitArray::new(20);
+        assert_eq!(bit_array.count_ones(), 0);
+        bit_array.set(0, true);
+        bit_array.set(9, true);
+        bit_array.set(19, true);
+        assert_eq!(bit_array.count_ones(), 3);
+    }
+
     #[test]
     fn test_out_of_bound_set_panic() {
         let result = std::panic::catch_unwind(|| {
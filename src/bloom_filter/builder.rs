@@ -1,25 +1,55 @@
-use super::{BloomFilter, CompressMode};
+use super::{BitArray, BloomFilter, CompressMode, Layout, FALSE_POSITIVE_RATE};
+use crate::params::Params;
 use anyhow::Result;
 
 pub struct BloomFilterBuilder {
-    bloom_filter: BloomFilter,
+    capacity: usize,
+    false_positive_rate: f64,
+    compress_mode: CompressMode,
+    layout: Layout,
 }
 
 impl BloomFilterBuilder {
     pub fn new(capacity: usize) -> Self {
-        let mut bloom_filter = BloomFilter::new(capacity);
-        bloom_filter.compress_mode = CompressMode::Lzw;
+        Self {
+            capacity,
+            false_positive_rate: FALSE_POSITIVE_RATE,
+            compress_mode: CompressMode::Lzw,
+            layout: Layout::Standard,
+        }
+    }
 
-        Self { bloom_filter }
+    pub fn false_positive_rate(mut self, false_positive_rate: f64) -> Self {
+        self.false_positive_rate = false_positive_rate;
+        self
     }
 
     pub fn no_compress(mut self) -> Self {
-        self.bloom_filter.compress_mode = CompressMode::None;
+        self.compress_mode = CompressMode::None;
+        self
+    }
+
+    pub fn partitioned(mut self) -> Self {
+        self.layout = Layout::Partitioned;
         self
     }
 
+    pub fn params(&self) -> Result<Params> {
+        Params::optimal(self.capacity, self.false_positive_rate)
+    }
+
+    // Out of range parameters are clamped, check them first with `params`
     pub fn build(self) -> BloomFilter {
-        self.bloom_filter
+        let params = Params::clamped(self.capacity, self.false_positive_rate);
+        let mut bloom_filter = BloomFilter::from_params(&params);
+        bloom_filter.compress_mode = self.compress_mode;
+        if self.layout == Layout::Partitioned {
+            // Whole slices, so no bit is left out
+            let bits = params.bits.next_multiple_of(params.hash_count);
+            bloom_filter.bit_array = BitArray::new(bits);
+            bloom_filter.layout = Layout::Partitioned;
+        }
+        bloom_filter
     }
 
     pub fn load(path: &str) -> Result<BloomFilter> {
@@ -42,4 +72,48 @@ mod tests {
         let bloom_filter = BloomFilterBuilder::new(100).no_compress().build();
         assert_eq!(bloom_filter.compress_mode, CompressMode::None);
     }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let default = BloomFilterBuilder::new(100).build();
+        let bloom_filter = BloomFilterBuilder::new(100)
+            .false_positive_rate(0.001)
+            .build();
+        assert!(bloom_filter.bit_array.size > default.bit_array.size);
+        assert_eq!(bloom_filter.hash_count, 10);
+    }
+
+    #[test]
+    fn test_partitioned() {
+        let bloom_filter = BloomFilterBuilder::new(2).partitioned().build();
+        assert_eq!(bloom_filter.layout, Layout::Partitioned);
+        assert_eq!(bloom_filter.bit_array.size, 21);
+        assert_eq!(bloom_filter.hash_count, 7);
+        let default = BloomFilterBuilder::new(2).build();
+        assert_eq!(default.layout, Layout::Standard);
+    }
+
+    #[test]
+    fn test_params() {
+        let params = BloomFilterBuilder::new(2).params().unwrap();
+        assert_eq!(params.bits, 20);
+        assert_eq!(params.hash_count, 7);
+        assert!(BloomFilterBuilder::new(0).params().is_err());
+        assert!(BloomFilterBuilder::new(2)
+            .false_positive_rate(0.0)
+            .params()
+            .is_err());
+    }
+
+    #[test]
+    fn test_clamped() {
+        let bloom_filter = BloomFilterBuilder::new(0).build();
+        assert_eq!(bloom_filter.bit_array.size, 10);
+        let bloom_filter = BloomFilterBuilder::new(2)
+            .false_positive_rate(0.0)
+            .partitioned()
+            .build();
+        assert_eq!(bloom_filter.hash_count, 50);
+        assert_eq!(bloom_filter.bit_array.size % 50, 0);
+    }
 }
@@ -0,0 +1,181 @@
+use super::{estimate_count, BloomFilter};
+use crate::error::BloomFilterError;
+use anyhow::Result;
+
+// Normal quantile of the two-sided 95% bounds
+const Z_95: f64 = 1.96;
+
+// Estimated value with its lower and upper bounds
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Estimate {
+    pub value: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+// Estimates about the key sets of two filters, from their set bits
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Similarity {
+    pub left: Estimate,
+    pub right: Estimate,
+    pub union: Estimate,
+    pub intersection: Estimate,
+    pub jaccard: Estimate,
+}
+
+impl BloomFilter {
+    // ref: Swamidass & Baldi (2007), "Mathematical correction for
+    // fingerprint similarity measures to improve chemical retrieval"
+    //
+    // The union's count is estimated from the bits set in either filter,
+    // the intersection as |A| + |B| - |A ∪ B| and Jaccard as their ratio.
+    // Each count is bounded at 95% from the binomial spread of its zero
+    // bits. The intersection and Jaccard bounds combine the count bounds
+    // at their extremes, so they hold at least as often but run wide when
+    // the intersection is small next to the union.
+    pub fn similarity(&self, other: &Self) -> Result<Similarity> {
+        let mut union = self.clone();
+        union.union(other)?;
+        let union_ones = union.bit_array.count_ones();
+        if union_ones >= self.bit_array.size {
+            let reason = "every bit of the union is set".to_string();
+            return Err(BloomFilterError::Unsupported(reason).into());
+        }
+
+        let left = self.bounded_count(self.bit_array.count_ones());
+        let right = self.bounded_count(other.bit_array.count_ones());
+        let union = self.bounded_count(union_ones);
+
+        let intersection =
+            |left: f64, right: f64, union: f64| (left + right - union).clamp(0.0, left.min(right));
+        let intersection = Estimate {
+            value: intersection(left.value, right.value, union.value),
+            low: intersection(left.low, right.low, union.high),
+            high: intersection(left.high, right.high, union.low),
+        };
+        let ratio = |intersection: f64, union: f64| {
+            if intersection == 0.0 {
+                0.0
+            } else {
+                (intersection / union).min(1.0)
+            }
+        };
+        let jaccard = Estimate {
+            value: ratio(intersection.value, union.value),
+            low: ratio(intersection.low, union.high),
+            high: ratio(intersection.high, union.low),
+        };
+
+        Ok(Similarity {
+            left,
+            right,
+            union,
+            intersection,
+            jaccard,
+        })
+    }
+
+    // Count for `ones` set bits, bounded by the normal approximation of
+    // the number of zero bits
+    fn bounded_count(&self, ones: usize) -> Estimate {
+        let size = self.bit_array.size as f64;
+        let zeros = size - ones as f64;
+        let spread = Z_95 * (zeros * (size - zeros) / size).sqrt();
+        let count = |zeros: f64| {
+            let ones = (size - zeros.clamp(0.0, size)).round() as usize;
+            estimate_count(self.bit_array.size, self.hash_count, ones)
+        };
+        Estimate {
+            value: count(zeros),
+            low: count(zeros + spread),
+            high: count(zeros - spread),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BloomFilterBuilder, Layout};
+
+    fn filter(range: std::ops::Range<usize>) -> BloomFilter {
+        let mut bloom_filter = BloomFilterBuilder::new(20_000).build();
+        for i in range {
+            bloom_filter.insert(&format!("key-{}", i));
+        }
+        bloom_filter
+    }
+
+    fn assert_within(estimate: Estimate, expected: f64) {
+        assert!(
+            estimate.low <= estimate.value && estimate.value <= estimate.high,
+            "{:?}",
+            estimate
+        );
+        assert!(
+            estimate.low <= expected && expected <= estimate.high,
+            "{:?} for {}",
+            estimate,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_similarity() {
+        // |A| = |B| = 10_000 with overlaps from none to all
+        for shared in [0, 1000, 5000, 9000, 10_000] {
+            let left = filter(0..10_000);
+            let right = filter(10_000 - shared..20_000 - shared);
+            let similarity = left.similarity(&right).unwrap();
+            let union = 20_000 - shared;
+            assert_within(similarity.left, 10_000.0);
+            assert_within(similarity.right, 10_000.0);
+            assert_within(similarity.union, union as f64);
+            assert_within(similarity.intersection, shared as f64);
+            assert_within(similarity.jaccard, shared as f64 / union as f64);
+            assert!((similarity.jaccard.value - shared as f64 / union as f64).abs() < 0.02);
+            assert_eq!(right.similarity(&left).unwrap().jaccard, similarity.jaccard);
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let empty = filter(0..0);
+        let similarity = empty.similarity(&empty).unwrap();
+        let zero = Estimate {
+            value: 0.0,
+            low: 0.0,
+            high: 0.0,
+        };
+        assert_eq!(similarity.union, zero);
+        assert_eq!(similarity.jaccard, zero);
+    }
+
+    #[test]
+    fn test_loaded() {
+        let left = filter(0..3000);
+        let right = filter(1000..4000);
+        let loaded = BloomFilter::from_bytes(&right.to_bytes()).unwrap();
+        assert_eq!(
+            left.similarity(&loaded).unwrap(),
+            left.similarity(&right).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_incompatible() {
+        let left = filter(0..10);
+        let smaller = BloomFilterBuilder::new(100).build();
+        assert_eq!(
+            left.similarity(&smaller).unwrap_err().to_string(),
+            "Incompatible filters: size 191702 bits != 959 bits"
+        );
+        let mut partitioned = left.clone();
+        partitioned.layout = Layout::Partitioned;
+        assert!(left.similarity(&partitioned).is_err());
+
+        let mut full = left.clone();
+        full.bit_array.byte_array.fill(0xff);
+        assert!(left.similarity(&full).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+mod add;
+mod args;
+mod bench;
+mod check;
+mod compare;
+mod convert;
+mod create;
+mod info;
+mod merge;
+mod params;
+
+use anyhow::{bail, Context, Result};
+use args::Args;
+use bloom_filter_yss::{
+    BinaryFuse16Filter, BinaryFuse8Filter, BloomFilter, CompressMode, CountMinSketch, CuckooFilter,
+    FilterKind, Persistable, QuotientFilter, RibbonFilter, RotatingBloomFilter, StableBloomFilter,
+    SystemClock, Xor16Filter, Xor8Filter,
+};
+use std::fs::{self, File};
+use std::io::{self, prelude::*, BufReader};
+use std::process::{self, ExitCode};
+
+const USAGE: &str = "\
+Usage: bloom-filter-yss <command> [options]
+
+Commands:
+  create <filter> --capacity <n> [--fpr <p>] [--compress <lzw|none>] [--partitioned] [--force]
+  add    <filter> [key...] [--input <path|->...]
+  check  <filter> [key...] [--input <path|->...] [--quiet]
+  info   <filter>
+  merge  <output> <input>... [--compress <lzw|none>] [--force]
+  compare <left> <right>
+  convert <input> [output] [--compress <lzw|none>]
+  bench  --capacity <n> [--fpr <p>] [--inserted <n>] [--probes <m>] [--seed <s>]
+  params [--capacity <n>] [--bits <m>] [--hash-count <k>] [--fpr <p>]
+
+Keys are read one per line from --input files, `-` being stdin. Without
+keys or --input, add and check read from stdin.
+
+check accepts any filter kind the crate writes, such as xor and binary
+fuse filters built elsewhere. check exits with 1 when any key is absent,
+every command exits with 2 on error. Filters are written to a temporary
+file, verified by decoding it again, then renamed over the destination.
+
+compare estimates the union, intersection and Jaccard similarity of two
+compatible filters from their set bits, with 95% bounds.
+
+bench measures the false positive rate of a fresh filter on random keys.
+params solves the missing sizing values from the given ones.";
+
+pub fn run<I>(args: I, out: &mut dyn Write) -> Result<ExitCode>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+    let Some(command) = args.next() else {
+        bail!("missing command\n\n{}", USAGE);
+    };
+
+    match command.as_str() {
+        "create" => create::run(args, out),
+        "add" => add::run(args, out),
+        "check" => check::run(args, out),
+        "info" => info::run(args, out),
+        "merge" => merge::run(args, out),
+        "compare" => compare::run(args, out),
+        "convert" => convert::run(args, out),
+        "bench" => bench::run(args, out),
+        "params" => params::run(args, out),
+        "help" | "--help" | "-h" => {
+            writeln!(out, "{}", USAGE)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        _ => bail!("unknown command `{}`\n\n{}", command, USAGE),
+    }
+}
+
+fn filter_path(args: &Args) -> Result<&str> {
+    match args.positionals().first() {
+        Some(path) => Ok(path),
+        None => bail!("missing filter path"),
+    }
+}
+
+fn load(path: &str) -> Result<BloomFilter> {
+    BloomFilter::from_file(path).with_context(|| format!("failed to load {}", path))
+}
+
+type Lookup = Box<dyn Fn(&str) -> bool>;
+
+// Lookup into a filter of any kind, for read only commands
+fn load_lookup(path: &str) -> Result<Lookup> {
+    let load = || -> Result<Lookup> {
+        let bytes = fs::read(path)?;
+        Ok(match FilterKind::detect(&bytes)? {
+            FilterKind::Bloom => {
+                let filter = BloomFilter::from_bytes(&bytes)?;
+                Box::new(move |key| filter.lookup(key))
+            }
+            FilterKind::Cuckoo => {
+                let filter = CuckooFilter::from_bytes(&bytes)?;
+                Box::new(move |key| filter.lookup(key))
+            }
+            FilterKind::Quotient => {
+                let filter = QuotientFilter::from_bytes(&bytes)?;
+                Box::new(move |key| filter.lookup(key))
+            }
+            FilterKind::Xor8 => {
+                let filter = Xor8Filter::from_bytes(&bytes)?;
+                Box::new(move |key| filter.lookup(key))
+            }
+            FilterKind::Xor16 => {
+                let filter = Xor16Filter::from_bytes(&bytes)?;
+                Box::new(move |key| filter.lookup(key))
+            }
+            FilterKind::BinaryFuse8 => {
+                let filter = BinaryFuse8Filter::from_bytes(&bytes)?;
+                Box::new(move |key| filter.lookup(key))
+            }
+            FilterKind::BinaryFuse16 => {
+                let filter = BinaryFuse16Filter::from_bytes(&bytes)?;
+                Box::new(move |key| filter.lookup(key))
+            }
+            FilterKind::Ribbon => {
+                let filter = RibbonFilter::from_bytes(&bytes)?;
+                Box::new(move |key| filter.lookup(key))
+            }
+            FilterKind::Stable => {
+                let filter = StableBloomFilter::from_bytes(&bytes)?;
+                Box::new(move |key| filter.lookup(key))
+            }
+            // Keys counted at least once, with the sketch's overcounting
+            FilterKind::CountMin => {
+                let sketch = CountMinSketch::from_bytes(&bytes)?;
+                Box::new(move |key| sketch.estimate(key) > 0)
+            }
+            FilterKind::Rotating => {
+                let filter = RotatingBloomFilter::<SystemClock>::from_bytes(&bytes)?;
+                Box::new(move |key| filter.lookup(key))
+            }
+            FilterKind::HyperLogLog => {
+                bail!("HyperLogLog sketches count keys without keeping them")
+            }
+            FilterKind::Invertible => {
+                bail!("invertible Bloom lookup tables list keys by peeling, not lookups")
+            }
+        })
+    };
+    load().with_context(|| format!("failed to load {}", path))
+}
+
+fn compress_mode(args: &Args) -> Result<Option<CompressMode>> {
+    match args.value("compress") {
+        None => Ok(None),
+        Some("lzw") => Ok(Some(CompressMode::Lzw)),
+        Some("none") => Ok(Some(CompressMode::None)),
+        Some(mode) => bail!("unknown compression `{}`, expected lzw or none", mode),
+    }
+}
+
+// Atomic write: the temporary file is decoded and compared before the rename
+fn save(path: &str, bloom_filter: &BloomFilter) -> Result<()> {
+    let tmp_path = format!("{}.tmp-{}", path, process::id());
+    let result = write_verified(&tmp_path, bloom_filter)
+        .and_then(|_| fs::rename(&tmp_path, path).map_err(Into::into));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result.with_context(|| format!("failed to write {}", path))
+}
+
+fn write_verified(path: &str, bloom_filter: &BloomFilter) -> Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(&bloom_filter.to_bytes())?;
+    file.sync_all()?;
+
+    if BloomFilter::from_file(path)? != *bloom_filter {
+        bail!("written filter does not decode to the original");
+    }
+    Ok(())
+}
+
+// Feeds keys given after the filter path, then every --input source
+fn for_each_key<F>(args: &Args, mut f: F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<()>,
+{
+    let keys = &args.positionals()[1..];
+    let mut inputs: Vec<&str> = args.values("input").collect();
+    if keys.is_empty() && inputs.is_empty() {
+        inputs.push("-");
+    }
+
+    for key in keys {
+        f(key)?;
+    }
+    for input in inputs {
+        let reader: Box<dyn BufRead> = match input {
+            "-" => Box::new(io::stdin().lock()),
+            path => Box::new(BufReader::new(
+                File::open(path).with_context(|| format!("failed to open {}", path))?,
+            )),
+        };
+        for line in reader.lines() {
+            let line = line.with_context(|| format!("failed to read {}", input))?;
+            if !line.is_empty() {
+                f(&line)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    pub fn tmp_path(name: &str) -> String {
+        let tmp_dir = Path::new("tmp");
+        if !tmp_dir.exists() {
+            fs::create_dir(tmp_dir).unwrap();
+        }
+        format!("tmp/cli_{}", name)
+    }
+
+    pub fn run_cli(args: &[&str]) -> (Result<ExitCode>, String) {
+        let mut out = Vec::new();
+        let result = run(args.iter().map(|arg| arg.to_string()), &mut out);
+        (result, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        assert!(run_cli(&[]).0.is_err());
+        assert!(run_cli(&["frobnicate"]).0.is_err());
+        let (result, out) = run_cli(&["help"]);
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        assert!(out.starts_with("Usage:"));
+    }
+
+    #[test]
+    fn test_create_add_check() {
+        let path = tmp_path("create_add_check.bin");
+        let keys = tmp_path("create_add_check.txt");
+        fs::write(&keys, "from-file-1\n\nfrom-file-2\r\n").unwrap();
+
+        let (result, _) = run_cli(&["create", &path, "--capacity", "100", "--force"]);
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        let (result, out) = run_cli(&["add", &path, "a", "b", "--input", &keys]);
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        assert_eq!(out, "added 4 of 4 keys\n");
+
+        let (result, out) = run_cli(&["check", &path, "a", "from-file-2"]);
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        assert_eq!(out, "present\ta\npresent\tfrom-file-2\n");
+
+        let (result, out) = run_cli(&["check", &path, "a", "c", "--quiet"]);
+        assert_eq!(result.unwrap(), ExitCode::FAILURE);
+        assert_eq!(out, "");
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(keys).unwrap();
+    }
+
+    #[test]
+    fn test_check_static_filters() {
+        let path = tmp_path("check_static_filters.bin");
+        let keys = ["a", "b", "c"];
+        for bytes in [
+            Xor8Filter::new(keys).unwrap().to_bytes(),
+            Xor16Filter::new(keys).unwrap().to_bytes(),
+            BinaryFuse8Filter::new(keys).unwrap().to_bytes(),
+            BinaryFuse16Filter::new(keys).unwrap().to_bytes(),
+            RibbonFilter::new(9, keys).unwrap().to_bytes(),
+        ] {
+            fs::write(&path, bytes).unwrap();
+            let (result, out) = run_cli(&["check", &path, "a", "c"]);
+            assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+            assert_eq!(out, "present\ta\npresent\tc\n");
+        }
+
+        // Other commands still need a Bloom filter
+        let (result, _) = run_cli(&["add", &path, "d"]);
+        assert!(format!("{:#}", result.unwrap_err()).contains("expected Bloom"));
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,28 @@
+use super::args::Args;
+use anyhow::Result;
+use std::io::Write;
+use std::process::ExitCode;
+
+pub fn run<I>(args: I, out: &mut dyn Write) -> Result<ExitCode>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = Args::parse(args, &["input"], &[])?;
+    let path = super::filter_path(&args)?;
+    let mut bloom_filter = super::load(path)?;
+
+    let mut total = 0;
+    let mut added = 0;
+    super::for_each_key(&args, |key| {
+        total += 1;
+        if bloom_filter.insert(key) {
+            added += 1;
+        }
+        Ok(())
+    })?;
+
+    super::save(path, &bloom_filter)?;
+    writeln!(out, "added {} of {} keys", added, total)?;
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,131 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::str::FromStr;
+
+// Minimal GNU-style parser: `--name value`, `--name=value`, flags and
+// positionals. `--` stops option parsing, `-` is kept as a positional.
+pub struct Args {
+    positionals: Vec<String>,
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    pub fn parse<I>(args: I, options: &[&str], flags: &[&str]) -> Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut parsed = Self {
+            positionals: Vec::new(),
+            options: Vec::new(),
+            flags: Vec::new(),
+        };
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.positionals.extend(args.by_ref());
+                break;
+            }
+            let Some(name) = arg.strip_prefix("--") else {
+                parsed.positionals.push(arg);
+                continue;
+            };
+
+            let (name, inline_value) = match name.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (name, None),
+            };
+            if options.contains(&name) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .ok_or_else(|| anyhow!("missing value for --{}", name))?,
+                };
+                parsed.options.push((name.to_string(), value));
+            } else if flags.contains(&name) && inline_value.is_none() {
+                parsed.flags.push(name.to_string());
+            } else {
+                bail!("unknown option --{}", name);
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn positionals(&self) -> &[String] {
+        &self.positionals
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.options
+            .iter()
+            .filter(move |(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn parse_value<T>(&self, name: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        self.value(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .with_context(|| format!("invalid value for --{}: {}", name, value))
+            })
+            .transpose()
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        let args = args.iter().map(|arg| arg.to_string());
+        Args::parse(args, &["capacity", "input"], &["force"])
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = parse(&["a.bin", "--capacity", "10", "--input=-", "--force", "k"]).unwrap();
+        assert_eq!(args.positionals(), ["a.bin", "k"]);
+        assert_eq!(args.value("capacity"), Some("10"));
+        assert_eq!(args.parse_value::<usize>("capacity").unwrap(), Some(10));
+        assert_eq!(args.values("input").collect::<Vec<_>>(), ["-"]);
+        assert!(args.flag("force"));
+        assert!(args.value("missing").is_none());
+    }
+
+    #[test]
+    fn test_parse_end_of_options() {
+        let args = parse(&["a.bin", "--", "--force", "-"]).unwrap();
+        assert_eq!(args.positionals(), ["a.bin", "--force", "-"]);
+        assert!(!args.flag("force"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["--capacity"]).is_err());
+        assert!(parse(&["--force=1"]).is_err());
+        assert!(parse(&["--capacity", "ten"])
+            .unwrap()
+            .parse_value::<usize>("capacity")
+            .is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use super::args::Args;
+use anyhow::{bail, Result};
+use bloom_filter_yss::params::Params;
+use bloom_filter_yss::FalsePositiveTest;
+use std::io::Write;
+use std::process::ExitCode;
+
+pub fn run<I>(args: I, out: &mut dyn Write) -> Result<ExitCode>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = Args::parse(
+        args,
+        &["capacity", "fpr", "inserted", "probes", "seed"],
+        &[],
+    )?;
+    if let Some(arg) = args.positionals().first() {
+        bail!("unexpected argument `{}`", arg);
+    }
+    let Some(capacity) = args.parse_value::<usize>("capacity")? else {
+        bail!("missing --capacity");
+    };
+
+    let mut test = FalsePositiveTest::new(capacity, 0.01);
+    if let Some(fpr) = args.parse_value::<f64>("fpr")? {
+        test.false_positive_rate = fpr;
+    }
+    Params::optimal(test.capacity, test.false_positive_rate)?;
+    test.inserted = args.parse_value("inserted")?.unwrap_or(test.inserted);
+    test.probes = args.parse_value("probes")?.unwrap_or(test.probes);
+    test.seed = args.parse_value("seed")?.unwrap_or(test.seed);
+
+    let report = test.run();
+    let (low, high) = report.confidence_interval;
+    writeln!(
+        out,
+        "filter: {} bits, {} hash functions",
+        report.size, report.hash_count
+    )?;
+    writeln!(out, "inserted: {}", report.inserted)?;
+    writeln!(
+        out,
+        "false positives: {} of {} probes",
+        report.false_positives, report.probes
+    )?;
+    writeln!(out, "measured: {:.6}", report.measured)?;
+    writeln!(out, "95% confidence interval: [{:.6}, {:.6}]", low, high)?;
+    writeln!(out, "theoretical: {:.6}", report.theoretical)?;
+    writeln!(out, "target: {:.6}", report.target)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::tests::run_cli;
+
+    #[test]
+    fn test_bench() {
+        let (result, out) = run_cli(&[
+            "bench",
+            "--capacity",
+            "1000",
+            "--probes",
+            "1000",
+            "--seed",
+            "7",
+        ]);
+        result.unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "filter: 9586 bits, 7 hash functions");
+        assert_eq!(lines[1], "inserted: 1000");
+        assert!(lines[2].ends_with("of 1000 probes"));
+        assert_eq!(lines[6], "target: 0.010000");
+
+        assert!(run_cli(&["bench"]).0.is_err());
+        assert!(run_cli(&["bench", "--capacity", "10", "--fpr", "0"])
+            .0
+            .is_err());
+    }
+}
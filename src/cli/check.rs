@@ -0,0 +1,31 @@
+use super::args::Args;
+use anyhow::Result;
+use std::io::Write;
+use std::process::ExitCode;
+
+pub fn run<I>(args: I, out: &mut dyn Write) -> Result<ExitCode>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = Args::parse(args, &["input"], &["quiet"])?;
+    let path = super::filter_path(&args)?;
+    let lookup = super::load_lookup(path)?;
+    let quiet = args.flag("quiet");
+
+    let mut all_present = true;
+    super::for_each_key(&args, |key| {
+        let present = lookup(key);
+        all_present &= present;
+        if !quiet {
+            let status = if present { "present" } else { "absent" };
+            writeln!(out, "{}\t{}", status, key)?;
+        }
+        Ok(())
+    })?;
+
+    if all_present {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
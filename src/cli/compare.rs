@@ -0,0 +1,92 @@
+use super::args::Args;
+use anyhow::{bail, Context, Result};
+use bloom_filter_yss::Estimate;
+use std::io::Write;
+use std::process::ExitCode;
+
+pub fn run<I>(args: I, out: &mut dyn Write) -> Result<ExitCode>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = Args::parse(args, &[], &[])?;
+    let (left, right) = match args.positionals() {
+        [left, right] => (left, right),
+        _ => bail!("expected two filter paths"),
+    };
+
+    let similarity = super::load(left)?
+        .similarity(&super::load(right)?)
+        .with_context(|| format!("cannot compare {} with {}", left, right))?;
+    let count = |estimate: Estimate| {
+        format!(
+            "{:.0} ({:.0} to {:.0})",
+            estimate.value, estimate.low, estimate.high
+        )
+    };
+    writeln!(out, "left count: {}", count(similarity.left))?;
+    writeln!(out, "right count: {}", count(similarity.right))?;
+    writeln!(out, "union: {}", count(similarity.union))?;
+    writeln!(out, "intersection: {}", count(similarity.intersection))?;
+    let jaccard = similarity.jaccard;
+    writeln!(
+        out,
+        "jaccard: {:.4} ({:.4} to {:.4})",
+        jaccard.value, jaccard.low, jaccard.high
+    )?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::tests::{run_cli, tmp_path};
+    use std::fs;
+
+    #[test]
+    fn test_compare() {
+        let left = tmp_path("compare_left.bin");
+        let right = tmp_path("compare_right.bin");
+        let keys = tmp_path("compare_keys.txt");
+        for (path, range) in [(&left, 0..600), (&right, 200..800)] {
+            run_cli(&["create", path, "--capacity", "1000", "--force"])
+                .0
+                .unwrap();
+            let lines: Vec<String> = range.map(|i| format!("key-{}", i)).collect();
+            fs::write(&keys, lines.join("\n")).unwrap();
+            run_cli(&["add", path, "--input", &keys]).0.unwrap();
+        }
+
+        let (result, out) = run_cli(&["compare", &left, &right]);
+        result.unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("left count: "), "{}", out);
+        assert!(lines[2].starts_with("union: "), "{}", out);
+        let jaccard: f64 = lines[4]["jaccard: ".len()..][..6].parse().unwrap();
+        assert!((jaccard - 0.5).abs() < 0.05, "{}", out);
+
+        assert!(run_cli(&["compare", &left]).0.is_err());
+        for path in [left, right, keys] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_compare_incompatible() {
+        let left = tmp_path("compare_incompatible_left.bin");
+        let right = tmp_path("compare_incompatible_right.bin");
+        run_cli(&["create", &left, "--capacity", "100", "--force"])
+            .0
+            .unwrap();
+        run_cli(&["create", &right, "--capacity", "1000", "--force"])
+            .0
+            .unwrap();
+
+        let (result, _) = run_cli(&["compare", &left, &right]);
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("Incompatible filters"), "{}", message);
+
+        fs::remove_file(left).unwrap();
+        fs::remove_file(right).unwrap();
+    }
+}
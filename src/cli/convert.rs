@@ -0,0 +1,104 @@
+use super::args::Args;
+use anyhow::{bail, Context, Result};
+use bloom_filter_yss::{BloomFilter, FileFormat};
+use std::fs;
+use std::io::Write;
+use std::process::ExitCode;
+
+pub fn run<I>(args: I, out: &mut dyn Write) -> Result<ExitCode>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = Args::parse(args, &["compress"], &[])?;
+    let (input, output) = match args.positionals() {
+        [input] => (input, input),
+        [input, output] => (input, output),
+        _ => bail!("expected an input path and an optional output path"),
+    };
+
+    let bytes = fs::read(input).with_context(|| format!("failed to read {}", input))?;
+    let (format, mut bloom_filter) = FileFormat::detect(&bytes)
+        .and_then(|format| Ok((format, BloomFilter::from_bytes(&bytes)?)))
+        .with_context(|| format!("failed to load {}", input))?;
+    let compress_mode = bloom_filter.compress_mode.clone();
+    if let Some(compress_mode) = super::compress_mode(&args)? {
+        bloom_filter.compress_mode = compress_mode;
+    }
+
+    super::save(output, &bloom_filter)?;
+    writeln!(
+        out,
+        "converted {} ({:?}, {:?}) to {} ({:?}, {:?})",
+        input,
+        format,
+        compress_mode,
+        output,
+        FileFormat::CURRENT,
+        bloom_filter.compress_mode
+    )?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::tests::{run_cli, tmp_path};
+    use bloom_filter_yss::{BloomFilter, BloomFilterBuilder, CompressMode, Encodable, FileFormat};
+    use std::fs;
+
+    #[test]
+    fn test_convert_compress_mode() {
+        let input = tmp_path("convert_input.bin");
+        let output = tmp_path("convert_output.bin");
+        run_cli(&["create", &input, "--capacity", "100", "--force"])
+            .0
+            .unwrap();
+        run_cli(&["add", &input, "test"]).0.unwrap();
+
+        let (result, out) = run_cli(&["convert", &input, &output, "--compress", "none"]);
+        result.unwrap();
+        assert_eq!(
+            out,
+            format!("converted {} (V4, Lzw) to {} (V4, None)\n", input, output)
+        );
+        let converted = BloomFilter::from_file(&output).unwrap();
+        assert_eq!(converted.compress_mode, CompressMode::None);
+        assert!(converted.lookup("test"));
+        let mut original = BloomFilter::from_file(&input).unwrap();
+        original.compress_mode = CompressMode::None;
+        assert_eq!(converted, original);
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_convert_legacy_in_place() {
+        let path = tmp_path("convert_legacy.bin");
+        let mut bloom_filter = BloomFilterBuilder::new(100).build();
+        bloom_filter.insert("test");
+        // Legacy files hold the encoding without hash scheme and layout
+        fs::write(&path, &bloom_filter.encode()[2..]).unwrap();
+
+        let (result, out) = run_cli(&["convert", &path]);
+        result.unwrap();
+        assert_eq!(
+            out,
+            format!("converted {} (Legacy, Lzw) to {} (V4, Lzw)\n", path, path)
+        );
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(FileFormat::detect(&bytes).unwrap(), FileFormat::V4);
+        assert_eq!(BloomFilter::from_bytes(&bytes).unwrap(), bloom_filter);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_convert_invalid_input() {
+        let path = tmp_path("convert_invalid.bin");
+        fs::write(&path, b"not a filter").unwrap();
+        assert!(run_cli(&["convert", &path]).0.is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"not a filter");
+        fs::remove_file(path).unwrap();
+    }
+}
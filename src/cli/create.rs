@@ -0,0 +1,117 @@
+use super::args::Args;
+use anyhow::{bail, Result};
+use bloom_filter_yss::{BloomFilterBuilder, CompressMode};
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+
+pub fn run<I>(args: I, out: &mut dyn Write) -> Result<ExitCode>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = Args::parse(
+        args,
+        &["capacity", "fpr", "compress"],
+        &["force", "partitioned"],
+    )?;
+    let path = super::filter_path(&args)?;
+    if args.positionals().len() > 1 {
+        bail!("unexpected argument `{}`", args.positionals()[1]);
+    }
+    if Path::new(path).exists() && !args.flag("force") {
+        bail!("{} already exists, use --force to overwrite", path);
+    }
+
+    let Some(capacity) = args.parse_value::<usize>("capacity")? else {
+        bail!("missing --capacity");
+    };
+    let mut builder = BloomFilterBuilder::new(capacity);
+    if let Some(fpr) = args.parse_value::<f64>("fpr")? {
+        builder = builder.false_positive_rate(fpr);
+    }
+    if super::compress_mode(&args)? == Some(CompressMode::None) {
+        builder = builder.no_compress();
+    }
+    if args.flag("partitioned") {
+        builder = builder.partitioned();
+    }
+
+    builder.params()?;
+    let bloom_filter = builder.build();
+    super::save(path, &bloom_filter)?;
+    writeln!(
+        out,
+        "created {} ({} bits, {} hash functions)",
+        path, bloom_filter.bit_array.size, bloom_filter.hash_count
+    )?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::tests::{run_cli, tmp_path};
+    use bloom_filter_yss::{BloomFilter, CompressMode, Layout};
+    use std::fs;
+
+    #[test]
+    fn test_create() {
+        let path = tmp_path("create.bin");
+        let _ = fs::remove_file(&path);
+
+        let (result, out) = run_cli(&["create", &path, "--capacity=2", "--compress", "none"]);
+        result.unwrap();
+        assert_eq!(
+            out,
+            format!("created {} (20 bits, 7 hash functions)\n", path)
+        );
+        let bloom_filter = BloomFilter::from_file(&path).unwrap();
+        assert_eq!(bloom_filter.compress_mode, CompressMode::None);
+
+        // Existing file is kept unless forced
+        assert!(run_cli(&["create", &path, "--capacity", "2"]).0.is_err());
+        run_cli(&[
+            "create",
+            &path,
+            "--capacity",
+            "2",
+            "--fpr",
+            "0.001",
+            "--force",
+        ])
+        .0
+        .unwrap();
+        let bloom_filter = BloomFilter::from_file(&path).unwrap();
+        assert_eq!(bloom_filter.compress_mode, CompressMode::Lzw);
+        assert_eq!(bloom_filter.hash_count, 10);
+        assert_eq!(bloom_filter.layout, Layout::Standard);
+
+        let (result, out) = run_cli(&["create", &path, "--capacity=2", "--partitioned", "--force"]);
+        result.unwrap();
+        assert_eq!(
+            out,
+            format!("created {} (21 bits, 7 hash functions)\n", path)
+        );
+        let bloom_filter = BloomFilter::from_file(&path).unwrap();
+        assert_eq!(bloom_filter.layout, Layout::Partitioned);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_create_invalid_options() {
+        let path = tmp_path("create_invalid.bin");
+        assert!(run_cli(&["create", &path]).0.is_err());
+        assert!(run_cli(&["create", &path, "--capacity", "0"]).0.is_err());
+        assert!(
+            run_cli(&["create", &path, "--capacity", "2", "--fpr", "1.5"])
+                .0
+                .is_err()
+        );
+        assert!(
+            run_cli(&["create", &path, "--capacity", "2", "--compress", "gzip"])
+                .0
+                .is_err()
+        );
+    }
+}
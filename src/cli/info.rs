@@ -0,0 +1,107 @@
+use super::args::Args;
+use anyhow::{bail, Context, Result};
+use bloom_filter_yss::{BloomFilter, CompressMode, FileFormat, HashScheme, Layout};
+use std::fs;
+use std::io::Write;
+use std::process::ExitCode;
+
+pub fn run<I>(args: I, out: &mut dyn Write) -> Result<ExitCode>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = Args::parse(args, &[], &[])?;
+    let path = super::filter_path(&args)?;
+    if args.positionals().len() > 1 {
+        bail!("unexpected argument `{}`", args.positionals()[1]);
+    }
+    let bytes = fs::read(path).with_context(|| format!("failed to read {}", path))?;
+    let (format, bloom_filter) = FileFormat::detect(&bytes)
+        .and_then(|format| Ok((format, BloomFilter::from_bytes(&bytes)?)))
+        .with_context(|| format!("failed to load {}", path))?;
+    let file_size = bytes.len();
+    let mut uncompressed = bloom_filter.clone();
+    uncompressed.compress_mode = CompressMode::None;
+    let raw_size = uncompressed.to_bytes().len();
+
+    let compression = match bloom_filter.compress_mode {
+        CompressMode::None => "none",
+        CompressMode::Lzw => "lzw",
+    };
+    let hash_scheme = match bloom_filter.hash_scheme {
+        HashScheme::Murmur3Fnv => "murmur3-fnv",
+        HashScheme::RedisBloom => "redisbloom",
+        HashScheme::Guava => "guava",
+        HashScheme::Bip37 { .. } => "bip37",
+        HashScheme::BitsAndBlooms => "bits-and-blooms",
+    };
+    let layout = match bloom_filter.layout {
+        Layout::Standard => "standard",
+        Layout::Partitioned => "partitioned",
+    };
+    writeln!(
+        out,
+        "size: {} bits ({} bytes)",
+        bloom_filter.bit_array.size,
+        bloom_filter.bit_array.byte_array.len()
+    )?;
+    writeln!(out, "hash functions: {}", bloom_filter.hash_count)?;
+    writeln!(out, "hash scheme: {}", hash_scheme)?;
+    writeln!(out, "layout: {}", layout)?;
+    writeln!(out, "fill ratio: {:.4}", bloom_filter.fill_ratio())?;
+    writeln!(
+        out,
+        "estimated count: {:.0}",
+        bloom_filter.estimated_count()
+    )?;
+    writeln!(out, "compression: {}", compression)?;
+    writeln!(out, "format: {:?}", format)?;
+    writeln!(
+        out,
+        "file size: {} bytes ({:.1}% of uncompressed {} bytes)",
+        file_size,
+        file_size as f64 * 100.0 / raw_size as f64,
+        raw_size
+    )?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::tests::{run_cli, tmp_path};
+    use std::fs;
+
+    #[test]
+    fn test_info() {
+        let path = tmp_path("info.bin");
+        run_cli(&[
+            "create",
+            &path,
+            "--capacity",
+            "2",
+            "--compress",
+            "none",
+            "--force",
+        ])
+        .0
+        .unwrap();
+        run_cli(&["add", &path, "test"]).0.unwrap();
+
+        let (result, out) = run_cli(&["info", &path]);
+        result.unwrap();
+        assert_eq!(
+            out,
+            "size: 20 bits (3 bytes)\n\
+             hash functions: 7\n\
+             hash scheme: murmur3-fnv\n\
+             layout: standard\n\
+             fill ratio: 0.2000\n\
+             estimated count: 1\n\
+             compression: none\n\
+             format: V4\n\
+             file size: 36 bytes (100.0% of uncompressed 36 bytes)\n"
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+}
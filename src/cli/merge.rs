@@ -0,0 +1,96 @@
+use super::args::Args;
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+
+pub fn run<I>(args: I, out: &mut dyn Write) -> Result<ExitCode>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = Args::parse(args, &["compress"], &["force"])?;
+    let (output, inputs) = match args.positionals() {
+        [output, inputs @ ..] if !inputs.is_empty() => (output, inputs),
+        _ => bail!("expected an output path and at least one input"),
+    };
+    if Path::new(output).exists() && !args.flag("force") && !inputs.contains(output) {
+        bail!("{} already exists, use --force to overwrite", output);
+    }
+
+    let mut merged = super::load(&inputs[0])?;
+    for input in &inputs[1..] {
+        let bloom_filter = super::load(input)?;
+        merged
+            .union(&bloom_filter)
+            .with_context(|| format!("cannot merge {} into {}", input, inputs[0]))?;
+    }
+    if let Some(compress_mode) = super::compress_mode(&args)? {
+        merged.compress_mode = compress_mode;
+    }
+
+    super::save(output, &merged)?;
+    writeln!(out, "merged {} filters into {}", inputs.len(), output)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::tests::{run_cli, tmp_path};
+    use bloom_filter_yss::{BloomFilter, CompressMode};
+    use std::fs;
+
+    #[test]
+    fn test_merge() {
+        let left = tmp_path("merge_left.bin");
+        let right = tmp_path("merge_right.bin");
+        let output = tmp_path("merge_output.bin");
+        let _ = fs::remove_file(&output);
+        run_cli(&["create", &left, "--capacity", "100", "--force"])
+            .0
+            .unwrap();
+        run_cli(&["create", &right, "--capacity", "100", "--force"])
+            .0
+            .unwrap();
+        run_cli(&["add", &left, "left"]).0.unwrap();
+        run_cli(&["add", &right, "right"]).0.unwrap();
+
+        let (result, out) = run_cli(&["merge", &output, &left, &right, "--compress", "none"]);
+        result.unwrap();
+        assert_eq!(out, format!("merged 2 filters into {}\n", output));
+        let merged = BloomFilter::from_file(&output).unwrap();
+        assert!(merged.lookup("left"));
+        assert!(merged.lookup("right"));
+        assert_eq!(merged.compress_mode, CompressMode::None);
+
+        // Refuses to overwrite unrelated files, merging in place is fine
+        assert!(run_cli(&["merge", &output, &left]).0.is_err());
+        run_cli(&["merge", &left, &left, &right]).0.unwrap();
+        assert!(BloomFilter::from_file(&left).unwrap().lookup("right"));
+
+        for path in [left, right, output] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_merge_incompatible() {
+        let left = tmp_path("merge_incompatible_left.bin");
+        let right = tmp_path("merge_incompatible_right.bin");
+        let output = tmp_path("merge_incompatible_output.bin");
+        run_cli(&["create", &left, "--capacity", "100", "--force"])
+            .0
+            .unwrap();
+        run_cli(&["create", &right, "--capacity", "1000", "--force"])
+            .0
+            .unwrap();
+
+        let (result, _) = run_cli(&["merge", &output, &left, &right, "--force"]);
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("Incompatible filters"), "{}", message);
+        assert!(!std::path::Path::new(&output).exists());
+
+        fs::remove_file(left).unwrap();
+        fs::remove_file(right).unwrap();
+    }
+}
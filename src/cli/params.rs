@@ -0,0 +1,60 @@
+use super::args::Args;
+use anyhow::{bail, Result};
+use bloom_filter_yss::params::{self, Query};
+use std::io::Write;
+use std::process::ExitCode;
+
+pub fn run<I>(args: I, out: &mut dyn Write) -> Result<ExitCode>
+where
+    I: IntoIterator<Item = String>,
+{
+    let args = Args::parse(args, &["capacity", "bits", "hash-count", "fpr"], &[])?;
+    if let Some(arg) = args.positionals().first() {
+        bail!("unexpected argument `{}`", arg);
+    }
+
+    let params = params::solve(Query {
+        items: args.parse_value("capacity")?,
+        bits: args.parse_value("bits")?,
+        hash_count: args.parse_value("hash-count")?,
+        false_positive_rate: args.parse_value("fpr")?,
+    })?;
+    writeln!(out, "capacity: {}", params.items)?;
+    writeln!(out, "bits: {}", params.bits)?;
+    writeln!(out, "hash functions: {}", params.hash_count)?;
+    writeln!(
+        out,
+        "false positive rate: {:.6}",
+        params.false_positive_rate
+    )?;
+    writeln!(out, "bits per key: {:.2}", params.bits_per_key())?;
+    writeln!(out, "memory: {} bytes", params.memory_bytes())?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::tests::run_cli;
+
+    #[test]
+    fn test_params() {
+        let (result, out) = run_cli(&["params", "--capacity", "1000", "--fpr", "0.01"]);
+        result.unwrap();
+        assert_eq!(
+            out,
+            "capacity: 1000\n\
+             bits: 9586\n\
+             hash functions: 7\n\
+             false positive rate: 0.010000\n\
+             bits per key: 9.59\n\
+             memory: 1199 bytes\n"
+        );
+
+        let (result, out) = run_cli(&["params", "--bits", "9586", "--fpr", "0.01"]);
+        result.unwrap();
+        assert!(out.starts_with("capacity: 1000\n"));
+
+        assert!(run_cli(&["params", "--capacity", "1000"]).0.is_err());
+    }
+}
@@ -1,31 +1,37 @@
 // ref: https://www2.cs.duke.edu/csed/curious/compression/lzw.html
 
-use std::collections::HashMap;
+use crate::error::BloomFilterError;
+use anyhow::Result;
 
 type CodeType = u16;
 const CODE_SIZE: usize = 2; // in bytes
 const DMS: usize = CodeType::MAX as usize; // dictionary last
 const INITIAL_CODE: CodeType = CodeType::MAX;
+const ALPHABET_SIZE: usize = u8::MAX as usize + 1;
+
+// Open addressing table, kept at most half full (DMS entries max)
+const TABLE_BITS: u32 = 17;
+const TABLE_SIZE: usize = 1 << TABLE_BITS;
+const EMPTY_KEY: u32 = u32::MAX;
 
 pub fn compress(bytes: &[u8]) -> Vec<u8> {
-    let mut dictionary = compress_dictionary();
+    let mut dictionary = CompressDictionary::new();
     let mut output: Vec<u8> = Vec::new();
     let mut curr_code = INITIAL_CODE;
 
     for &byte in bytes {
-        if dictionary.len() == DMS {
-            dictionary = compress_dictionary();
+        if dictionary.len == DMS {
+            dictionary.reset();
         }
 
-        match dictionary.get(&(curr_code, byte)) {
-            Some(&code) => {
+        match dictionary.get(curr_code, byte) {
+            Some(code) => {
                 curr_code = code;
             }
             None => {
                 output.extend_from_slice(&curr_code.to_be_bytes());
-                let next_code = dictionary.len() as CodeType;
-                dictionary.insert((curr_code, byte), next_code);
-                curr_code = *dictionary.get(&(INITIAL_CODE, byte)).unwrap();
+                dictionary.insert(curr_code, byte);
+                curr_code = byte as CodeType;
             }
         }
     }
@@ -36,49 +42,107 @@ pub fn compress(bytes: &[u8]) -> Vec<u8> {
     output
 }
 
-pub fn decompress(bytes: &[u8]) -> Vec<u8> {
+// Corrupt input, a stray byte or a code the dictionary can't resolve, is
+// an error
+pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>> {
+    if !bytes.len().is_multiple_of(CODE_SIZE) {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
     let mut output = Vec::new();
     let mut dictionary = decompress_dictionary();
     let mut prev_code = INITIAL_CODE;
 
     for code in bytes.chunks_exact(CODE_SIZE) {
         let curr_code = CodeType::from_be_bytes([code[0], code[1]]);
-        let dict_len = dictionary.len();
         let code_usize = curr_code as usize;
 
         if dictionary.len() == DMS {
-            dictionary = decompress_dictionary();
+            dictionary.truncate(ALPHABET_SIZE);
         }
+        // Compare against the length after reset, the first code of a new
+        // block can already be a <char><String><char> entry
+        let dict_len = dictionary.len();
         if code_usize > dict_len {
-            panic!("invalid compressed code");
+            return Err(BloomFilterError::InvalidFormat.into());
         }
 
         // Decompress
-        let entry: Vec<u8>;
         if code_usize == dict_len {
             // Case: <char><String><char>
-            let tmp = translate(&dictionary, &prev_code);
-            dictionary.push((prev_code, tmp[0]));
-            entry = translate(&dictionary, &curr_code);
+            let first = first_byte(&dictionary, prev_code)?;
+            dictionary.push((prev_code, first));
+            translate(&dictionary, curr_code, &mut output)?;
         } else {
-            entry = translate(&dictionary, &curr_code);
+            let entry_start = output.len();
+            translate(&dictionary, curr_code, &mut output)?;
             if prev_code != INITIAL_CODE {
-                dictionary.push((prev_code, entry[0]));
+                dictionary.push((prev_code, output[entry_start]));
             }
         }
         prev_code = curr_code;
-        output.extend_from_slice(&entry);
     }
 
-    output
+    Ok(output)
+}
+
+// Maps (prefix code, byte) to the code of the extended string. Single bytes
+// are implicit: (INITIAL_CODE, c) always maps to c.
+struct CompressDictionary {
+    keys: Vec<u32>,
+    codes: Vec<CodeType>,
+    len: usize,
 }
 
-fn compress_dictionary() -> HashMap<(CodeType, u8), CodeType> {
-    let mut dictionary = HashMap::with_capacity(DMS);
-    for (i, c) in (u8::MIN..=u8::MAX).enumerate() {
-        dictionary.insert((INITIAL_CODE, c), i as CodeType);
+impl CompressDictionary {
+    fn new() -> Self {
+        Self {
+            keys: vec![EMPTY_KEY; TABLE_SIZE],
+            codes: vec![0; TABLE_SIZE],
+            len: ALPHABET_SIZE,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.keys.fill(EMPTY_KEY);
+        self.len = ALPHABET_SIZE;
+    }
+
+    fn get(&self, prefix: CodeType, byte: u8) -> Option<CodeType> {
+        if prefix == INITIAL_CODE {
+            return Some(byte as CodeType);
+        }
+
+        let key = Self::key(prefix, byte);
+        let mut slot = Self::slot(key);
+        loop {
+            match self.keys[slot] {
+                EMPTY_KEY => return None,
+                k if k == key => return Some(self.codes[slot]),
+                _ => slot = (slot + 1) & (TABLE_SIZE - 1),
+            }
+        }
+    }
+
+    // Caller must have checked the key is absent
+    fn insert(&mut self, prefix: CodeType, byte: u8) {
+        let key = Self::key(prefix, byte);
+        let mut slot = Self::slot(key);
+        while self.keys[slot] != EMPTY_KEY {
+            slot = (slot + 1) & (TABLE_SIZE - 1);
+        }
+        self.keys[slot] = key;
+        self.codes[slot] = self.len as CodeType;
+        self.len += 1;
+    }
+
+    fn key(prefix: CodeType, byte: u8) -> u32 {
+        ((prefix as u32) << 8) | byte as u32
+    }
+
+    // Fibonacci hashing
+    fn slot(key: u32) -> usize {
+        (key.wrapping_mul(0x9e37_79b9) >> (32 - TABLE_BITS)) as usize
     }
-    dictionary
 }
 
 fn decompress_dictionary() -> Vec<(CodeType, u8)> {
@@ -89,28 +153,101 @@ fn decompress_dictionary() -> Vec<(CodeType, u8)> {
     dictionary
 }
 
-// Translate encoded string
-fn translate(dictionary: &[(CodeType, u8)], code: &CodeType) -> Vec<u8> {
-    let mut output = Vec::with_capacity(DMS);
-    let mut curr_code = *code;
+// Translate encoded string, appending it to output
+fn translate(dictionary: &[(CodeType, u8)], code: CodeType, output: &mut Vec<u8>) -> Result<()> {
+    let start = output.len();
+    let mut curr_code = code;
 
     while curr_code != INITIAL_CODE {
-        let decoded_val = dictionary[curr_code as usize];
+        let decoded_val = entry(dictionary, curr_code)?;
         output.push(decoded_val.1);
         curr_code = decoded_val.0;
+        // A chain longer than the dictionary loops, only corrupt input has one
+        if output.len() - start > dictionary.len() {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
     }
-    output.reverse();
+    output[start..].reverse();
+    Ok(())
+}
 
-    output
+fn first_byte(dictionary: &[(CodeType, u8)], code: CodeType) -> Result<u8> {
+    let mut decoded_val = entry(dictionary, code)?;
+
+    for _ in 0..dictionary.len() {
+        if decoded_val.0 == INITIAL_CODE {
+            return Ok(decoded_val.1);
+        }
+        decoded_val = entry(dictionary, decoded_val.0)?;
+    }
+
+    Err(BloomFilterError::InvalidFormat.into())
+}
+
+fn entry(dictionary: &[(CodeType, u8)], code: CodeType) -> Result<(CodeType, u8)> {
+    let entry = dictionary.get(code as usize);
+    Ok(*entry.ok_or(BloomFilterError::InvalidFormat)?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+
+    // Previous HashMap based implementation, kept as the output reference
+    fn reference_compress(bytes: &[u8]) -> Vec<u8> {
+        let new_dictionary = || {
+            let mut dictionary = HashMap::with_capacity(DMS);
+            for (i, c) in (u8::MIN..=u8::MAX).enumerate() {
+                dictionary.insert((INITIAL_CODE, c), i as CodeType);
+            }
+            dictionary
+        };
+        let mut dictionary = new_dictionary();
+        let mut output: Vec<u8> = Vec::new();
+        let mut curr_code = INITIAL_CODE;
+
+        for &byte in bytes {
+            if dictionary.len() == DMS {
+                dictionary = new_dictionary();
+            }
+
+            match dictionary.get(&(curr_code, byte)) {
+                Some(&code) => {
+                    curr_code = code;
+                }
+                None => {
+                    output.extend_from_slice(&curr_code.to_be_bytes());
+                    let next_code = dictionary.len() as CodeType;
+                    dictionary.insert((curr_code, byte), next_code);
+                    curr_code = *dictionary.get(&(INITIAL_CODE, byte)).unwrap();
+                }
+            }
+        }
+        if curr_code != INITIAL_CODE {
+            output.extend_from_slice(&curr_code.to_be_bytes());
+        }
+
+        output
+    }
+
+    // xorshift64, mapped onto a small alphabet so long runs still repeat
+    fn pseudo_random_bytes(len: usize, alphabet: u64) -> Vec<u8> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state % alphabet) as u8
+            })
+            .collect()
+    }
 
     fn assert_compression(bytes: &[u8]) {
         let compressed = compress(bytes);
-        let decompressed = decompress(&compressed);
+        assert_eq!(compressed, reference_compress(bytes));
+        let decompressed = decompress(&compressed).unwrap();
         assert_eq!(decompressed, bytes);
     }
 
@@ -122,4 +259,22 @@ mod tests {
         assert_compression(b"");
         assert_compression(&[0b00000000, 0b11111111]);
     }
+
+    #[test]
+    fn test_decompress_invalid() {
+        // Stray byte, code past the dictionary, first code referring to
+        // the entry it would create
+        let inputs: [&[u8]; 3] = [&[0, 97, 0], &[0, 97, 1, 2], &[1, 0]];
+        for input in inputs {
+            assert!(decompress(input).is_err(), "{:?}", input);
+        }
+        assert_eq!(decompress(&[0, 97, 1, 0]).unwrap(), b"aaa");
+    }
+
+    #[test]
+    fn test_compressor_multi_megabyte() {
+        assert_compression(&pseudo_random_bytes(2 << 20, 256));
+        assert_compression(&pseudo_random_bytes(2 << 20, 5));
+        assert_compression(&vec![0; 2 << 20]);
+    }
 }
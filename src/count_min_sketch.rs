@@ -0,0 +1,383 @@
+mod builder;
+
+use crate::bloom_filter::murmur3_fnv_hashing;
+use crate::error::BloomFilterError;
+use crate::format::{FilterKind, Persistable};
+use anyhow::Result;
+pub use builder::CountMinSketchBuilder;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::f64::consts::E;
+
+// ref: Cormode & Muthukrishnan (2005), "An Improved Data Stream Summary:
+// The Count-Min Sketch and its Applications"
+//
+// `depth` rows of `width` counters, a key adding to one counter per row.
+// The smallest of its counters never undercounts a key, and overcounts by
+// at most epsilon * total with probability 1 - delta, for width e/epsilon
+// and depth ln(1/delta). Conservative update only raises counters below
+// the new estimate, which keeps the same bound with less overcounting but
+// rules out negative counts.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CountMinSketch {
+    pub(crate) counters: Vec<u64>,
+    pub(crate) width: usize,
+    pub(crate) depth: usize,
+    pub(crate) conservative: bool,
+    pub(crate) total: u64,
+    pub(crate) heavy_hitters: Option<TopK>,
+}
+
+impl CountMinSketch {
+    pub fn new(epsilon: f64, delta: f64) -> Result<Self> {
+        CountMinSketchBuilder::new(epsilon, delta).build()
+    }
+
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) {
+        self.add(key, 1)
+    }
+
+    pub fn add<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K, count: u64) {
+        let indexes = self.hashing(key.as_ref());
+        if self.conservative {
+            let estimate = self.min_counter(&indexes).saturating_add(count);
+            for i in indexes {
+                self.counters[i] = self.counters[i].max(estimate);
+            }
+        } else {
+            for i in indexes {
+                self.counters[i] = self.counters[i].saturating_add(count);
+            }
+        }
+        self.total = self.total.saturating_add(count);
+
+        if self.heavy_hitters.is_some() {
+            let estimate = self.estimate(key);
+            if let Some(heavy_hitters) = &mut self.heavy_hitters {
+                heavy_hitters.offer(key.as_ref(), estimate);
+            }
+        }
+    }
+
+    // Never below the true count
+    pub fn estimate<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> u64 {
+        self.min_counter(&self.hashing(key.as_ref()))
+    }
+
+    // Adds the counts of a sketch of the same dimensions. Tracked heavy
+    // hitters of both are estimated again on the merged counters.
+    pub fn merge(&mut self, other: &Self) -> Result<()> {
+        if (self.width, self.depth) != (other.width, other.depth) {
+            let reason = format!(
+                "dimensions {}x{} != {}x{}",
+                self.depth, self.width, other.depth, other.width
+            );
+            return Err(BloomFilterError::Incompatible(reason).into());
+        }
+
+        for (counter, other_counter) in self.counters.iter_mut().zip(&other.counters) {
+            *counter = counter.saturating_add(*other_counter);
+        }
+        self.total = self.total.saturating_add(other.total);
+
+        if let Some(mut heavy_hitters) = self.heavy_hitters.take() {
+            let mut keys = heavy_hitters.keys();
+            if let Some(other_heavy_hitters) = &other.heavy_hitters {
+                keys.extend(other_heavy_hitters.keys());
+            }
+            heavy_hitters.clear();
+            for key in keys {
+                heavy_hitters.offer(&key, self.estimate(&key));
+            }
+            self.heavy_hitters = Some(heavy_hitters);
+        }
+        Ok(())
+    }
+
+    // Tracked keys with their estimates, largest first. Empty unless built
+    // with `CountMinSketchBuilder::top_k`.
+    pub fn top_k(&self) -> Vec<(Vec<u8>, u64)> {
+        let mut top_k: Vec<(Vec<u8>, u64)> = self
+            .heavy_hitters
+            .iter()
+            .flat_map(|heavy_hitters| heavy_hitters.entries())
+            .map(|(count, key)| (key.clone(), *count))
+            .collect();
+        top_k.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_k
+    }
+
+    // Sum of all added counts
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    // Overcount bound as a fraction of the total, e / width
+    pub fn epsilon(&self) -> f64 {
+        E / self.width as f64
+    }
+
+    // Probability of exceeding the overcount bound, e^-depth
+    pub fn delta(&self) -> f64 {
+        (-(self.depth as f64)).exp()
+    }
+
+    // Index of the key's counter in each row
+    fn hashing(&self, bytes: &[u8]) -> Vec<usize> {
+        murmur3_fnv_hashing(bytes, self.depth, self.width)
+            .into_iter()
+            .enumerate()
+            .map(|(row, column)| row * self.width + column)
+            .collect()
+    }
+
+    fn min_counter(&self, indexes: &[usize]) -> u64 {
+        indexes.iter().map(|&i| self.counters[i]).min().unwrap_or(0)
+    }
+}
+
+impl Persistable for CountMinSketch {
+    fn kind() -> FilterKind {
+        FilterKind::CountMin
+    }
+}
+
+// Min-heap of the k keys with the largest estimates. k is meant to be
+// small, so the heap is scanned to find a key rather than indexed.
+#[derive(Debug, Clone)]
+pub(crate) struct TopK {
+    pub(crate) k: usize,
+    heap: BinaryHeap<Reverse<(u64, Vec<u8>)>>,
+}
+
+impl TopK {
+    pub(crate) fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    // Estimates only grow, so a tracked key is updated in place and any
+    // other key replaces the smallest once it outgrows it
+    pub(crate) fn offer(&mut self, key: &[u8], estimate: u64) {
+        if self.heap.iter().any(|Reverse((_, tracked))| tracked == key) {
+            let heap = std::mem::take(&mut self.heap);
+            self.heap = heap
+                .into_iter()
+                .map(|Reverse((count, tracked))| {
+                    let count = if tracked == key { estimate } else { count };
+                    Reverse((count, tracked))
+                })
+                .collect();
+        } else if self.heap.len() < self.k {
+            self.heap.push(Reverse((estimate, key.to_vec())));
+        } else if let Some(Reverse((smallest, _))) = self.heap.peek() {
+            if estimate > *smallest {
+                self.heap.pop();
+                self.heap.push(Reverse((estimate, key.to_vec())));
+            }
+        }
+    }
+
+    pub(crate) fn entries(&self) -> impl Iterator<Item = &(u64, Vec<u8>)> {
+        self.heap.iter().map(|Reverse(entry)| entry)
+    }
+
+    fn keys(&self) -> Vec<Vec<u8>> {
+        self.entries().map(|(_, key)| key.clone()).collect()
+    }
+
+    fn clear(&mut self) {
+        self.heap.clear();
+    }
+}
+
+// Same k and tracked entries, whatever the heap order
+impl PartialEq for TopK {
+    fn eq(&self, other: &Self) -> bool {
+        let sorted = |top_k: &Self| {
+            let mut entries: Vec<_> = top_k.entries().cloned().collect();
+            entries.sort();
+            entries
+        };
+        self.k == other.k && sorted(self) == sorted(other)
+    }
+}
+
+fn width_for(epsilon: f64) -> usize {
+    (E / epsilon).ceil() as usize
+}
+
+fn depth_for(delta: f64) -> usize {
+    ((1.0 / delta).ln().ceil() as usize).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encodable;
+    use crate::test_util::prepare_tmp_dir;
+    use std::collections::HashMap;
+    use std::fs;
+
+    // Key i occurs about 10_000 / (i + 1) times, a Zipf like stream
+    fn stream() -> Vec<(String, u64)> {
+        (0..2000)
+            .map(|i| (format!("key-{}", i), 10_000 / (i + 1)))
+            .collect()
+    }
+
+    fn sketch(conservative: bool) -> CountMinSketch {
+        let mut builder = CountMinSketchBuilder::new(0.001, 0.01);
+        if conservative {
+            builder = builder.conservative();
+        }
+        let mut sketch = builder.build().unwrap();
+        for (key, count) in stream() {
+            for _ in 0..count {
+                sketch.insert(&key);
+            }
+        }
+        sketch
+    }
+
+    #[test]
+    fn test_sizing() {
+        let sketch = CountMinSketch::new(0.001, 0.01).unwrap();
+        assert_eq!((sketch.width(), sketch.depth()), (2719, 5));
+        assert!(sketch.epsilon() <= 0.001 && sketch.delta() <= 0.01);
+        assert_eq!(width_for(0.5), 6);
+        assert_eq!(depth_for(0.9), 1);
+    }
+
+    #[test]
+    fn test_estimate() {
+        let total: u64 = stream().iter().map(|(_, count)| count).sum();
+        let overcounts = |sketch: &CountMinSketch| -> Vec<u64> {
+            assert_eq!(sketch.total(), total);
+            stream()
+                .iter()
+                .map(|(key, count)| sketch.estimate(key) - count)
+                .collect()
+        };
+        let plain = overcounts(&sketch(false));
+        let conservative = overcounts(&sketch(true));
+
+        // Within epsilon * total for all but about delta of the keys
+        let bound = (0.001 * total as f64) as u64;
+        let within = plain
+            .iter()
+            .filter(|&&overcount| overcount <= bound)
+            .count();
+        assert!(within >= 1980, "{}", within);
+        assert!(plain.iter().sum::<u64>() > 2 * conservative.iter().sum::<u64>());
+        assert!(conservative
+            .iter()
+            .zip(&plain)
+            .all(|(conservative, plain)| conservative <= plain));
+    }
+
+    #[test]
+    fn test_add() {
+        let mut sketch = CountMinSketch::new(0.01, 0.01).unwrap();
+        sketch.add("key", 5);
+        sketch.add(b"key", 3);
+        sketch.insert(&String::from("key"));
+        assert_eq!(sketch.estimate("key"), 9);
+        assert_eq!(sketch.estimate("other"), 0);
+        sketch.add("key", u64::MAX);
+        assert_eq!(sketch.estimate("key"), u64::MAX);
+    }
+
+    #[test]
+    fn test_top_k() {
+        let mut sketch = CountMinSketchBuilder::new(0.001, 0.01)
+            .top_k(5)
+            .build()
+            .unwrap();
+        let mut stream = stream();
+        stream.reverse();
+        for (key, count) in &stream {
+            for _ in 0..*count {
+                sketch.insert(key);
+            }
+        }
+        let top_k = sketch.top_k();
+        let keys: Vec<&[u8]> = top_k.iter().map(|(key, _)| key.as_slice()).collect();
+        assert_eq!(
+            keys,
+            [&b"key-0"[..], b"key-1", b"key-2", b"key-3", b"key-4"]
+        );
+        assert_eq!(top_k[0].1, sketch.estimate("key-0"));
+        assert!(CountMinSketch::new(0.1, 0.1).unwrap().top_k().is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let builder = || CountMinSketchBuilder::new(0.01, 0.01).top_k(2);
+        let mut left = builder().build().unwrap();
+        let mut right = builder().build().unwrap();
+        let mut counts = HashMap::new();
+        for (i, key) in ["a", "b", "c", "a", "c", "c"].iter().enumerate() {
+            let sketch = if i % 2 == 0 { &mut left } else { &mut right };
+            sketch.insert(key);
+            *counts.entry(*key).or_insert(0) += 1;
+        }
+
+        left.merge(&right).unwrap();
+        assert_eq!(left.total(), 6);
+        for (key, count) in counts {
+            assert!(left.estimate(key) >= count);
+        }
+        let top_k = left.top_k();
+        assert_eq!(top_k[0].0, b"c");
+        assert_eq!(top_k.len(), 2);
+
+        let smaller = CountMinSketch::new(0.1, 0.01).unwrap();
+        assert_eq!(
+            left.merge(&smaller).unwrap_err().to_string(),
+            "Incompatible filters: dimensions 5x272 != 5x28"
+        );
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        let mut sketch = CountMinSketchBuilder::new(0.1, 0.1)
+            .top_k(3)
+            .build()
+            .unwrap();
+        sketch.add("key", 7);
+        let bytes = sketch.to_bytes();
+        assert_eq!(&bytes[..6], b"BFYS\x04\x0a");
+        assert_eq!(&bytes[6..], sketch.encode());
+        assert_eq!(CountMinSketch::from_bytes(&bytes).unwrap(), sketch);
+        assert!(crate::BloomFilter::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_persist_local_file() {
+        prepare_tmp_dir();
+        let test_file = "tmp/count_min_sketch_test_persist_local_file.bin";
+        let mut sketch = CountMinSketchBuilder::new(0.01, 0.01)
+            .conservative()
+            .build()
+            .unwrap();
+        sketch.add("test", 3);
+        sketch.to_file(test_file).unwrap();
+
+        let loaded = CountMinSketchBuilder::load(test_file).unwrap();
+        assert_eq!(loaded, sketch);
+        assert_eq!(loaded.estimate("test"), 3);
+
+        fs::remove_file(test_file).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use super::{depth_for, width_for, CountMinSketch, TopK};
+use crate::error::BloomFilterError;
+use crate::format::Persistable;
+use anyhow::Result;
+
+pub struct CountMinSketchBuilder {
+    epsilon: f64,
+    delta: f64,
+    conservative: bool,
+    top_k: Option<usize>,
+}
+
+impl CountMinSketchBuilder {
+    // Estimates exceed true counts by at most epsilon times the total count,
+    // except with probability delta
+    pub fn new(epsilon: f64, delta: f64) -> Self {
+        Self {
+            epsilon,
+            delta,
+            conservative: false,
+            top_k: None,
+        }
+    }
+
+    pub fn conservative(mut self) -> Self {
+        self.conservative = true;
+        self
+    }
+
+    // Tracks the k keys with the largest estimates
+    pub fn top_k(mut self, k: usize) -> Self {
+        self.top_k = Some(k);
+        self
+    }
+
+    pub fn build(self) -> Result<CountMinSketch> {
+        if !(self.epsilon > 0.0 && self.epsilon < 1.0) {
+            let reason = format!("epsilon {} must be in (0, 1)", self.epsilon);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        if !(self.delta > 0.0 && self.delta < 1.0) {
+            let reason = format!("delta {} must be in (0, 1)", self.delta);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        if self.top_k == Some(0) {
+            let reason = "top k must be positive".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+
+        let width = width_for(self.epsilon);
+        let depth = depth_for(self.delta);
+        let Some(counters) = width.checked_mul(depth) else {
+            let reason = format!("epsilon {} is too small", self.epsilon);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        };
+        Ok(CountMinSketch {
+            counters: vec![0; counters],
+            width,
+            depth,
+            conservative: self.conservative,
+            total: 0,
+            heavy_hitters: self.top_k.map(TopK::new),
+        })
+    }
+
+    pub fn load(path: &str) -> Result<CountMinSketch> {
+        CountMinSketch::from_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        let sketch = CountMinSketchBuilder::new(0.01, 0.001).build().unwrap();
+        assert_eq!((sketch.width, sketch.depth), (272, 7));
+        assert_eq!(sketch.counters.len(), 272 * 7);
+        assert!(!sketch.conservative);
+        assert!(sketch.heavy_hitters.is_none());
+    }
+
+    #[test]
+    fn test_options() {
+        let sketch = CountMinSketchBuilder::new(0.01, 0.001)
+            .conservative()
+            .top_k(10)
+            .build()
+            .unwrap();
+        assert!(sketch.conservative);
+        assert_eq!(sketch.heavy_hitters.unwrap().k, 10);
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        assert!(CountMinSketchBuilder::new(0.0, 0.1).build().is_err());
+        assert!(CountMinSketchBuilder::new(1.0, 0.1).build().is_err());
+        assert!(CountMinSketchBuilder::new(0.1, 0.0).build().is_err());
+        assert!(CountMinSketchBuilder::new(0.1, f64::NAN).build().is_err());
+        assert!(CountMinSketchBuilder::new(0.1, 0.1)
+            .top_k(0)
+            .build()
+            .is_err());
+        assert!(CountMinSketchBuilder::new(1e-300, 0.1).build().is_err());
+    }
+}
@@ -0,0 +1,322 @@
+mod builder;
+
+use crate::error::BloomFilterError;
+use crate::format::{FilterKind, Persistable};
+use crate::hash::{murmur3, murmur3_x64_128};
+use crate::rng::SplitMix64;
+use anyhow::Result;
+pub use builder::CuckooFilterBuilder;
+
+const MURMUR3_SEED: u32 = 0xdead_cafe;
+const FINGERPRINT_BITS: u32 = 12;
+const BUCKET_SIZE: usize = 4;
+const MAX_KICKS: usize = 500;
+
+// Partial-key cuckoo hashing, ref: Fan et al. (2014), "Cuckoo Filter:
+// Practically Better Than Bloom"
+//
+// Each key is stored as a fingerprint in one of two buckets, the second
+// one being the first XOR the hash of the fingerprint, so either bucket
+// can be found from the other without the key. Fingerprints are packed
+// `fingerprint_bits` apiece, 0 marks an empty slot.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CuckooFilter {
+    pub(crate) table: Vec<u8>,
+    pub(crate) bucket_count: usize,
+    pub(crate) bucket_size: usize,
+    pub(crate) fingerprint_bits: u32,
+    pub(crate) max_kicks: usize,
+    pub(crate) len: usize,
+}
+
+impl CuckooFilter {
+    // 12 bit fingerprints in buckets of 4, about 0.2% false positives
+    pub fn new(capacity: usize) -> Self {
+        CuckooFilterBuilder::new(capacity)
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Fails with `BloomFilterError::Full` when no slot frees up within
+    // `max_kicks` relocations, the filter is then left unchanged. Inserting
+    // a key again stores another copy.
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> Result<()> {
+        let (index, fingerprint, hash) = self.hashing(key.as_ref());
+        let alt_index = self.alt_index(index, fingerprint);
+        if self.put(index, fingerprint) || self.put(alt_index, fingerprint) {
+            self.len += 1;
+            return Ok(());
+        }
+
+        let mut rng = SplitMix64::new(hash);
+        let mut index = if rng.next_u64() & 1 == 0 {
+            index
+        } else {
+            alt_index
+        };
+        let mut fingerprint = fingerprint;
+        // Grown as kicks happen, max_kicks may come from an untrusted file
+        let mut kicks = Vec::new();
+        for _ in 0..self.max_kicks {
+            let slot = (rng.next_u64() % self.bucket_size as u64) as usize;
+            let victim = self.get(index, slot);
+            self.set(index, slot, fingerprint);
+            kicks.push((index, slot, victim));
+            fingerprint = victim;
+            index = self.alt_index(index, fingerprint);
+            if self.put(index, fingerprint) {
+                self.len += 1;
+                return Ok(());
+            }
+        }
+
+        for (index, slot, victim) in kicks.into_iter().rev() {
+            self.set(index, slot, victim);
+        }
+        let reason = format!(
+            "{} items in {} slots, no room after {} kicks",
+            self.len,
+            self.capacity(),
+            self.max_kicks
+        );
+        Err(BloomFilterError::Full(reason).into())
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        let (index, fingerprint, _) = self.hashing(key.as_ref());
+        self.find(index, fingerprint).is_some()
+            || self
+                .find(self.alt_index(index, fingerprint), fingerprint)
+                .is_some()
+    }
+
+    // Removes one copy of the key. Only remove keys that were inserted, any
+    // other key sharing a fingerprint and bucket would be removed instead.
+    pub fn remove<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> bool {
+        let (index, fingerprint, _) = self.hashing(key.as_ref());
+        let alt_index = self.alt_index(index, fingerprint);
+        for index in [index, alt_index] {
+            if let Some(slot) = self.find(index, fingerprint) {
+                self.set(index, slot, 0);
+                self.len -= 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Number of slots, inserts usually start failing at about 95% of it
+    pub fn capacity(&self) -> usize {
+        self.bucket_count * self.bucket_size
+    }
+
+    pub fn load_factor(&self) -> f64 {
+        self.len as f64 / self.capacity() as f64
+    }
+
+    pub fn fingerprint_bits(&self) -> u32 {
+        self.fingerprint_bits
+    }
+
+    pub fn bucket_size(&self) -> usize {
+        self.bucket_size
+    }
+
+    // Bucket index, non zero fingerprint, and the hash seeding the kicks
+    fn hashing(&self, bytes: &[u8]) -> (usize, u32, u64) {
+        let (h1, h2) = murmur3_x64_128(bytes, MURMUR3_SEED);
+        let fingerprint = ((h2 & self.fingerprint_mask()) as u32).max(1);
+        (h1 as usize & (self.bucket_count - 1), fingerprint, h2)
+    }
+
+    fn alt_index(&self, index: usize, fingerprint: u32) -> usize {
+        let hash = murmur3(&fingerprint.to_le_bytes(), MURMUR3_SEED) as usize;
+        (index ^ hash) & (self.bucket_count - 1)
+    }
+
+    fn fingerprint_mask(&self) -> u64 {
+        (1 << self.fingerprint_bits) - 1
+    }
+
+    fn find(&self, index: usize, fingerprint: u32) -> Option<usize> {
+        (0..self.bucket_size).find(|&slot| self.get(index, slot) == fingerprint)
+    }
+
+    // Stores the fingerprint in a free slot of the bucket, if any
+    fn put(&mut self, index: usize, fingerprint: u32) -> bool {
+        match self.find(index, 0) {
+            Some(slot) => {
+                self.set(index, slot, fingerprint);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Fingerprints span at most 5 bytes, read little endian
+    fn get(&self, index: usize, slot: usize) -> u32 {
+        let bit = (index * self.bucket_size + slot) * self.fingerprint_bits as usize;
+        let window = self.table[bit / 8..]
+            .iter()
+            .take(5)
+            .enumerate()
+            .fold(0u64, |window, (i, &byte)| window | (byte as u64) << (8 * i));
+        ((window >> (bit % 8)) & self.fingerprint_mask()) as u32
+    }
+
+    fn set(&mut self, index: usize, slot: usize, fingerprint: u32) {
+        let bit = (index * self.bucket_size + slot) * self.fingerprint_bits as usize;
+        let mask = self.fingerprint_mask() << (bit % 8);
+        let value = (fingerprint as u64) << (bit % 8);
+        for (i, byte) in self.table[bit / 8..].iter_mut().take(5).enumerate() {
+            let byte_mask = (mask >> (8 * i)) as u8;
+            *byte = (*byte & !byte_mask) | ((value >> (8 * i)) as u8 & byte_mask);
+        }
+    }
+
+    pub(crate) fn count_occupied(&self) -> usize {
+        (0..self.bucket_count)
+            .flat_map(|index| (0..self.bucket_size).map(move |slot| (index, slot)))
+            .filter(|&(index, slot)| self.get(index, slot) != 0)
+            .count()
+    }
+}
+
+impl Persistable for CuckooFilter {
+    fn kind() -> FilterKind {
+        FilterKind::Cuckoo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encodable;
+    use crate::test_util::prepare_tmp_dir;
+    use crate::BloomFilter;
+    use std::fs;
+
+    #[test]
+    fn test_insert_lookup() {
+        let mut filter = CuckooFilter::new(10_000);
+        assert_eq!(filter.capacity(), 16_384);
+        for i in 0..10_000 {
+            filter.insert(&format!("key-{}", i)).unwrap();
+        }
+        assert_eq!(filter.len(), 10_000);
+        assert!((0..10_000).all(|i| filter.lookup(&format!("key-{}", i))));
+
+        // About 2 * 4 / 2^12 at full load, less here
+        let false_positives = (10_000..110_000)
+            .filter(|i| filter.lookup(&format!("key-{}", i)))
+            .count();
+        assert!(false_positives < 250, "{}", false_positives);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut filter = CuckooFilter::new(100);
+        filter.insert("twice").unwrap();
+        filter.insert("twice").unwrap();
+        filter.insert("once").unwrap();
+        assert_eq!(filter.len(), 3);
+
+        assert!(filter.remove("twice"));
+        assert!(filter.lookup("twice"));
+        assert!(filter.remove("twice"));
+        assert!(!filter.lookup("twice"));
+        assert!(!filter.remove("twice"));
+        assert!(filter.lookup("once"));
+        assert_eq!(filter.len(), 1);
+        assert!(filter.remove("once"));
+        assert!(filter.is_empty());
+        assert_eq!(filter.count_occupied(), 0);
+    }
+
+    #[test]
+    fn test_full() {
+        let mut filter = CuckooFilterBuilder::new(100).max_kicks(20).build().unwrap();
+        let mut inserted = 0;
+        let error = loop {
+            match filter.insert(&format!("key-{}", inserted)) {
+                Ok(()) => inserted += 1,
+                Err(err) => break err,
+            }
+        };
+        assert!(inserted > 100 && inserted < filter.capacity());
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Filter is full: {} items in 128 slots, no room after 20 kicks",
+                inserted
+            )
+        );
+
+        // Failed inserts leave the filter as it was
+        let before = filter.clone();
+        let key = format!("key-{}", inserted);
+        assert!(filter.insert(&key).is_err());
+        assert_eq!(filter, before);
+        assert!((0..inserted).all(|i| filter.lookup(&format!("key-{}", i))));
+        assert!(!filter.lookup(&key));
+    }
+
+    #[test]
+    fn test_unbounded_kicks() {
+        let mut filter = CuckooFilterBuilder::new(100)
+            .max_kicks(usize::MAX)
+            .build()
+            .unwrap();
+        for i in 0..100 {
+            filter.insert(&format!("key-{}", i)).unwrap();
+        }
+        assert_eq!(filter.len(), 100);
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        let mut filter = CuckooFilterBuilder::new(100)
+            .fingerprint_bits(7)
+            .bucket_size(3)
+            .build()
+            .unwrap();
+        for i in 0..50 {
+            filter.insert(&format!("key-{}", i)).unwrap();
+        }
+
+        let bytes = filter.to_bytes();
+        assert_eq!(&bytes[..6], b"BFYS\x04\x01");
+        assert_eq!(&bytes[6..], filter.encode());
+        assert_eq!(CuckooFilter::from_bytes(&bytes).unwrap(), filter);
+
+        assert_eq!(
+            BloomFilter::from_bytes(&bytes).unwrap_err().to_string(),
+            "Unsupported filter: Cuckoo filter file, expected Bloom"
+        );
+        let bloom_filter = BloomFilter::new(10).to_bytes();
+        assert!(CuckooFilter::from_bytes(&bloom_filter).is_err());
+    }
+
+    #[test]
+    fn test_persist_local_file() {
+        prepare_tmp_dir();
+        let test_file = "tmp/cuckoo_filter_test_persist_local_file.bin";
+        let mut filter = CuckooFilter::new(10);
+        filter.insert("abound").unwrap();
+        filter.to_file(test_file).unwrap();
+
+        let loaded = CuckooFilter::from_file(test_file).unwrap();
+        assert_eq!(loaded, filter);
+        assert!(loaded.lookup("abound"));
+
+        fs::remove_file(test_file).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use super::{CuckooFilter, BUCKET_SIZE, FINGERPRINT_BITS, MAX_KICKS};
+use crate::error::BloomFilterError;
+use crate::format::Persistable;
+use anyhow::Result;
+
+// Buckets are added past this load, where inserts start failing
+const MAX_LOAD_FACTOR: f64 = 0.96;
+
+pub struct CuckooFilterBuilder {
+    capacity: usize,
+    fingerprint_bits: u32,
+    bucket_size: usize,
+    max_kicks: usize,
+}
+
+impl CuckooFilterBuilder {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            fingerprint_bits: FINGERPRINT_BITS,
+            bucket_size: BUCKET_SIZE,
+            max_kicks: MAX_KICKS,
+        }
+    }
+
+    // 2 to 32, each bit halves the false positive rate
+    pub fn fingerprint_bits(mut self, fingerprint_bits: u32) -> Self {
+        self.fingerprint_bits = fingerprint_bits;
+        self
+    }
+
+    // 1 to 8 slots, larger buckets load further but match more
+    pub fn bucket_size(mut self, bucket_size: usize) -> Self {
+        self.bucket_size = bucket_size;
+        self
+    }
+
+    // Relocations an insert may try before the filter is full
+    pub fn max_kicks(mut self, max_kicks: usize) -> Self {
+        self.max_kicks = max_kicks;
+        self
+    }
+
+    pub fn build(self) -> Result<CuckooFilter> {
+        if !(2..=32).contains(&self.fingerprint_bits) {
+            let reason = "fingerprint bits must be in [2, 32]".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        if !(1..=8).contains(&self.bucket_size) {
+            let reason = "bucket size must be in [1, 8]".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+
+        let mut bucket_count = (self.capacity / self.bucket_size)
+            .max(1)
+            .checked_next_power_of_two();
+        if let Some(count) = bucket_count {
+            if self.capacity as f64 / (count as f64 * self.bucket_size as f64) > MAX_LOAD_FACTOR {
+                bucket_count = count.checked_mul(2);
+            }
+        }
+        let table_bits = bucket_count
+            .and_then(|count| count.checked_mul(self.bucket_size))
+            .and_then(|slots| slots.checked_mul(self.fingerprint_bits as usize));
+        let (Some(bucket_count), Some(table_bits)) = (bucket_count, table_bits) else {
+            let reason = format!("capacity {} is too large", self.capacity);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        };
+
+        Ok(CuckooFilter {
+            table: vec![0; table_bits.div_ceil(8)],
+            bucket_count,
+            bucket_size: self.bucket_size,
+            fingerprint_bits: self.fingerprint_bits,
+            max_kicks: self.max_kicks,
+            len: 0,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<CuckooFilter> {
+        CuckooFilter::from_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        let filter = CuckooFilterBuilder::new(100).build().unwrap();
+        assert_eq!(filter.fingerprint_bits(), 12);
+        assert_eq!(filter.bucket_size(), 4);
+        assert_eq!(filter.bucket_count, 32);
+        assert_eq!(filter.table.len(), 32 * 4 * 12 / 8);
+        assert_eq!(filter.max_kicks, 500);
+    }
+
+    #[test]
+    fn test_sizing() {
+        // 124 in 128 slots is past the maximum load
+        let filter = CuckooFilterBuilder::new(124).build().unwrap();
+        assert_eq!(filter.capacity(), 256);
+        let filter = CuckooFilterBuilder::new(0).build().unwrap();
+        assert_eq!(filter.capacity(), 4);
+        let filter = CuckooFilterBuilder::new(100)
+            .fingerprint_bits(5)
+            .bucket_size(1)
+            .build()
+            .unwrap();
+        assert_eq!(filter.capacity(), 128);
+        assert_eq!(filter.table.len(), 80);
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let build = |fingerprint_bits, bucket_size| {
+            CuckooFilterBuilder::new(100)
+                .fingerprint_bits(fingerprint_bits)
+                .bucket_size(bucket_size)
+                .build()
+        };
+        assert!(build(2, 1).is_ok());
+        assert!(build(32, 8).is_ok());
+        assert!(build(1, 4).is_err());
+        assert!(build(33, 4).is_err());
+        assert!(build(12, 0).is_err());
+        assert!(build(12, 9).is_err());
+        assert!(CuckooFilterBuilder::new(usize::MAX).build().is_err());
+    }
+}
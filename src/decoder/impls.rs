@@ -1,16 +1,42 @@
 use super::Decodable;
-use crate::bloom_filter::{BitArray, BloomFilter, CompressMode};
+use crate::binary_fuse_filter::{self, BinaryFuseFilter};
+use crate::bip37::read_compact_size;
+use crate::bloom_filter::{BitArray, BloomFilter, CompressMode, HashScheme, Layout};
 use crate::compressor::lzw;
+use crate::count_min_sketch::{CountMinSketch, TopK};
+use crate::cuckoo_filter::CuckooFilter;
+use crate::encoder::Encodable;
 use crate::error::BloomFilterError;
+use crate::fast_local_bloom_filter::{self, FastLocalBloomFilter};
+use crate::gcs_filter::GcsFilter;
+use crate::hyperloglog::{self, HyperLogLog, Registers};
+use crate::invertible_bloom_lookup_table::{Cell, InvertibleBloomLookupTable};
+use crate::leveldb_bloom_filter::{self, LevelDbBloomFilter};
+use crate::quotient_filter::{self, QuotientFilter};
+use crate::ribbon_filter::{self, RibbonFilter};
+use crate::rng::SplitMix64;
+use crate::rotating_bloom_filter::{Clock, Generation, RotatingBloomFilter};
+use crate::split_block_bloom_filter::{self, SplitBlockBloomFilter};
+use crate::stable_bloom_filter::StableBloomFilter;
+use crate::xor_filter::{Fingerprint, XorFilter};
 use anyhow::Result;
+use std::collections::{BTreeMap, VecDeque};
 
 impl Decodable for BitArray {
     fn decode(bytes: &[u8]) -> Result<Self> {
-        let buffer = &bytes[..8];
-        let byte_size = u64::from_be_bytes(buffer.try_into()?) as usize;
-        let byte_data = &bytes[8..8 + byte_size];
-        let buffer = &bytes[(8 + byte_size)..];
-        let size = u64::from_be_bytes(buffer.try_into()?) as usize;
+        let buffer = bytes.get(..8).ok_or(BloomFilterError::InvalidFormat)?;
+        let byte_size = usize::try_from(u64::from_be_bytes(buffer.try_into()?))?;
+        let data_end = 8usize
+            .checked_add(byte_size)
+            .ok_or(BloomFilterError::InvalidFormat)?;
+        let byte_data = bytes
+            .get(8..data_end)
+            .ok_or(BloomFilterError::InvalidFormat)?;
+        let buffer = &bytes[data_end..];
+        if buffer.len() != 8 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let size = usize::try_from(u64::from_be_bytes(buffer.try_into()?))?;
 
         let bit_array = Self {
             byte_array: byte_data.into(),
@@ -30,16 +56,55 @@ impl Decodable for CompressMode {
     }
 }
 
+// Reads the scheme at the start of bytes, its parameters included
+impl Decodable for HashScheme {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        match bytes.first() {
+            Some(0) => Ok(Self::Murmur3Fnv),
+            Some(1) => Ok(Self::RedisBloom),
+            Some(2) => Ok(Self::Guava),
+            Some(3) => {
+                let tweak = bytes.get(1..5).ok_or(BloomFilterError::InvalidFormat)?;
+                Ok(Self::Bip37 {
+                    tweak: u32::from_le_bytes(tweak.try_into()?),
+                })
+            }
+            Some(4) => Ok(Self::BitsAndBlooms),
+            _ => Err(BloomFilterError::InvalidFormat.into()),
+        }
+    }
+}
+
+// Reads the layout at the start of bytes
+impl Decodable for Layout {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        match bytes.first() {
+            Some(0) => Ok(Self::Standard),
+            Some(1) => Ok(Self::Partitioned),
+            _ => Err(BloomFilterError::InvalidFormat.into()),
+        }
+    }
+}
+
+// Hash scheme and layout, then the bit array, hash count and compress mode
 impl Decodable for BloomFilter {
     fn decode(bytes: &[u8]) -> Result<Self> {
+        let hash_scheme = HashScheme::decode(bytes)?;
+        let bytes = &bytes[hash_scheme.encode().len()..];
+        let layout = Layout::decode(bytes)?;
+        let bytes = &bytes[layout.encode().len()..];
+        if bytes.len() < 9 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
         let split_idx = bytes.len() - 9;
         let bit_array_buffer = &bytes[..split_idx];
         let buffer = &bytes[split_idx..(bytes.len() - 1)];
-        let hash_count = u64::from_be_bytes(buffer.try_into()?) as usize;
+        let hash_count = u64::from_be_bytes(buffer.try_into()?);
+        let hash_count = usize::try_from(hash_count).unwrap_or(usize::MAX);
         let last_byte = *bytes.last().ok_or(BloomFilterError::InvalidFormat)?;
         let compress_mode = CompressMode::decode(&[last_byte])?;
         let decompressed_bit_array = match compress_mode {
-            CompressMode::Lzw => &lzw::decompress(bit_array_buffer),
+            CompressMode::Lzw => &lzw::decompress(bit_array_buffer)?,
             _ => bit_array_buffer,
         };
 
@@ -47,11 +112,496 @@ impl Decodable for BloomFilter {
             bit_array: BitArray::decode(decompressed_bit_array)?,
             hash_count,
             compress_mode,
+            hash_scheme,
+            layout,
         };
+        bloom_filter.validate()?;
         Ok(bloom_filter)
     }
 }
 
+// Trailing bytes past the bitset, such as the rest of a Parquet file, are
+// ignored
+impl Decodable for SplitBlockBloomFilter {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let (num_bytes, header_size) = split_block_bloom_filter::read_header(bytes)?;
+        let bitset = bytes
+            .get(header_size..header_size + num_bytes)
+            .ok_or(BloomFilterError::InvalidFormat)?;
+        Self::from_bitset(bitset)
+    }
+}
+
+// The whole input is the filter, trailing bytes are an error
+impl Decodable for GcsFilter {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let (item_count, data) = read_compact_size(bytes)?;
+        let filter = Self {
+            item_count,
+            data: data.to_vec(),
+        };
+        filter.validate()?;
+        Ok(filter)
+    }
+}
+
+// LevelDB matches every key against a block with more than 30 probes,
+// reserved for other encodings, these are refused instead
+impl Decodable for LevelDbBloomFilter {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let (&hash_count, data) = bytes.split_last().ok_or(BloomFilterError::InvalidFormat)?;
+        let hash_count = hash_count as usize;
+        if data.is_empty() || hash_count == 0 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        if hash_count > leveldb_bloom_filter::MAX_HASH_COUNT {
+            let reason = format!("{} probes, LevelDB Bloom filters use 1 to 30", hash_count);
+            return Err(BloomFilterError::Unsupported(reason).into());
+        }
+        Ok(Self {
+            data: data.to_vec(),
+            hash_count,
+        })
+    }
+}
+
+// Blocks written by other RocksDB filter implementations are refused, those
+// too short for the metadata read as empty like RocksDB does
+impl Decodable for FastLocalBloomFilter {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let Some(split_idx) = bytes
+            .len()
+            .checked_sub(fast_local_bloom_filter::METADATA_SIZE)
+        else {
+            return Ok(Self {
+                data: Vec::new(),
+                hash_count: 0,
+            });
+        };
+        let (data, metadata) = bytes.split_at(split_idx);
+        let unsupported =
+            |reason: &str| Err(BloomFilterError::Unsupported(reason.to_string()).into());
+        match metadata[0] as i8 {
+            -1 => {}
+            -2 => return unsupported("Ribbon filter block, expected FastLocalBloom"),
+            1.. => return unsupported("legacy Bloom filter block, expected FastLocalBloom"),
+            _ => return Err(BloomFilterError::InvalidFormat.into()),
+        }
+        if metadata[1] != 0 {
+            return unsupported("unknown Bloom implementation, expected FastLocalBloom");
+        }
+        if metadata[2] >> 5 != 0 {
+            return unsupported("cache lines other than 64 bytes");
+        }
+        let hash_count = (metadata[2] & 31) as usize;
+        if hash_count == 0
+            || hash_count > 30
+            || metadata[3..] != [0, 0]
+            || !data
+                .len()
+                .is_multiple_of(fast_local_bloom_filter::CACHE_LINE_SIZE)
+        {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(Self {
+            data: data.to_vec(),
+            hash_count,
+        })
+    }
+}
+
+// Parameters are checked the way the builder does, and the length against
+// the occupied slots so removals cannot underflow
+impl Decodable for CuckooFilter {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 26 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let fingerprint_bits = bytes[0] as u32;
+        let bucket_size = bytes[1] as usize;
+        let bucket_count = u64::from_be_bytes(bytes[2..10].try_into()?);
+        let max_kicks = u64::from_be_bytes(bytes[10..18].try_into()?);
+        let len = u64::from_be_bytes(bytes[18..26].try_into()?);
+        let table = &bytes[26..];
+        if !(2..=32).contains(&fingerprint_bits)
+            || !(1..=8).contains(&bucket_size)
+            || !bucket_count.is_power_of_two()
+        {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let table_bits = bucket_count
+            .checked_mul((bucket_size * fingerprint_bits as usize) as u64)
+            .ok_or(BloomFilterError::InvalidFormat)?;
+        if table.len() as u64 != table_bits.div_ceil(8) {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+
+        let filter = Self {
+            table: table.to_vec(),
+            bucket_count: bucket_count as usize,
+            bucket_size,
+            fingerprint_bits,
+            max_kicks: max_kicks as usize,
+            len: len as usize,
+        };
+        if filter.count_occupied() as u64 != len {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(filter)
+    }
+}
+
+// The length is checked against the stored fingerprints
+impl Decodable for QuotientFilter {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 10 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let quotient_bits = bytes[0] as u32;
+        let remainder_bits = bytes[1] as u32;
+        let len = u64::from_be_bytes(bytes[2..10].try_into()?);
+        if !(1..=quotient_filter::MAX_QUOTIENT_BITS).contains(&quotient_bits)
+            || !(1..=quotient_filter::MAX_REMAINDER_BITS).contains(&remainder_bits)
+        {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let table_bits = (1u64 << quotient_bits) * (remainder_bits as u64 + 3);
+        if bytes[10..].len() as u64 != table_bits.div_ceil(8) {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+
+        let filter = Self {
+            table: bytes[10..].to_vec(),
+            quotient_bits,
+            remainder_bits,
+            len: len as usize,
+        };
+        // Also checks the slot flags, lookups on broken ones may not end
+        if filter.fingerprints()?.len() as u64 != len {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(filter)
+    }
+}
+
+impl<F: Fingerprint> Decodable for XorFilter<F> {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let (seed, data) = read_fingerprint_header::<F>(bytes)?;
+        let block_length = u64::from_be_bytes(data[..8].try_into()?);
+        let fingerprints = read_fingerprints::<F>(&data[8..])?;
+        if block_length == 0 || block_length.checked_mul(3) != Some(fingerprints.len() as u64) {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(Self {
+            seed,
+            block_length: block_length as usize,
+            fingerprints,
+        })
+    }
+}
+
+impl<F: Fingerprint> Decodable for BinaryFuseFilter<F> {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let (seed, data) = read_fingerprint_header::<F>(bytes)?;
+        let segment_length = u32::from_be_bytes(data[..4].try_into()?) as usize;
+        let segment_count = u32::from_be_bytes(data[4..8].try_into()?) as usize;
+        let fingerprints = read_fingerprints::<F>(&data[8..])?;
+        if !segment_length.is_power_of_two()
+            || segment_length > binary_fuse_filter::MAX_SEGMENT_LENGTH
+            || segment_count == 0
+            || (segment_count + 2) * segment_length != fingerprints.len()
+        {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(Self {
+            seed,
+            segment_length,
+            segment_count,
+            fingerprints,
+        })
+    }
+}
+
+impl Decodable for RibbonFilter {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 17 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let result_bits = bytes[0] as u32;
+        let seed = u64::from_be_bytes(bytes[1..9].try_into()?);
+        let num_slots = u64::from_be_bytes(bytes[9..17].try_into()?);
+        let words = &bytes[17..];
+        let coeff_bits = ribbon_filter::COEFF_BITS as u64;
+        if !(1..=ribbon_filter::MAX_RESULT_BITS).contains(&result_bits)
+            || num_slots == 0
+            || !num_slots.is_multiple_of(coeff_bits)
+            || (num_slots / coeff_bits).checked_mul(result_bits as u64 * 16)
+                != Some(words.len() as u64)
+        {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(Self {
+            seed,
+            result_bits,
+            num_slots: num_slots as usize,
+            solution: words
+                .chunks_exact(16)
+                .map(|word| u128::from_le_bytes(word.try_into().unwrap()))
+                .collect(),
+        })
+    }
+}
+
+// Fingerprint bits and seed, leaving at least the 8 bytes of sizing
+fn read_fingerprint_header<F: Fingerprint>(bytes: &[u8]) -> Result<(u64, &[u8])> {
+    if bytes.len() < 17 {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+    if bytes[0] as u32 != F::BITS {
+        let reason = format!("{} bit fingerprints, expected {}", bytes[0], F::BITS);
+        return Err(BloomFilterError::Unsupported(reason).into());
+    }
+    Ok((u64::from_be_bytes(bytes[1..9].try_into()?), &bytes[9..]))
+}
+
+fn read_fingerprints<F: Fingerprint>(bytes: &[u8]) -> Result<Vec<F>> {
+    let width = F::BITS as usize / 8;
+    if !bytes.len().is_multiple_of(width) {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+    Ok(bytes.chunks_exact(width).map(F::read_le).collect())
+}
+
+impl Decodable for StableBloomFilter {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 33 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let cell_bits = bytes[0] as u32;
+        let hash_count = u64::from_be_bytes(bytes[1..9].try_into()?);
+        let decrement = u64::from_be_bytes(bytes[9..17].try_into()?);
+        let cell_count = u64::from_be_bytes(bytes[17..25].try_into()?);
+        let state = u64::from_be_bytes(bytes[25..33].try_into()?);
+        let cells = &bytes[33..];
+        if !(1..=8).contains(&cell_bits)
+            || !(1..=cell_count).contains(&hash_count)
+            || decrement == 0
+            || cell_count
+                .checked_mul(cell_bits as u64)
+                .map(|bits| bits.div_ceil(8))
+                != Some(cells.len() as u64)
+        {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(Self {
+            cells: cells.to_vec(),
+            cell_count: cell_count as usize,
+            cell_bits,
+            hash_count: hash_count as usize,
+            decrement: decrement as usize,
+            rng: SplitMix64 { state },
+        })
+    }
+}
+
+// Generations must be in start order and share the first one's size and
+// hashing
+impl<C: Clock + Default> Decodable for RotatingBloomFilter<C> {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 24 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let generation_count = u64::from_be_bytes(bytes[..8].try_into()?);
+        let window = u64::from_be_bytes(bytes[8..16].try_into()?);
+        let stored = u64::from_be_bytes(bytes[16..24].try_into()?);
+        if window == 0 || stored == 0 || stored > generation_count {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+
+        let mut generations: VecDeque<Generation> = VecDeque::new();
+        let mut rest = &bytes[24..];
+        for _ in 0..stored {
+            if rest.len() < 16 {
+                return Err(BloomFilterError::InvalidFormat.into());
+            }
+            let started = u64::from_be_bytes(rest[..8].try_into()?);
+            let len = u64::from_be_bytes(rest[8..16].try_into()?);
+            let filter_bytes = usize::try_from(len)
+                .ok()
+                .and_then(|len| 16usize.checked_add(len))
+                .and_then(|end| rest.get(16..end))
+                .ok_or(BloomFilterError::InvalidFormat)?;
+            let bloom_filter = BloomFilter::from_bytes(filter_bytes)?;
+            if let Some(previous) = generations.back() {
+                let first = &generations[0].bloom_filter;
+                if started <= previous.started
+                    || bloom_filter.bit_array.size != first.bit_array.size
+                    || bloom_filter.hash_count != first.hash_count
+                    || bloom_filter.hash_scheme != first.hash_scheme
+                    || bloom_filter.layout != first.layout
+                {
+                    return Err(BloomFilterError::InvalidFormat.into());
+                }
+            }
+            generations.push_back(Generation {
+                started,
+                bloom_filter,
+            });
+            rest = &rest[16 + filter_bytes.len()..];
+        }
+        if !rest.is_empty() {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(Self::from_generations(
+            generations,
+            generation_count as usize,
+            window,
+        ))
+    }
+}
+
+impl Decodable for CountMinSketch {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 41 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let width = u64::from_be_bytes(bytes[..8].try_into()?);
+        let depth = u64::from_be_bytes(bytes[8..16].try_into()?);
+        let conservative = match bytes[16] {
+            0 => false,
+            1 => true,
+            _ => return Err(BloomFilterError::InvalidFormat.into()),
+        };
+        let total = u64::from_be_bytes(bytes[17..25].try_into()?);
+        let k = u64::from_be_bytes(bytes[25..33].try_into()?) as usize;
+        let entry_count = u64::from_be_bytes(bytes[33..41].try_into()?);
+        if width == 0 || depth == 0 || entry_count > k as u64 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+
+        let mut heavy_hitters = (k > 0).then(|| TopK::new(k));
+        let mut rest = &bytes[41..];
+        for _ in 0..entry_count {
+            let count = rest.get(..8).ok_or(BloomFilterError::InvalidFormat)?;
+            let len = rest.get(8..16).ok_or(BloomFilterError::InvalidFormat)?;
+            let len = u64::from_be_bytes(len.try_into()?);
+            let key = usize::try_from(len)
+                .ok()
+                .and_then(|len| 16usize.checked_add(len))
+                .and_then(|end| rest.get(16..end))
+                .ok_or(BloomFilterError::InvalidFormat)?;
+            if let Some(top_k) = &mut heavy_hitters {
+                top_k.offer(key, u64::from_be_bytes(count.try_into()?));
+            }
+            rest = &rest[16 + key.len()..];
+        }
+        let counters = width
+            .checked_mul(depth)
+            .and_then(|counters| counters.checked_mul(8));
+        if counters != Some(rest.len() as u64) {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+
+        Ok(Self {
+            counters: rest
+                .chunks_exact(8)
+                .map(|counter| u64::from_be_bytes(counter.try_into().unwrap()))
+                .collect(),
+            width: width as usize,
+            depth: depth as usize,
+            conservative,
+            total,
+            heavy_hitters,
+        })
+    }
+}
+
+impl Decodable for HyperLogLog {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let (&precision, rest) = bytes.split_first().ok_or(BloomFilterError::InvalidFormat)?;
+        let precision = precision as u32;
+        let (&representation, rest) = rest.split_first().ok_or(BloomFilterError::InvalidFormat)?;
+        let mut sketch = Self::new(precision).map_err(|_| BloomFilterError::InvalidFormat)?;
+        // Ranks go up to 65 less the bits of the index
+        let valid = |rank: u8, index_bits: u32| (1..=65 - index_bits).contains(&(rank as u32));
+        sketch.registers = match representation {
+            0 => {
+                let count = rest.get(..4).ok_or(BloomFilterError::InvalidFormat)?;
+                let count = u32::from_be_bytes(count.try_into()?) as usize;
+                let entries = &rest[4..];
+                if count > sketch.sparse_limit() || entries.len() != count * 4 {
+                    return Err(BloomFilterError::InvalidFormat.into());
+                }
+                let mut sparse = BTreeMap::new();
+                let mut last = None;
+                for entry in entries.chunks_exact(4) {
+                    let entry = u32::from_be_bytes(entry.try_into()?);
+                    let (index, rank) = (entry >> 6, (entry & 0x3f) as u8);
+                    // Strictly increasing indices within the sparse precision
+                    if last.is_some_and(|last| index <= last)
+                        || index >= 1 << hyperloglog::SPARSE_PRECISION
+                        || !valid(rank, hyperloglog::SPARSE_PRECISION)
+                    {
+                        return Err(BloomFilterError::InvalidFormat.into());
+                    }
+                    sparse.insert(index, rank);
+                    last = Some(index);
+                }
+                Registers::Sparse(sparse)
+            }
+            1 => {
+                if rest.len() != 1 << precision
+                    || rest
+                        .iter()
+                        .any(|&rank| rank != 0 && !valid(rank, precision))
+                {
+                    return Err(BloomFilterError::InvalidFormat.into());
+                }
+                Registers::Dense(rest.to_vec())
+            }
+            _ => return Err(BloomFilterError::InvalidFormat.into()),
+        };
+        Ok(sketch)
+    }
+}
+
+impl Decodable for InvertibleBloomLookupTable {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 24 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let hash_count = u64::from_be_bytes(bytes[..8].try_into()?);
+        let key_size = u64::from_be_bytes(bytes[8..16].try_into()?);
+        let cell_count = u64::from_be_bytes(bytes[16..24].try_into()?);
+        if hash_count == 0
+            || !(1..=u16::MAX as u64).contains(&key_size)
+            || cell_count == 0
+            || cell_count % hash_count != 0
+        {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let cell_size = key_size as usize + 18;
+        let rest = &bytes[24..];
+        if cell_count.checked_mul(cell_size as u64) != Some(rest.len() as u64) {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+
+        let cells = rest
+            .chunks_exact(cell_size)
+            .map(|cell| Cell {
+                count: i64::from_be_bytes(cell[..8].try_into().unwrap()),
+                key_sum: cell[8..cell_size - 8].to_vec(),
+                hash_sum: u64::from_be_bytes(cell[cell_size - 8..].try_into().unwrap()),
+            })
+            .collect();
+        Ok(Self {
+            cells,
+            hash_count: hash_count as usize,
+            key_size: key_size as usize,
+        })
+    }
+}
+
 #[cfg(test)]
 mod decodable {
     mod bit_array {
@@ -70,17 +620,28 @@ mod decodable {
             assert!(!bit_array[0]);
             assert!(bit_array[5]);
             assert!(bit_array[9]);
+
+            for len in 0..encoded.len() {
+                assert!(BitArray::decode(&encoded[..len]).is_err(), "{}", len);
+            }
+            assert!(BitArray::decode(&[&encoded[..], &[0]].concat()).is_err());
+            let mut huge = encoded.clone();
+            huge[..8].fill(0xff);
+            assert!(BitArray::decode(&huge).is_err());
         }
     }
 
     mod bloom_filter {
         use crate::compressor::lzw;
         use crate::decoder::Decodable;
-        use crate::{BloomFilter, CompressMode};
+        use crate::encoder::Encodable;
+        use crate::{BloomFilter, BloomFilterBuilder, CompressMode, HashScheme, Layout};
 
         #[test]
         fn test_decode_without_compression() {
             let encoded = vec![
+                0, // Hash scheme
+                0, // Layout
                 0, 0, 0, 0, 0, 0, 0, 3, // BitArray: byte size
                 0b10000100, 0b00100001, 0, // BitArray: byte data
                 0, 0, 0, 0, 0, 0, 0, 20, // BitArray: bin size
@@ -103,7 +664,8 @@ mod decodable {
                 0b10000100, 0b00100001, 0, // BitArray: byte data
                 0, 0, 0, 0, 0, 0, 0, 20, // BitArray: bin size
             ];
-            let mut encoded = lzw::compress(&encoded_bit_array);
+            let mut encoded = vec![0, 0]; // Hash scheme, layout
+            encoded.extend(lzw::compress(&encoded_bit_array));
             encoded.extend([
                 0, 0, 0, 0, 0, 0, 0, 7, // Number of hash functions
                 1, // Compress mode
@@ -117,5 +679,552 @@ mod decodable {
             assert!(bloom_filter.lookup("test"));
             assert!(!bloom_filter.lookup("test1"));
         }
+
+        #[test]
+        fn test_decode_scheme_and_layout() {
+            let mut bloom_filter = BloomFilterBuilder::new(10).partitioned().build();
+            bloom_filter.insert("test");
+            let decoded = BloomFilter::decode(&bloom_filter.encode()).unwrap();
+            assert_eq!(decoded, bloom_filter);
+            assert_eq!(decoded.layout, Layout::Partitioned);
+
+            let mut bloom_filter = BloomFilterBuilder::new(10).build();
+            bloom_filter.hash_scheme = HashScheme::Bip37 { tweak: 7 };
+            bloom_filter.insert("test");
+            let decoded = BloomFilter::decode(&bloom_filter.encode()).unwrap();
+            assert_eq!(decoded.hash_scheme, HashScheme::Bip37 { tweak: 7 });
+            assert!(decoded.lookup("test"));
+
+            // Only the default scheme can be partitioned
+            let mut encoded = bloom_filter.encode();
+            encoded[5] = 1;
+            assert!(BloomFilter::decode(&encoded).is_err());
+            assert!(BloomFilter::decode(&[5, 0]).is_err());
+            assert!(BloomFilter::decode(&[0, 2]).is_err());
+            assert!(BloomFilter::decode(&[0]).is_err());
+        }
+
+        #[test]
+        fn test_decode_truncated() {
+            for compress in [false, true] {
+                let mut builder = BloomFilterBuilder::new(100);
+                if !compress {
+                    builder = builder.no_compress();
+                }
+                let mut bloom_filter = builder.build();
+                bloom_filter.insert("test");
+                let encoded = bloom_filter.encode();
+                for len in 0..encoded.len() {
+                    assert!(BloomFilter::decode(&encoded[..len]).is_err(), "{}", len);
+                }
+                assert!(BloomFilter::decode(&[&encoded[..], &[0]].concat()).is_err());
+            }
+        }
+
+        #[test]
+        fn test_decode_invalid() {
+            let encoded = |size: u8, hash_count: [u8; 8]| {
+                let mut bytes = vec![
+                    0, // Hash scheme
+                    0, // Layout
+                    0, 0, 0, 0, 0, 0, 0, 1,    // BitArray: byte size
+                    0xff, // BitArray: byte data
+                    0, 0, 0, 0, 0, 0, 0, size, // BitArray: bin size
+                ];
+                bytes.extend(hash_count);
+                bytes.push(0); // Compress mode
+                bytes
+            };
+            let seven = 7u64.to_be_bytes();
+            assert!(BloomFilter::decode(&encoded(8, seven)).is_ok());
+            assert!(BloomFilter::decode(&encoded(0, seven)).is_err());
+            assert!(BloomFilter::decode(&encoded(9, seven)).is_err());
+            // No probes would match every key, too many can't be allocated
+            assert!(BloomFilter::decode(&encoded(8, [0; 8])).is_err());
+            assert!(BloomFilter::decode(&encoded(8, [0xff; 8])).is_err());
+            assert!(BloomFilter::decode(&encoded(8, 1025u64.to_be_bytes())).is_err());
+
+            // Code 0x0102 is past the LZW dictionary
+            let mut lzw = vec![0, 0, 1, 2];
+            lzw.extend(seven);
+            lzw.push(1);
+            assert!(BloomFilter::decode(&lzw).is_err());
+        }
+    }
+
+    mod split_block_bloom_filter {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::SplitBlockBloomFilter;
+
+        #[test]
+        fn test_decode() {
+            let mut filter = SplitBlockBloomFilter::new(1024);
+            filter.insert("a");
+            let mut encoded = filter.encode();
+            assert_eq!(SplitBlockBloomFilter::decode(&encoded).unwrap(), filter);
+
+            encoded.push(0xff);
+            assert_eq!(SplitBlockBloomFilter::decode(&encoded).unwrap(), filter);
+            encoded.truncate(encoded.len() - 2);
+            assert!(SplitBlockBloomFilter::decode(&encoded).is_err());
+        }
+    }
+
+    mod gcs_filter {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::GcsFilter;
+
+        #[test]
+        fn test_decode() {
+            let filter = GcsFilter::new(&[7; 16], ["a", "b", "c"]).unwrap();
+            let encoded = filter.encode();
+            assert_eq!(GcsFilter::decode(&encoded).unwrap(), filter);
+
+            assert!(GcsFilter::decode(&encoded[..encoded.len() - 1]).is_err());
+            assert!(GcsFilter::decode(&[&encoded[..], &[0]].concat()).is_err());
+            let mut more_items = encoded.clone();
+            more_items[0] = 4;
+            assert!(GcsFilter::decode(&more_items).is_err());
+            // Single delta of 2^20, past N * M for N = 1
+            assert!(GcsFilter::decode(&[1, 0b1100_0000, 0, 0]).is_err());
+            assert!(GcsFilter::decode(&[]).is_err());
+        }
+    }
+
+    mod leveldb_bloom_filter {
+        use crate::decoder::Decodable;
+        use crate::LevelDbBloomFilter;
+
+        #[test]
+        fn test_decode() {
+            let filter = LevelDbBloomFilter::decode(&[1, 2, 3, 6]).unwrap();
+            assert_eq!(filter.data, [1, 2, 3]);
+            assert_eq!(filter.hash_count, 6);
+
+            assert!(LevelDbBloomFilter::decode(&[]).is_err());
+            assert!(LevelDbBloomFilter::decode(&[6]).is_err());
+            assert!(LevelDbBloomFilter::decode(&[1, 2, 3, 0]).is_err());
+            assert_eq!(
+                LevelDbBloomFilter::decode(&[1, 2, 3, 31])
+                    .unwrap_err()
+                    .to_string(),
+                "Unsupported filter: 31 probes, LevelDB Bloom filters use 1 to 30"
+            );
+        }
+    }
+
+    mod fast_local_bloom_filter {
+        use crate::decoder::Decodable;
+        use crate::FastLocalBloomFilter;
+
+        fn block(metadata: [u8; 5]) -> Vec<u8> {
+            let mut block = vec![0xaa; 64];
+            block.extend_from_slice(&metadata);
+            block
+        }
+
+        #[test]
+        fn test_decode() {
+            let filter = FastLocalBloomFilter::decode(&block([0xff, 0, 6, 0, 0])).unwrap();
+            assert_eq!(filter.data, [0xaa; 64]);
+            assert_eq!(filter.hash_count, 6);
+
+            let error = |metadata| {
+                FastLocalBloomFilter::decode(&block(metadata))
+                    .unwrap_err()
+                    .to_string()
+            };
+            assert_eq!(
+                error([0xfe, 0, 0, 0, 0]),
+                "Unsupported filter: Ribbon filter block, expected FastLocalBloom"
+            );
+            assert_eq!(
+                error([6, 0, 0, 0, 0]),
+                "Unsupported filter: legacy Bloom filter block, expected FastLocalBloom"
+            );
+            assert_eq!(
+                error([0xff, 1, 6, 0, 0]),
+                "Unsupported filter: unknown Bloom implementation, expected FastLocalBloom"
+            );
+            assert_eq!(
+                error([0xff, 0, 0x26, 0, 0]),
+                "Unsupported filter: cache lines other than 64 bytes"
+            );
+            assert_eq!(error([0, 0, 6, 0, 0]), "Invalid format");
+            assert_eq!(error([0xff, 0, 0, 0, 0]), "Invalid format");
+            assert_eq!(error([0xff, 0, 6, 1, 0]), "Invalid format");
+            let mut partial_line = block([0xff, 0, 6, 0, 0]);
+            partial_line.remove(0);
+            assert!(FastLocalBloomFilter::decode(&partial_line).is_err());
+        }
+    }
+
+    mod cuckoo_filter {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::{CuckooFilter, CuckooFilterBuilder};
+
+        #[test]
+        fn test_decode() {
+            let mut filter = CuckooFilterBuilder::new(20)
+                .fingerprint_bits(9)
+                .build()
+                .unwrap();
+            filter.insert("key").unwrap();
+            filter.insert("other key").unwrap();
+            let encoded = filter.encode();
+            let decoded = CuckooFilter::decode(&encoded).unwrap();
+            assert_eq!(decoded, filter);
+            assert!(decoded.lookup("key"));
+
+            let corrupt = |i: usize, value: u8| {
+                let mut bytes = encoded.clone();
+                bytes[i] = value;
+                CuckooFilter::decode(&bytes)
+            };
+            assert!(corrupt(0, 1).is_err());
+            assert!(corrupt(1, 9).is_err());
+            assert!(corrupt(9, 3).is_err());
+            assert!(corrupt(25, 3).is_err());
+            assert!(CuckooFilter::decode(&encoded[..encoded.len() - 1]).is_err());
+            assert!(CuckooFilter::decode(&encoded[..20]).is_err());
+        }
+    }
+
+    mod quotient_filter {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::QuotientFilter;
+
+        #[test]
+        fn test_decode() {
+            let mut filter = QuotientFilter::with_bits(4, 7).unwrap();
+            filter.insert("key").unwrap();
+            filter.insert("other key").unwrap();
+            let encoded = filter.encode();
+            let decoded = QuotientFilter::decode(&encoded).unwrap();
+            assert_eq!(decoded, filter);
+            assert!(decoded.lookup("key"));
+
+            let corrupt = |i: usize, value: u8| {
+                let mut bytes = encoded.clone();
+                bytes[i] = value;
+                QuotientFilter::decode(&bytes)
+            };
+            assert!(corrupt(0, 0).is_err());
+            assert!(corrupt(0, 5).is_err());
+            assert!(corrupt(1, 33).is_err());
+            assert!(corrupt(9, 3).is_err());
+            assert!(QuotientFilter::decode(&encoded[..encoded.len() - 1]).is_err());
+            assert!(QuotientFilter::decode(&encoded[..9]).is_err());
+
+            // Two slots of 1 bit remainders: a shifted slot after an empty
+            // one, every slot shifted, a continuation in its canonical slot,
+            // an occupied quotient without a run, a head marked shifted in
+            // its canonical slot and an unsorted run
+            let table = |len: u8, table: u8| {
+                QuotientFilter::decode(&[1, 1, 0, 0, 0, 0, 0, 0, 0, len, table])
+            };
+            assert!(table(1, 0x40).is_err());
+            assert!(table(2, 0x44).is_err());
+            assert!(table(1, 0x03).is_err());
+            assert!(table(0, 0x01).is_err());
+            assert!(table(1, 0x05).is_err());
+            assert!(table(2, 0x69).is_err());
+            // Head in slot 0 then its continuation, as inserts lay them out
+            assert_eq!(table(2, 0xe1).unwrap().fingerprints().unwrap(), [0, 1]);
+        }
+    }
+
+    mod xor_filter {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::{Xor16Filter, Xor8Filter};
+
+        #[test]
+        fn test_decode() {
+            let filter = Xor8Filter::new(["a", "b", "c"]).unwrap();
+            let encoded = filter.encode();
+            let decoded = Xor8Filter::decode(&encoded).unwrap();
+            assert_eq!(decoded, filter);
+            assert!(decoded.lookup("b"));
+
+            assert_eq!(
+                Xor16Filter::decode(&encoded).unwrap_err().to_string(),
+                "Unsupported filter: 8 bit fingerprints, expected 16"
+            );
+            assert!(Xor8Filter::decode(&encoded[..encoded.len() - 1]).is_err());
+            assert!(Xor8Filter::decode(&encoded[..16]).is_err());
+            let mut zero_blocks = encoded[..17].to_vec();
+            zero_blocks[16] = 0;
+            assert!(Xor8Filter::decode(&zero_blocks).is_err());
+        }
+    }
+
+    mod binary_fuse_filter {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::BinaryFuse16Filter;
+
+        #[test]
+        fn test_decode() {
+            let filter = BinaryFuse16Filter::new(["a", "b", "c"]).unwrap();
+            let encoded = filter.encode();
+            let decoded = BinaryFuse16Filter::decode(&encoded).unwrap();
+            assert_eq!(decoded, filter);
+            assert!(decoded.lookup("c"));
+
+            let corrupt = |i: usize, value: u8| {
+                let mut bytes = encoded.clone();
+                bytes[i] = value;
+                BinaryFuse16Filter::decode(&bytes)
+            };
+            assert!(corrupt(0, 8).is_err());
+            // Segment length 3, then a count off by one
+            assert!(corrupt(12, 3).is_err());
+            assert!(corrupt(16, 2).is_err());
+            assert!(BinaryFuse16Filter::decode(&encoded[..encoded.len() - 1]).is_err());
+        }
+    }
+
+    mod ribbon_filter {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::RibbonFilter;
+
+        #[test]
+        fn test_decode() {
+            let filter = RibbonFilter::new(5, ["a", "b", "c"]).unwrap();
+            let encoded = filter.encode();
+            let decoded = RibbonFilter::decode(&encoded).unwrap();
+            assert_eq!(decoded, filter);
+            assert!(decoded.lookup("a"));
+
+            let corrupt = |i: usize, value: u8| {
+                let mut bytes = encoded.clone();
+                bytes[i] = value;
+                RibbonFilter::decode(&bytes)
+            };
+            assert!(corrupt(0, 0).is_err());
+            assert!(corrupt(0, 33).is_err());
+            assert!(corrupt(0, 4).is_err());
+            assert!(corrupt(16, 129).is_err());
+            assert!(RibbonFilter::decode(&encoded[..encoded.len() - 1]).is_err());
+            assert!(RibbonFilter::decode(&encoded[..16]).is_err());
+        }
+    }
+
+    mod stable_bloom_filter {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::{StableBloomFilter, StableBloomFilterBuilder};
+
+        #[test]
+        fn test_decode() {
+            let mut filter = StableBloomFilterBuilder::new(100)
+                .cell_bits(5)
+                .decrement(3)
+                .build()
+                .unwrap();
+            filter.insert("key");
+            let encoded = filter.encode();
+            let decoded = StableBloomFilter::decode(&encoded).unwrap();
+            assert_eq!(decoded, filter);
+            assert!(decoded.lookup("key"));
+
+            let corrupt = |i: usize, value: u8| {
+                let mut bytes = encoded.clone();
+                bytes[i] = value;
+                StableBloomFilter::decode(&bytes)
+            };
+            assert!(corrupt(0, 0).is_err());
+            assert!(corrupt(0, 9).is_err());
+            assert!(corrupt(0, 4).is_err());
+            // Hash count 0, then more hashes than cells
+            assert!(corrupt(8, 0).is_err());
+            assert!(corrupt(8, 101).is_err());
+            assert!(corrupt(16, 0).is_err());
+            assert!(StableBloomFilter::decode(&encoded[..encoded.len() - 1]).is_err());
+            assert!(StableBloomFilter::decode(&encoded[..32]).is_err());
+        }
+    }
+
+    mod rotating_bloom_filter {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::{BloomFilterBuilder, ManualClock, RotatingBloomFilter};
+        use std::time::Duration;
+
+        type Filter = RotatingBloomFilter<ManualClock>;
+
+        #[test]
+        fn test_decode() {
+            let clock = ManualClock::new(0);
+            let template = BloomFilterBuilder::new(10).build();
+            let window = Duration::from_secs(60);
+            let mut filter = Filter::with_clock(template, 3, window, clock.clone()).unwrap();
+            filter.insert("key");
+            clock.advance(window);
+            filter.insert("other key");
+            let encoded = filter.encode();
+            let mut decoded = Filter::decode(&encoded).unwrap();
+            assert_eq!(decoded, filter);
+            decoded.set_clock(clock);
+            assert!(decoded.lookup("key") && decoded.lookup("other key"));
+
+            let corrupt = |i: usize, value: u8| {
+                let mut bytes = encoded.clone();
+                bytes[i] = value;
+                Filter::decode(&bytes)
+            };
+            // Window 0, no generations, more than the generation count
+            assert!(corrupt(15, 0).is_err());
+            assert!(corrupt(23, 0).is_err());
+            assert!(corrupt(7, 1).is_err());
+            // Second generation starting with the first
+            let second = 40 + filter.generations().next().unwrap().1.to_bytes().len();
+            assert!(corrupt(second + 7, 0).is_err());
+            assert!(corrupt(second + 15, 0).is_err());
+            // Generation length overflowing the offset
+            let mut bytes = encoded.clone();
+            bytes[32..40].fill(0xff);
+            assert!(Filter::decode(&bytes).is_err());
+            assert!(Filter::decode(&encoded[..encoded.len() - 1]).is_err());
+            assert!(Filter::decode(&[encoded.as_slice(), &[0]].concat()).is_err());
+
+            let other = BloomFilterBuilder::new(20).build();
+            let mut mixed = Filter::with_clock(other, 3, window, ManualClock::new(60)).unwrap();
+            mixed.generations.push_front(filter.generations[0].clone());
+            assert!(Filter::decode(&mixed.encode()).is_err());
+        }
+    }
+
+    mod count_min_sketch {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::{CountMinSketch, CountMinSketchBuilder};
+
+        #[test]
+        fn test_decode() {
+            let mut sketch = CountMinSketchBuilder::new(0.1, 0.1)
+                .top_k(2)
+                .build()
+                .unwrap();
+            sketch.add("key", 4);
+            sketch.add("other key", 2);
+            sketch.add("third key", 1);
+            let encoded = sketch.encode();
+            let decoded = CountMinSketch::decode(&encoded).unwrap();
+            assert_eq!(decoded, sketch);
+            assert_eq!(decoded.estimate("key"), 4);
+            assert_eq!(decoded.top_k(), sketch.top_k());
+
+            let corrupt = |i: usize, value: u8| {
+                let mut bytes = encoded.clone();
+                bytes[i] = value;
+                CountMinSketch::decode(&bytes)
+            };
+            // Zero width, zero depth, unknown flag, more entries than k
+            assert!(corrupt(7, 0).is_err());
+            assert!(corrupt(15, 0).is_err());
+            assert!(corrupt(16, 2).is_err());
+            assert!(corrupt(40, 3).is_err());
+            // Key length past the end
+            assert!(corrupt(49, 0xff).is_err());
+            let mut bytes = encoded.clone();
+            bytes[49..57].fill(0xff);
+            assert!(CountMinSketch::decode(&bytes).is_err());
+            assert!(CountMinSketch::decode(&encoded[..encoded.len() - 1]).is_err());
+            assert!(CountMinSketch::decode(&encoded[..40]).is_err());
+
+            let plain = CountMinSketch::new(0.5, 0.5).unwrap();
+            assert_eq!(CountMinSketch::decode(&plain.encode()).unwrap(), plain);
+        }
+    }
+
+    mod hyperloglog {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::HyperLogLog;
+
+        #[test]
+        fn test_decode() {
+            let mut sketch = HyperLogLog::new(8).unwrap();
+            for i in 0..20 {
+                sketch.insert(&i.to_string());
+            }
+            assert!(sketch.is_sparse());
+            let encoded = sketch.encode();
+            let decoded = HyperLogLog::decode(&encoded).unwrap();
+            assert_eq!(decoded, sketch);
+            assert_eq!(decoded.estimate(), sketch.estimate());
+
+            let corrupt = |i: usize, value: u8| {
+                let mut bytes = encoded.clone();
+                bytes[i] = value;
+                HyperLogLog::decode(&bytes)
+            };
+            // Precision out of range, unknown representation, entry count
+            assert!(corrupt(0, 3).is_err());
+            assert!(corrupt(0, 19).is_err());
+            assert!(corrupt(1, 2).is_err());
+            assert!(corrupt(5, 21).is_err());
+            // Zero rank, indices out of order
+            assert!(corrupt(9, encoded[9] & 0xc0).is_err());
+            let mut swapped = encoded.clone();
+            swapped[6..14].rotate_left(4);
+            assert!(HyperLogLog::decode(&swapped).is_err());
+            assert!(HyperLogLog::decode(&encoded[..encoded.len() - 1]).is_err());
+            assert!(HyperLogLog::decode(&encoded[..1]).is_err());
+
+            for i in 20..1000 {
+                sketch.insert(&i.to_string());
+            }
+            let encoded = sketch.encode();
+            assert_eq!(HyperLogLog::decode(&encoded).unwrap(), sketch);
+            assert!(HyperLogLog::decode(&encoded[..encoded.len() - 1]).is_err());
+            let mut bytes = encoded.clone();
+            bytes[2] = 58;
+            assert!(HyperLogLog::decode(&bytes).is_err());
+        }
+    }
+
+    mod invertible_bloom_lookup_table {
+        use crate::decoder::Decodable;
+        use crate::encoder::Encodable;
+        use crate::{InvertibleBloomLookupTable, InvertibleBloomLookupTableBuilder};
+
+        #[test]
+        fn test_decode() {
+            let mut table = InvertibleBloomLookupTableBuilder::new(4)
+                .key_size(8)
+                .build()
+                .unwrap();
+            table.insert("key").unwrap();
+            table.remove("other").unwrap();
+            let encoded = table.encode();
+            let decoded = InvertibleBloomLookupTable::decode(&encoded).unwrap();
+            assert_eq!(decoded, table);
+            let difference = decoded.peel().unwrap();
+            assert_eq!(difference.left_only, [b"key"]);
+            assert_eq!(difference.right_only, [b"other"]);
+
+            let corrupt = |i: usize, value: u8| {
+                let mut bytes = encoded.clone();
+                bytes[i] = value;
+                InvertibleBloomLookupTable::decode(&bytes)
+            };
+            // Zero hash count, hash count not dividing the cells, zero key
+            // size, key size past u16, cell count off
+            assert!(corrupt(7, 0).is_err());
+            assert!(corrupt(7, 3).is_err());
+            assert!(corrupt(15, 0).is_err());
+            assert!(corrupt(13, 1).is_err());
+            assert!(corrupt(23, 44).is_err());
+            assert!(InvertibleBloomLookupTable::decode(&encoded[..encoded.len() - 1]).is_err());
+            assert!(InvertibleBloomLookupTable::decode(&encoded[..20]).is_err());
+        }
     }
 }
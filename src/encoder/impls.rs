@@ -1,6 +1,21 @@
 use super::Encodable;
-use crate::bloom_filter::{BitArray, BloomFilter, CompressMode};
+use crate::binary_fuse_filter::BinaryFuseFilter;
+use crate::bip37::write_compact_size;
+use crate::bloom_filter::{BitArray, BloomFilter, CompressMode, HashScheme, Layout};
 use crate::compressor::lzw;
+use crate::count_min_sketch::CountMinSketch;
+use crate::cuckoo_filter::CuckooFilter;
+use crate::fast_local_bloom_filter::FastLocalBloomFilter;
+use crate::gcs_filter::GcsFilter;
+use crate::hyperloglog::{HyperLogLog, Registers};
+use crate::invertible_bloom_lookup_table::InvertibleBloomLookupTable;
+use crate::leveldb_bloom_filter::LevelDbBloomFilter;
+use crate::quotient_filter::QuotientFilter;
+use crate::ribbon_filter::RibbonFilter;
+use crate::rotating_bloom_filter::{Clock, RotatingBloomFilter};
+use crate::split_block_bloom_filter::{self, SplitBlockBloomFilter};
+use crate::stable_bloom_filter::StableBloomFilter;
+use crate::xor_filter::{Fingerprint, XorFilter};
 
 impl Encodable for BitArray {
     fn encode(&self) -> Vec<u8> {
@@ -24,6 +39,32 @@ impl Encodable for CompressMode {
     }
 }
 
+impl Encodable for HashScheme {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            HashScheme::Murmur3Fnv => vec![0],
+            HashScheme::RedisBloom => vec![1],
+            HashScheme::Guava => vec![2],
+            HashScheme::Bip37 { tweak } => {
+                let mut encoded = vec![3];
+                encoded.extend_from_slice(&tweak.to_le_bytes());
+                encoded
+            }
+            HashScheme::BitsAndBlooms => vec![4],
+        }
+    }
+}
+
+impl Encodable for Layout {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Layout::Standard => vec![0],
+            Layout::Partitioned => vec![1],
+        }
+    }
+}
+
+// Hash scheme and layout, then the bit array, hash count and compress mode
 impl Encodable for BloomFilter {
     fn encode(&self) -> Vec<u8> {
         let encoded_bit_array = self.bit_array.encode();
@@ -32,8 +73,10 @@ impl Encodable for BloomFilter {
             _ => encoded_bit_array,
         };
 
-        let byte_size = compressed_bit_array.len() + 8 + 1;
+        let byte_size = 5 + 1 + compressed_bit_array.len() + 8 + 1;
         let mut encoded = Vec::with_capacity(byte_size);
+        encoded.extend_from_slice(&self.hash_scheme.encode());
+        encoded.extend_from_slice(&self.layout.encode());
         encoded.extend_from_slice(&compressed_bit_array);
         encoded.extend_from_slice(&(self.hash_count as u64).to_be_bytes());
         encoded.extend_from_slice(&self.compress_mode.encode());
@@ -42,6 +85,222 @@ impl Encodable for BloomFilter {
     }
 }
 
+// Thrift header followed by the bitset, as found at a Parquet column
+// chunk's bloom_filter_offset
+impl Encodable for SplitBlockBloomFilter {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(16 + self.num_bytes());
+        split_block_bloom_filter::write_header(self.num_bytes(), &mut encoded);
+        encoded.extend_from_slice(&self.bitset());
+        encoded
+    }
+}
+
+// BIP158 serialization, compact size item count then the coded deltas
+impl Encodable for GcsFilter {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(9 + self.data.len());
+        write_compact_size(self.item_count, &mut encoded);
+        encoded.extend_from_slice(&self.data);
+        encoded
+    }
+}
+
+// Filter block as LevelDB's `CreateFilter` writes it
+impl Encodable for LevelDbBloomFilter {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(self.data.len() + 1);
+        encoded.extend_from_slice(&self.data);
+        encoded.push(self.hash_count as u8);
+        encoded
+    }
+}
+
+// Full filter block, with the metadata trailer of RocksDB's new Bloom
+// filters
+impl Encodable for FastLocalBloomFilter {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(self.data.len() + 5);
+        encoded.extend_from_slice(&self.data);
+        encoded.extend_from_slice(&[0xff, 0, self.hash_count as u8, 0, 0]);
+        encoded
+    }
+}
+
+// Parameters, big endian counts, then the packed fingerprints
+impl Encodable for CuckooFilter {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(26 + self.table.len());
+        encoded.push(self.fingerprint_bits as u8);
+        encoded.push(self.bucket_size as u8);
+        encoded.extend_from_slice(&(self.bucket_count as u64).to_be_bytes());
+        encoded.extend_from_slice(&(self.max_kicks as u64).to_be_bytes());
+        encoded.extend_from_slice(&(self.len as u64).to_be_bytes());
+        encoded.extend_from_slice(&self.table);
+        encoded
+    }
+}
+
+// Quotient and remainder bits, big endian length, then the packed slots
+impl Encodable for QuotientFilter {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(10 + self.table.len());
+        encoded.push(self.quotient_bits as u8);
+        encoded.push(self.remainder_bits as u8);
+        encoded.extend_from_slice(&(self.len as u64).to_be_bytes());
+        encoded.extend_from_slice(&self.table);
+        encoded
+    }
+}
+
+// Fingerprint bits, big endian seed and block length, then the little
+// endian fingerprints
+impl<F: Fingerprint> Encodable for XorFilter<F> {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(17 + self.fingerprints.len() * F::BITS as usize / 8);
+        encoded.push(F::BITS as u8);
+        encoded.extend_from_slice(&self.seed.to_be_bytes());
+        encoded.extend_from_slice(&(self.block_length as u64).to_be_bytes());
+        for fingerprint in &self.fingerprints {
+            fingerprint.write_le(&mut encoded);
+        }
+        encoded
+    }
+}
+
+// Fingerprint bits, big endian seed, segment length and count, then the
+// little endian fingerprints
+impl<F: Fingerprint> Encodable for BinaryFuseFilter<F> {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(17 + self.fingerprints.len() * F::BITS as usize / 8);
+        encoded.push(F::BITS as u8);
+        encoded.extend_from_slice(&self.seed.to_be_bytes());
+        encoded.extend_from_slice(&(self.segment_length as u32).to_be_bytes());
+        encoded.extend_from_slice(&(self.segment_count as u32).to_be_bytes());
+        for fingerprint in &self.fingerprints {
+            fingerprint.write_le(&mut encoded);
+        }
+        encoded
+    }
+}
+
+// Result bits, big endian seed and slot count, then the interleaved
+// solution as little endian words
+impl Encodable for RibbonFilter {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(17 + self.solution.len() * 16);
+        encoded.push(self.result_bits as u8);
+        encoded.extend_from_slice(&self.seed.to_be_bytes());
+        encoded.extend_from_slice(&(self.num_slots as u64).to_be_bytes());
+        for word in &self.solution {
+            encoded.extend_from_slice(&word.to_le_bytes());
+        }
+        encoded
+    }
+}
+
+// Cell bits, big endian hash count, decrement, cell count and generator
+// state, then the packed cells
+impl Encodable for StableBloomFilter {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(33 + self.cells.len());
+        encoded.push(self.cell_bits as u8);
+        encoded.extend_from_slice(&(self.hash_count as u64).to_be_bytes());
+        encoded.extend_from_slice(&(self.decrement as u64).to_be_bytes());
+        encoded.extend_from_slice(&(self.cell_count as u64).to_be_bytes());
+        encoded.extend_from_slice(&self.rng.state.to_be_bytes());
+        encoded.extend_from_slice(&self.cells);
+        encoded
+    }
+}
+
+// Big endian generation count, window seconds and number of stored
+// generations, then each generation's start, byte length and Bloom filter
+// file bytes
+impl<C: Clock> Encodable for RotatingBloomFilter<C> {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(&(self.generation_count as u64).to_be_bytes());
+        encoded.extend_from_slice(&self.window.to_be_bytes());
+        encoded.extend_from_slice(&(self.generations.len() as u64).to_be_bytes());
+        for generation in &self.generations {
+            let bytes = generation.bloom_filter.to_bytes();
+            encoded.extend_from_slice(&generation.started.to_be_bytes());
+            encoded.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+            encoded.extend_from_slice(&bytes);
+        }
+        encoded
+    }
+}
+
+// Big endian dimensions, conservative flag and total, the top k with its
+// entries as count, key length and key, then the counters row by row
+impl Encodable for CountMinSketch {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(41 + self.counters.len() * 8);
+        encoded.extend_from_slice(&(self.width as u64).to_be_bytes());
+        encoded.extend_from_slice(&(self.depth as u64).to_be_bytes());
+        encoded.push(self.conservative as u8);
+        encoded.extend_from_slice(&self.total.to_be_bytes());
+        let k = self.heavy_hitters.as_ref().map_or(0, |top_k| top_k.k);
+        let entries: Vec<_> = self
+            .heavy_hitters
+            .iter()
+            .flat_map(|top_k| top_k.entries())
+            .collect();
+        encoded.extend_from_slice(&(k as u64).to_be_bytes());
+        encoded.extend_from_slice(&(entries.len() as u64).to_be_bytes());
+        for (count, key) in entries {
+            encoded.extend_from_slice(&count.to_be_bytes());
+            encoded.extend_from_slice(&(key.len() as u64).to_be_bytes());
+            encoded.extend_from_slice(key);
+        }
+        for counter in &self.counters {
+            encoded.extend_from_slice(&counter.to_be_bytes());
+        }
+        encoded
+    }
+}
+
+// Precision and representation, then the sparse entries as big endian
+// index << 6 | rank, or the dense registers
+impl Encodable for HyperLogLog {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = vec![self.precision as u8];
+        match &self.registers {
+            Registers::Sparse(sparse) => {
+                encoded.push(0);
+                encoded.extend_from_slice(&(sparse.len() as u32).to_be_bytes());
+                for (&index, &rank) in sparse {
+                    encoded.extend_from_slice(&(index << 6 | rank as u32).to_be_bytes());
+                }
+            }
+            Registers::Dense(registers) => {
+                encoded.push(1);
+                encoded.extend_from_slice(registers);
+            }
+        }
+        encoded
+    }
+}
+
+// Big endian hash count, key size and cell count, then each cell's count,
+// key sum and hash sum
+impl Encodable for InvertibleBloomLookupTable {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(24 + self.cells.len() * (self.key_size + 18));
+        encoded.extend_from_slice(&(self.hash_count as u64).to_be_bytes());
+        encoded.extend_from_slice(&(self.key_size as u64).to_be_bytes());
+        encoded.extend_from_slice(&(self.cells.len() as u64).to_be_bytes());
+        for cell in &self.cells {
+            encoded.extend_from_slice(&cell.count.to_be_bytes());
+            encoded.extend_from_slice(&cell.key_sum);
+            encoded.extend_from_slice(&cell.hash_sum.to_be_bytes());
+        }
+        encoded
+    }
+}
+
 #[cfg(test)]
 mod encodable {
     mod bit_array {
@@ -76,7 +335,7 @@ mod encodable {
     mod bloom_filter {
         use crate::compressor::lzw;
         use crate::encoder::Encodable;
-        use crate::BloomFilterBuilder;
+        use crate::{BloomFilterBuilder, HashScheme};
 
         #[test]
         fn test_encode_without_compression() {
@@ -84,6 +343,8 @@ mod encodable {
             assert_eq!(
                 bloom_filter.encode(),
                 vec![
+                    0, // Hash scheme
+                    0, // Layout
                     0, 0, 0, 0, 0, 0, 0, 3, // BitArray: byte size
                     0, 0, 0, // BitArray: byte data
                     0, 0, 0, 0, 0, 0, 0, 20, // BitArray: bin size
@@ -96,6 +357,8 @@ mod encodable {
             assert_eq!(
                 bloom_filter.encode(),
                 vec![
+                    0, // Hash scheme
+                    0, // Layout
                     0, 0, 0, 0, 0, 0, 0, 3, // BitArray: byte size
                     0b10000100, 0b00100001, 0, // BitArray: byte data
                     0, 0, 0, 0, 0, 0, 0, 20, // BitArray: bin size
@@ -108,12 +371,367 @@ mod encodable {
         #[test]
         fn test_encode_with_lzw() {
             let bloom_filter = BloomFilterBuilder::new(2).build();
-            let mut encoded = lzw::compress(&bloom_filter.bit_array.encode());
+            let mut encoded = vec![0, 0];
+            encoded.extend(lzw::compress(&bloom_filter.bit_array.encode()));
             encoded.extend([
                 0, 0, 0, 0, 0, 0, 0, 7, // Number of hash functions
                 1,
             ]);
             assert_eq!(bloom_filter.encode(), encoded);
         }
+
+        #[test]
+        fn test_encode_scheme_and_layout() {
+            let mut bloom_filter = BloomFilterBuilder::new(2)
+                .no_compress()
+                .partitioned()
+                .build();
+            assert_eq!(bloom_filter.encode()[..2], [0, 1]);
+            bloom_filter.hash_scheme = HashScheme::Bip37 { tweak: 5 };
+            assert_eq!(bloom_filter.encode()[..6], [3, 5, 0, 0, 0, 1]);
+        }
+    }
+
+    mod split_block_bloom_filter {
+        use crate::encoder::Encodable;
+        use crate::SplitBlockBloomFilter;
+
+        #[test]
+        fn test_encode() {
+            let mut filter = SplitBlockBloomFilter::new(32);
+            filter.insert("a");
+            let mut expected = vec![
+                21, 64, // numBytes: 32
+                28, 28, 0, 0, // algorithm: BLOCK
+                28, 28, 0, 0, // hash: XXHASH
+                28, 28, 0, 0, // compression: UNCOMPRESSED
+                0,
+            ];
+            expected.extend(filter.bitset());
+            assert_eq!(filter.encode(), expected);
+        }
+    }
+
+    mod gcs_filter {
+        use crate::encoder::Encodable;
+        use crate::GcsFilter;
+
+        #[test]
+        fn test_encode() {
+            let filter = GcsFilter {
+                item_count: 300,
+                data: vec![0xab; 3],
+            };
+            assert_eq!(filter.encode(), [0xfd, 0x2c, 0x01, 0xab, 0xab, 0xab]);
+        }
+    }
+
+    mod leveldb_bloom_filter {
+        use crate::encoder::Encodable;
+        use crate::LevelDbBloomFilter;
+
+        #[test]
+        fn test_encode() {
+            let filter = LevelDbBloomFilter {
+                data: vec![0b0000_0001, 0, 0b1000_0000],
+                hash_count: 6,
+            };
+            assert_eq!(filter.encode(), [0b0000_0001, 0, 0b1000_0000, 6]);
+        }
+    }
+
+    mod fast_local_bloom_filter {
+        use crate::encoder::Encodable;
+        use crate::FastLocalBloomFilter;
+
+        #[test]
+        fn test_encode() {
+            let filter = FastLocalBloomFilter {
+                data: vec![0xaa; 64],
+                hash_count: 6,
+            };
+            let encoded = filter.encode();
+            assert_eq!(encoded.len(), 69);
+            assert_eq!(encoded[..64], [0xaa; 64]);
+            assert_eq!(encoded[64..], [0xff, 0, 6, 0, 0]);
+        }
+    }
+
+    mod cuckoo_filter {
+        use crate::encoder::Encodable;
+        use crate::CuckooFilterBuilder;
+
+        #[test]
+        fn test_encode() {
+            let mut filter = CuckooFilterBuilder::new(3)
+                .fingerprint_bits(4)
+                .bucket_size(2)
+                .max_kicks(10)
+                .build()
+                .unwrap();
+            filter.insert("key").unwrap();
+            let encoded = filter.encode();
+            assert_eq!(
+                encoded[..26],
+                [
+                    4, 2, // Fingerprint bits, bucket size
+                    0, 0, 0, 0, 0, 0, 0, 2, // Bucket count
+                    0, 0, 0, 0, 0, 0, 0, 10, // Max kicks
+                    0, 0, 0, 0, 0, 0, 0, 1, // Length
+                ]
+            );
+            assert_eq!(encoded[26..], filter.table);
+            assert_eq!(filter.table.len(), 2);
+        }
+    }
+
+    mod quotient_filter {
+        use crate::encoder::Encodable;
+        use crate::QuotientFilter;
+
+        #[test]
+        fn test_encode() {
+            let mut filter = QuotientFilter::with_bits(2, 5).unwrap();
+            filter.insert("key").unwrap();
+            let encoded = filter.encode();
+            assert_eq!(
+                encoded[..10],
+                [
+                    2, 5, // Quotient bits, remainder bits
+                    0, 0, 0, 0, 0, 0, 0, 1, // Length
+                ]
+            );
+            assert_eq!(encoded[10..], filter.table);
+            assert_eq!(filter.table.len(), 4);
+        }
+    }
+
+    mod xor_filter {
+        use crate::encoder::Encodable;
+        use crate::Xor16Filter;
+
+        #[test]
+        fn test_encode() {
+            let filter = Xor16Filter {
+                seed: 0x0102_0304_0506_0708,
+                block_length: 1,
+                fingerprints: vec![0xaabb, 0, 1],
+            };
+            assert_eq!(
+                filter.encode(),
+                [
+                    16, // Fingerprint bits
+                    1, 2, 3, 4, 5, 6, 7, 8, // Seed
+                    0, 0, 0, 0, 0, 0, 0, 1, // Block length
+                    0xbb, 0xaa, 0, 0, 1, 0, // Fingerprints
+                ]
+            );
+        }
+    }
+
+    mod binary_fuse_filter {
+        use crate::encoder::Encodable;
+        use crate::BinaryFuse8Filter;
+
+        #[test]
+        fn test_encode() {
+            let filter = BinaryFuse8Filter {
+                seed: 7,
+                segment_length: 1,
+                segment_count: 1,
+                fingerprints: vec![0xaa, 0, 1],
+            };
+            assert_eq!(
+                filter.encode(),
+                [
+                    8, // Fingerprint bits
+                    0, 0, 0, 0, 0, 0, 0, 7, // Seed
+                    0, 0, 0, 1, // Segment length
+                    0, 0, 0, 1, // Segment count
+                    0xaa, 0, 1, // Fingerprints
+                ]
+            );
+        }
+    }
+
+    mod ribbon_filter {
+        use crate::encoder::Encodable;
+        use crate::RibbonFilter;
+
+        #[test]
+        fn test_encode() {
+            let filter = RibbonFilter {
+                seed: 9,
+                result_bits: 1,
+                num_slots: 128,
+                solution: vec![0x0102],
+            };
+            let encoded = filter.encode();
+            assert_eq!(
+                encoded[..17],
+                [
+                    1, // Result bits
+                    0, 0, 0, 0, 0, 0, 0, 9, // Seed
+                    0, 0, 0, 0, 0, 0, 0, 128, // Slots
+                ]
+            );
+            assert_eq!(encoded[17..], [&[2, 1], &[0; 14][..]].concat());
+        }
+    }
+
+    mod stable_bloom_filter {
+        use crate::encoder::Encodable;
+        use crate::StableBloomFilterBuilder;
+
+        #[test]
+        fn test_encode() {
+            let filter = StableBloomFilterBuilder::new(5)
+                .cell_bits(2)
+                .hash_count(3)
+                .decrement(4)
+                .seed(6)
+                .build()
+                .unwrap();
+            let encoded = filter.encode();
+            assert_eq!(
+                encoded,
+                [
+                    2, // Cell bits
+                    0, 0, 0, 0, 0, 0, 0, 3, // Hash count
+                    0, 0, 0, 0, 0, 0, 0, 4, // Decrement
+                    0, 0, 0, 0, 0, 0, 0, 5, // Cell count
+                    0, 0, 0, 0, 0, 0, 0, 6, // Generator state
+                    0, 0, // Cells
+                ]
+            );
+        }
+    }
+
+    mod rotating_bloom_filter {
+        use crate::encoder::Encodable;
+        use crate::{BloomFilterBuilder, ManualClock, RotatingBloomFilter};
+        use std::time::Duration;
+
+        #[test]
+        fn test_encode() {
+            let template = BloomFilterBuilder::new(10).build();
+            let filter = RotatingBloomFilter::with_clock(
+                template,
+                3,
+                Duration::from_secs(60),
+                ManualClock::new(0x0102),
+            )
+            .unwrap();
+            let encoded = filter.encode();
+            assert_eq!(
+                encoded[..40],
+                [
+                    0, 0, 0, 0, 0, 0, 0, 3, // Generation count
+                    0, 0, 0, 0, 0, 0, 0, 60, // Window
+                    0, 0, 0, 0, 0, 0, 0, 1, // Stored generations
+                    0, 0, 0, 0, 0, 0, 1, 2, // Start
+                    0, 0, 0, 0, 0, 0, 0, 37, // Length
+                ]
+            );
+            let bloom_filter = filter.generations().next().unwrap().1;
+            assert_eq!(encoded[40..], bloom_filter.to_bytes());
+        }
+    }
+
+    mod count_min_sketch {
+        use crate::encoder::Encodable;
+        use crate::CountMinSketchBuilder;
+
+        #[test]
+        fn test_encode() {
+            let mut sketch = CountMinSketchBuilder::new(0.9, 0.5)
+                .conservative()
+                .top_k(2)
+                .build()
+                .unwrap();
+            sketch.add("ab", 3);
+            let encoded = sketch.encode();
+            assert_eq!(
+                encoded[..59],
+                [
+                    0, 0, 0, 0, 0, 0, 0, 4, // Width
+                    0, 0, 0, 0, 0, 0, 0, 1, // Depth
+                    1, // Conservative
+                    0, 0, 0, 0, 0, 0, 0, 3, // Total
+                    0, 0, 0, 0, 0, 0, 0, 2, // Top k
+                    0, 0, 0, 0, 0, 0, 0, 1, // Entries
+                    0, 0, 0, 0, 0, 0, 0, 3, // Count
+                    0, 0, 0, 0, 0, 0, 0, 2, // Key length
+                    b'a', b'b',
+                ]
+            );
+            assert_eq!(encoded.len(), 59 + 4 * 8);
+            let counters: Vec<u8> = sketch
+                .counters
+                .iter()
+                .flat_map(|c| c.to_be_bytes())
+                .collect();
+            assert_eq!(encoded[59..], counters);
+        }
+    }
+
+    mod hyperloglog {
+        use crate::encoder::Encodable;
+        use crate::HyperLogLog;
+
+        #[test]
+        fn test_encode() {
+            let mut sketch = HyperLogLog::new(4).unwrap();
+            sketch.insert("key");
+            let encoded = sketch.encode();
+            assert_eq!(encoded[..6], [4, 0, 0, 0, 0, 1]);
+            assert_eq!(encoded.len(), 10);
+
+            for i in 0..10 {
+                sketch.insert(&i.to_string());
+            }
+            assert!(!sketch.is_sparse());
+            let encoded = sketch.encode();
+            assert_eq!(encoded[..2], [4, 1]);
+            assert_eq!(encoded.len(), 2 + 16);
+        }
+    }
+
+    mod invertible_bloom_lookup_table {
+        use crate::encoder::Encodable;
+        use crate::InvertibleBloomLookupTableBuilder;
+
+        #[test]
+        fn test_encode() {
+            let mut table = InvertibleBloomLookupTableBuilder::new(1)
+                .hash_count(1)
+                .key_size(2)
+                .build()
+                .unwrap();
+            table.insert("a").unwrap();
+            let encoded = table.encode();
+            assert_eq!(
+                encoded[..24],
+                [
+                    0, 0, 0, 0, 0, 0, 0, 1, // Hash count
+                    0, 0, 0, 0, 0, 0, 0, 2, // Key size
+                    0, 0, 0, 0, 0, 0, 0, 34, // Cells
+                ]
+            );
+            assert_eq!(encoded.len(), 24 + 34 * 20);
+            let cell = table.cells.iter().position(|cell| cell.count == 1).unwrap();
+            let cell = &encoded[24 + cell * 20..][..20];
+            assert_eq!(cell[..12], [0, 0, 0, 0, 0, 0, 0, 1, 0, 1, b'a', 0]);
+            assert_eq!(
+                cell[12..],
+                table
+                    .cells
+                    .iter()
+                    .find(|c| c.count == 1)
+                    .unwrap()
+                    .hash_sum
+                    .to_be_bytes()
+            );
+        }
     }
 }
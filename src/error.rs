@@ -4,4 +4,20 @@ use thiserror::Error;
 pub enum BloomFilterError {
     #[error("Invalid format")]
     InvalidFormat,
+    #[error("Unsupported format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Incompatible filters: {0}")]
+    Incompatible(String),
+    #[error("Invalid parameters: {0}")]
+    InvalidParams(String),
+    #[error("Invalid dump: {0}")]
+    InvalidDump(String),
+    #[error("Unsupported filter: {0}")]
+    Unsupported(String),
+    #[error("Filter is full: {0}")]
+    Full(String),
+    #[error("Peel failed: {0}")]
+    PeelFailed(String),
+    #[error("Construction failed: {0}")]
+    ConstructionFailed(String),
 }
@@ -0,0 +1,170 @@
+// RocksDB's FastLocalBloom full filter, format_version 5 Bloom filters
+// ref: https://github.com/facebook/rocksdb/blob/main/util/bloom_impl.h
+// ref: https://github.com/facebook/rocksdb/blob/main/table/block_based/filter_policy.cc
+//
+// Keys are hashed with XXPH3 64, RocksDB's `Hash64`. The low half picks a
+// 64 byte cache line, the high half is multiplied by 0x9e3779b9 between
+// probes and its top 9 bits pick the bit within the line. Bits are numbered
+// from the least significant one of each byte.
+//
+// Block layout: the cache lines, then 5 bytes of metadata:
+//
+//   -1 (new Bloom), 0 (FastLocalBloom), probes | (log2 line bytes - 6) << 5,
+//   2 reserved zero bytes
+
+use crate::error::BloomFilterError;
+use crate::hash::xxph3_64;
+use anyhow::Result;
+
+pub(crate) const CACHE_LINE_SIZE: usize = 64;
+pub(crate) const METADATA_SIZE: usize = 5;
+const MAX_DATA_SIZE: usize = 0xffff_ffc0;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FastLocalBloomFilter {
+    // Empty for a filter without keys, which matches nothing
+    pub data: Vec<u8>,
+    pub hash_count: usize,
+}
+
+impl FastLocalBloomFilter {
+    // Sized like RocksDB's `NewBloomFilterPolicy(bits_per_key)`
+    pub fn new<I, K>(bits_per_key: f64, keys: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        if !(1.0..100.0).contains(&bits_per_key) {
+            let reason = "bits per key must be in [1, 100)".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        let millibits_per_key = (bits_per_key * 1000.0 + 0.500001) as u64;
+        let keys: Vec<K> = keys.into_iter().collect();
+        let target = ((keys.len() as u64 * millibits_per_key).div_ceil(8000) as usize)
+            .min(MAX_DATA_SIZE)
+            .next_multiple_of(CACHE_LINE_SIZE);
+
+        let mut filter = Self {
+            data: vec![0; target],
+            hash_count: hash_count(millibits_per_key),
+        };
+        if filter.data.is_empty() {
+            return Ok(filter);
+        }
+        for key in keys {
+            for (byte, bit) in filter.hashing(key.as_ref()) {
+                filter.data[byte] |= 1 << bit;
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        if self.data.is_empty() {
+            return false;
+        }
+        self.hashing(key.as_ref())
+            .all(|(byte, bit)| self.data[byte] & (1 << bit) != 0)
+    }
+
+    // (byte, bit within the byte) of each probe
+    fn hashing(&self, bytes: &[u8]) -> impl Iterator<Item = (usize, u32)> {
+        let hash = xxph3_64(bytes);
+        let lines = (self.data.len() / CACHE_LINE_SIZE) as u64;
+        let line = (((hash & 0xffff_ffff) * lines) >> 32) as usize * CACHE_LINE_SIZE;
+        let mut h = (hash >> 32) as u32;
+        (0..self.hash_count).map(move |_| {
+            let bit = (h >> (32 - 9)) as usize;
+            h = h.wrapping_mul(0x9e37_79b9);
+            (line + bit / 8, bit as u32 % 8)
+        })
+    }
+}
+
+// `FastLocalBloomImpl::ChooseNumProbes`
+fn hash_count(millibits_per_key: u64) -> usize {
+    match millibits_per_key {
+        0..=2080 => 1,
+        2081..=3580 => 2,
+        3581..=5100 => 3,
+        5101..=6640 => 4,
+        6641..=8300 => 5,
+        8301..=10070 => 6,
+        10071..=11720 => 7,
+        11721..=14001 => 8,
+        14002..=16050 => 9,
+        16051..=18300 => 10,
+        18301..=22001 => 11,
+        22002..=25501 => 12,
+        25502..=50000 => ((millibits_per_key - 1) / 2000 - 1) as usize,
+        _ => 24,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::keys;
+    use crate::{Decodable, Encodable};
+    use std::collections::HashSet;
+
+    // Generated by tests/data/sst/sst_golden.cc: key-0..key-{n - 1} inserted,
+    // along with the probes in key-n..key-{n + 9999} the filter accepts
+    const GOLDEN_100: &[u8] = include_bytes!("../tests/data/sst/fast_local_10_100.bin");
+    const GOLDEN_100_PRESENT: &str = include_str!("../tests/data/sst/fast_local_10_100.txt");
+    const GOLDEN_1000: &[u8] = include_bytes!("../tests/data/sst/fast_local_7.5_1000.bin");
+    const GOLDEN_1000_PRESENT: &str = include_str!("../tests/data/sst/fast_local_7.5_1000.txt");
+
+    fn check_lookups(filter: &FastLocalBloomFilter, n: usize, present: &str) {
+        let present: HashSet<usize> = present.lines().map(|i| i.parse().unwrap()).collect();
+        assert!(keys(0..n).iter().all(|key| filter.lookup(key)));
+        for i in n..n + 10_000 {
+            let key = format!("key-{}", i);
+            assert_eq!(filter.lookup(&key), present.contains(&i), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_golden() {
+        let filter = FastLocalBloomFilter::new(10.0, keys(0..100)).unwrap();
+        assert_eq!(filter.data.len(), 128);
+        assert_eq!(filter.hash_count, 6);
+        assert_eq!(filter.encode(), GOLDEN_100);
+        check_lookups(&filter, 100, GOLDEN_100_PRESENT);
+
+        let filter = FastLocalBloomFilter::decode(GOLDEN_1000).unwrap();
+        assert_eq!(filter.data.len(), 960);
+        assert_eq!(filter.hash_count, 5);
+        assert_eq!(
+            filter,
+            FastLocalBloomFilter::new(7.5, keys(0..1000)).unwrap()
+        );
+        check_lookups(&filter, 1000, GOLDEN_1000_PRESENT);
+    }
+
+    #[test]
+    fn test_empty() {
+        let filter = FastLocalBloomFilter::new(10.0, Vec::<&str>::new()).unwrap();
+        assert!(filter.data.is_empty());
+        assert!(!filter.lookup("key-0"));
+        assert_eq!(filter.encode(), [0xff, 0, 6, 0, 0]);
+        assert_eq!(
+            FastLocalBloomFilter::decode(&filter.encode()).unwrap(),
+            filter
+        );
+        // RocksDB reads blocks too short for metadata as empty
+        assert!(!FastLocalBloomFilter::decode(&[]).unwrap().lookup("key-0"));
+    }
+
+    #[test]
+    fn test_hash_count() {
+        assert_eq!(hash_count(1000), 1);
+        assert_eq!(hash_count(14001), 8);
+        assert_eq!(hash_count(14002), 9);
+        assert_eq!(hash_count(30000), 13);
+        assert_eq!(hash_count(50000), 23);
+        assert_eq!(hash_count(50001), 24);
+        assert!(FastLocalBloomFilter::new(0.5, keys(0..1)).is_err());
+        assert!(FastLocalBloomFilter::new(100.0, keys(0..1)).is_err());
+    }
+}
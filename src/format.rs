@@ -0,0 +1,245 @@
+use crate::decoder::Decodable;
+use crate::encoder::Encodable;
+use crate::error::BloomFilterError;
+use anyhow::Result;
+use std::fs;
+
+const MAGIC: &[u8; 4] = b"BFYS";
+const HEADER_SIZE: usize = MAGIC.len() + 1;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FileFormat {
+    // Bare filter encoding without header. Both of its layouts start with a
+    // zero byte: the big endian bit array length, or the first LZW code.
+    Legacy,
+    V1,
+    // Adds the hash scheme ahead of the filter encoding
+    V2,
+    // Adds the filter kind after the version, Bloom filters then follow the
+    // V2 layout
+    V3,
+    // Adds the Bloom filter layout after the hash scheme
+    V4,
+}
+
+// What a file holds, files before V3 only hold Bloom filters. Sketches
+// share the header.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FilterKind {
+    Bloom,
+    Cuckoo,
+    Quotient,
+    Xor8,
+    Xor16,
+    BinaryFuse8,
+    BinaryFuse16,
+    Ribbon,
+    Stable,
+    Rotating,
+    CountMin,
+    HyperLogLog,
+    Invertible,
+}
+
+impl FileFormat {
+    pub const CURRENT: Self = Self::V4;
+
+    pub fn detect(bytes: &[u8]) -> Result<Self> {
+        if let Some(version) = bytes.strip_prefix(MAGIC).and_then(|rest| rest.first()) {
+            return match version {
+                1 => Ok(Self::V1),
+                2 => Ok(Self::V2),
+                3 => Ok(Self::V3),
+                4 => Ok(Self::V4),
+                &version => Err(BloomFilterError::UnsupportedVersion(version).into()),
+            };
+        }
+        match bytes.first() {
+            Some(0) => Ok(Self::Legacy),
+            _ => Err(BloomFilterError::InvalidFormat.into()),
+        }
+    }
+
+    fn version(&self) -> u8 {
+        match self {
+            Self::Legacy => 0,
+            Self::V1 => 1,
+            Self::V2 => 2,
+            Self::V3 => 3,
+            Self::V4 => 4,
+        }
+    }
+}
+
+impl FilterKind {
+    pub fn detect(bytes: &[u8]) -> Result<Self> {
+        let (_, kind, _) = strip_header(bytes)?;
+        Ok(kind)
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            Self::Bloom => 0,
+            Self::Cuckoo => 1,
+            Self::Quotient => 2,
+            Self::Xor8 => 3,
+            Self::Xor16 => 4,
+            Self::BinaryFuse8 => 5,
+            Self::BinaryFuse16 => 6,
+            Self::Ribbon => 7,
+            Self::Stable => 8,
+            Self::Rotating => 9,
+            Self::CountMin => 10,
+            Self::HyperLogLog => 11,
+            Self::Invertible => 12,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Self> {
+        match tag {
+            0 => Ok(Self::Bloom),
+            1 => Ok(Self::Cuckoo),
+            2 => Ok(Self::Quotient),
+            3 => Ok(Self::Xor8),
+            4 => Ok(Self::Xor16),
+            5 => Ok(Self::BinaryFuse8),
+            6 => Ok(Self::BinaryFuse16),
+            7 => Ok(Self::Ribbon),
+            8 => Ok(Self::Stable),
+            9 => Ok(Self::Rotating),
+            10 => Ok(Self::CountMin),
+            11 => Ok(Self::HyperLogLog),
+            12 => Ok(Self::Invertible),
+            _ => Err(BloomFilterError::InvalidFormat.into()),
+        }
+    }
+}
+
+pub fn write_header(output: &mut Vec<u8>, kind: FilterKind) {
+    output.extend_from_slice(MAGIC);
+    output.push(FileFormat::CURRENT.version());
+    output.push(kind.tag());
+}
+
+// Returns the detected format and filter kind along with the payload
+// following the header
+pub fn strip_header(bytes: &[u8]) -> Result<(FileFormat, FilterKind, &[u8])> {
+    let format = FileFormat::detect(bytes)?;
+    match format {
+        FileFormat::Legacy => Ok((format, FilterKind::Bloom, bytes)),
+        FileFormat::V1 | FileFormat::V2 => Ok((format, FilterKind::Bloom, &bytes[HEADER_SIZE..])),
+        FileFormat::V3 | FileFormat::V4 => {
+            let tag = *bytes
+                .get(HEADER_SIZE)
+                .ok_or(BloomFilterError::InvalidFormat)?;
+            Ok((
+                format,
+                FilterKind::from_tag(tag)?,
+                &bytes[HEADER_SIZE + 1..],
+            ))
+        }
+    }
+}
+
+// Same as `strip_header`, for a file that must hold the given kind
+pub fn strip_header_for(bytes: &[u8], expected: FilterKind) -> Result<(FileFormat, &[u8])> {
+    let (format, kind, payload) = strip_header(bytes)?;
+    if kind != expected {
+        let reason = format!("{:?} filter file, expected {:?}", kind, expected);
+        return Err(BloomFilterError::Unsupported(reason).into());
+    }
+    Ok((format, payload))
+}
+
+// Filters and sketches stored as the versioned header, then their encoding
+pub trait Persistable: Encodable + Decodable {
+    // Kind written to the header, and expected when loading
+    fn kind() -> FilterKind;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, Self::kind());
+        bytes.extend_from_slice(&self.encode());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (_, payload) = strip_header_for(bytes, Self::kind())?;
+        Self::decode(payload)
+    }
+
+    fn to_file(&self, path: &str) -> Result<()> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    fn from_file(path: &str) -> Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(FileFormat::detect(b"BFYS\x01").unwrap(), FileFormat::V1);
+        assert_eq!(FileFormat::detect(b"BFYS\x02").unwrap(), FileFormat::V2);
+        assert_eq!(FileFormat::detect(b"BFYS\x03").unwrap(), FileFormat::V3);
+        assert_eq!(FileFormat::detect(b"BFYS\x04").unwrap(), FileFormat::V4);
+        assert_eq!(FileFormat::detect(&[0, 0, 1]).unwrap(), FileFormat::Legacy);
+        assert!(FileFormat::detect(b"BFYS\x07").is_err());
+        assert!(FileFormat::detect(b"BFYS").is_err());
+        assert!(FileFormat::detect(b"").is_err());
+        assert!(FileFormat::detect(b"junk").is_err());
+    }
+
+    #[test]
+    fn test_header() {
+        let mut output = Vec::new();
+        write_header(&mut output, FilterKind::Cuckoo);
+        output.extend([0, 1, 2]);
+        assert_eq!(output, b"BFYS\x04\x01\x00\x01\x02");
+
+        let (format, kind, payload) = strip_header(&output).unwrap();
+        assert_eq!(format, FileFormat::V4);
+        assert_eq!(kind, FilterKind::Cuckoo);
+        assert_eq!(payload, [0, 1, 2]);
+
+        let (format, kind, payload) = strip_header(b"BFYS\x03\x02\x05").unwrap();
+        assert_eq!(format, FileFormat::V3);
+        assert_eq!(kind, FilterKind::Quotient);
+        assert_eq!(payload, [5]);
+
+        let (format, kind, payload) = strip_header(b"BFYS\x02\x00\x01").unwrap();
+        assert_eq!(format, FileFormat::V2);
+        assert_eq!(kind, FilterKind::Bloom);
+        assert_eq!(payload, [0, 1]);
+
+        let (format, kind, payload) = strip_header(&[0, 1, 2]).unwrap();
+        assert_eq!(format, FileFormat::Legacy);
+        assert_eq!(kind, FilterKind::Bloom);
+        assert_eq!(payload, [0, 1, 2]);
+
+        assert!(strip_header(b"BFYS\x04").is_err());
+        assert!(strip_header(b"BFYS\x04\xff").is_err());
+    }
+
+    #[test]
+    fn test_filter_kind() {
+        assert_eq!(
+            FilterKind::detect(b"BFYS\x03\x00").unwrap(),
+            FilterKind::Bloom
+        );
+        assert_eq!(FilterKind::detect(b"BFYS\x01").unwrap(), FilterKind::Bloom);
+
+        assert!(strip_header_for(b"BFYS\x04\x01", FilterKind::Cuckoo).is_ok());
+        assert_eq!(
+            strip_header_for(b"BFYS\x04\x01", FilterKind::Bloom)
+                .unwrap_err()
+                .to_string(),
+            "Unsupported filter: Cuckoo filter file, expected Bloom"
+        );
+    }
+}
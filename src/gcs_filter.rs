@@ -0,0 +1,261 @@
+// Golomb-coded set, BIP158's basic filter
+// ref: https://github.com/bitcoin/bips/blob/master/bip-0158.mediawiki
+//
+// Items are hashed with SipHash-2-4 keyed by the first 16 bytes of the block
+// hash, as stored (the reverse of its hex display), and mapped into
+// [0, N * M). The sorted values are written as deltas, each one Golomb-Rice
+// coded with parameter P: the quotient in unary, ones closed by a zero, then
+// the low P bits, most significant bit first.
+//
+// Encoded as BIP158 serializes filters: compact size N, then the bit stream
+// padded to a whole byte. The key is not part of it.
+
+use crate::error::BloomFilterError;
+use crate::hash::siphash24;
+use anyhow::Result;
+
+pub const GCS_P: u8 = 19;
+pub const GCS_M: u64 = 784_931;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GcsFilter {
+    pub item_count: u64,
+    // Golomb-Rice coded deltas
+    pub data: Vec<u8>,
+}
+
+impl GcsFilter {
+    // Duplicate items are counted once
+    pub fn new<I, K>(key: &[u8; 16], items: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        let mut items: Vec<Vec<u8>> = items.into_iter().map(|k| k.as_ref().to_vec()).collect();
+        items.sort_unstable();
+        items.dedup();
+        let item_count = items.len() as u64;
+        if item_count > u32::MAX as u64 {
+            let reason = format!("{} items, BIP158 allows up to 2^32 - 1", item_count);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+
+        let mut values: Vec<u64> = items
+            .iter()
+            .map(|item| hash_to_range(key, item, item_count))
+            .collect();
+        values.sort_unstable();
+
+        let mut writer = BitWriter::default();
+        let mut last = 0;
+        for value in values {
+            let delta = value - last;
+            for _ in 0..delta >> GCS_P {
+                writer.write_bit(true);
+            }
+            writer.write_bit(false);
+            writer.write_bits(delta, GCS_P);
+            last = value;
+        }
+
+        Ok(Self {
+            item_count,
+            data: writer.bytes,
+        })
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &[u8; 16], item: &K) -> bool {
+        self.lookup_any(key, [item.as_ref()])
+    }
+
+    // True if any of the items may be in the set
+    pub fn lookup_any<I, K>(&self, key: &[u8; 16], items: I) -> bool
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        if self.item_count == 0 {
+            return false;
+        }
+        let mut queries: Vec<u64> = items
+            .into_iter()
+            .map(|item| hash_to_range(key, item.as_ref(), self.item_count))
+            .collect();
+        queries.sort_unstable();
+
+        // Both sides are sorted, walk them together
+        let mut values = self.values();
+        let mut queries = queries.into_iter().peekable();
+        let Some(mut value) = values.next() else {
+            return false;
+        };
+        while let Some(&query) = queries.peek() {
+            if query == value {
+                return true;
+            }
+            if query < value {
+                queries.next();
+            } else {
+                match values.next() {
+                    Some(next) => value = next,
+                    None => return false,
+                }
+            }
+        }
+        false
+    }
+
+    // Hashed values in ascending order, stops early on truncated data
+    fn values(&self) -> impl Iterator<Item = u64> + '_ {
+        let mut reader = BitReader::new(&self.data);
+        let mut last = 0u64;
+        (0..self.item_count).map_while(move |_| {
+            let delta = reader.read_golomb_rice()?;
+            last = last.checked_add(delta)?;
+            Some(last)
+        })
+    }
+
+    // Checks the data holds item_count values below N * M, and nothing more
+    // than the last byte's padding
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.item_count > u32::MAX as u64 {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let mut reader = BitReader::new(&self.data);
+        let mut last = 0u64;
+        for _ in 0..self.item_count {
+            let delta = reader
+                .read_golomb_rice()
+                .ok_or(BloomFilterError::InvalidFormat)?;
+            last = last
+                .checked_add(delta)
+                .ok_or(BloomFilterError::InvalidFormat)?;
+        }
+        if self.item_count > 0 && last >= self.item_count * GCS_M {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        if reader.position.div_ceil(8) != self.data.len() {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(())
+    }
+}
+
+fn hash_to_range(key: &[u8; 16], item: &[u8], item_count: u64) -> u64 {
+    let k0 = u64::from_le_bytes(key[..8].try_into().unwrap());
+    let k1 = u64::from_le_bytes(key[8..].try_into().unwrap());
+    let hash = siphash24(item, k0, k1);
+    ((hash as u128 * (item_count * GCS_M) as u128) >> 64) as u64
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl BitWriter {
+    fn write_bit(&mut self, bit: bool) {
+        if self.position.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 0x80 >> (self.position % 8);
+        }
+        self.position += 1;
+    }
+
+    // Low `count` bits of value, most significant first
+    fn write_bits(&mut self, value: u64, count: u8) {
+        for i in (0..count).rev() {
+            self.write_bit(value >> i & 1 == 1);
+        }
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn read_bit(&mut self) -> Option<bool> {
+        let byte = self.bytes.get(self.position / 8)?;
+        let bit = byte & (0x80 >> (self.position % 8)) != 0;
+        self.position += 1;
+        Some(bit)
+    }
+
+    fn read_golomb_rice(&mut self) -> Option<u64> {
+        let mut quotient = 0u64;
+        while self.read_bit()? {
+            quotient += 1;
+        }
+        let mut remainder = 0u64;
+        for _ in 0..GCS_P {
+            remainder = remainder << 1 | self.read_bit()? as u64;
+        }
+        quotient.checked_mul(1 << GCS_P)?.checked_add(remainder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+    use crate::{Decodable, Encodable};
+
+    // Block hash as displayed, key is the start of its stored byte order
+    fn block_key(hash: &str) -> [u8; 16] {
+        let mut bytes = hex(hash);
+        bytes.reverse();
+        bytes[..16].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_bip158_genesis() {
+        // BIP158 test vectors, testnet block 0: the coinbase output script
+        let key = block_key("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943");
+        let script = hex(concat!(
+            "4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61de",
+            "b649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac"
+        ));
+        let filter = GcsFilter::new(&key, [&script]).unwrap();
+        assert_eq!(filter.encode(), hex("019dfca8"));
+        assert!(filter.lookup(&key, &script));
+        assert!(!filter.lookup(&key, &script[1..]));
+        assert_eq!(GcsFilter::decode(&hex("019dfca8")).unwrap(), filter);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let key = *b"0123456789abcdef";
+        let items: Vec<String> = (0..1000).map(|i| format!("item-{}", i)).collect();
+        let filter = GcsFilter::new(&key, items.iter().chain(items.iter())).unwrap();
+        assert_eq!(filter.item_count, 1000);
+        assert!(items.iter().all(|item| filter.lookup(&key, item)));
+
+        // False positive rate is about 1 / M
+        let absent = (1000..11_000).filter(|i| filter.lookup(&key, &format!("item-{}", i)));
+        assert!(absent.count() <= 1);
+
+        assert!(filter.lookup_any(&key, ["absent", "item-999", "also absent"]));
+        assert!(!filter.lookup_any(&key, ["absent", "also absent"]));
+        assert!(!filter.lookup_any(&key, Vec::<&[u8]>::new()));
+        // The same items under another block's key
+        assert!(!filter.lookup_any(&key.map(|b| b ^ 1), &items[..10]));
+    }
+
+    #[test]
+    fn test_empty() {
+        let filter = GcsFilter::new(&[0; 16], Vec::<&[u8]>::new()).unwrap();
+        assert_eq!(filter.encode(), [0]);
+        assert!(!filter.lookup(&[0; 16], ""));
+        assert_eq!(GcsFilter::decode(&[0]).unwrap(), filter);
+    }
+}
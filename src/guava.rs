@@ -0,0 +1,250 @@
+// Guava BloomFilter interop, `BloomFilter.writeTo` / `BloomFilter.readFrom`
+// ref: https://github.com/google/guava/blob/master/guava/src/com/google/common/hash/BloomFilter.java
+// ref: https://github.com/google/guava/blob/master/guava/src/com/google/common/hash/BloomFilterStrategies.java
+//
+// Layout, big endian:
+//
+//   u8 strategy ordinal (1 = MURMUR128_MITZ_64), u8 hash functions,
+//   i32 word count, then the bit array as i64 words
+//
+// Guava numbers bits from the least significant one of each word, so with
+// the words written big endian its bit x is bit x ^ 63 of the crate's
+// `BitArray` over the same bytes.
+//
+// Keys are the funneled bytes: Java filters built with
+// `Funnels.byteArrayFunnel()` or `Funnels.stringFunnel(UTF_8)` give the same
+// answers as `lookup` on the raw bytes or UTF-8 string.
+
+use crate::bloom_filter::{BitArray, BloomFilter, CompressMode, HashScheme, Layout};
+use crate::error::BloomFilterError;
+use crate::hash::murmur3_x64_128;
+use anyhow::Result;
+use std::f64::consts::LN_2;
+
+const MURMUR128_MITZ_32: u8 = 0;
+const MURMUR128_MITZ_64: u8 = 1;
+const HEADER_SIZE: usize = 1 + 1 + 4;
+
+// Sized like `BloomFilter.create(funnel, expected_insertions, fpp)`
+pub fn create(expected_insertions: u64, fpp: f64) -> Result<BloomFilter> {
+    if !(fpp > 0.0 && fpp < 1.0) {
+        let reason = "false positive rate must be in (0, 1)".to_string();
+        return Err(BloomFilterError::InvalidParams(reason).into());
+    }
+    let n = expected_insertions.max(1) as f64;
+    let bits = (-n * fpp.ln() / (LN_2 * LN_2)) as u64;
+    let hash_count = ((bits as f64 / n * LN_2).round() as usize).max(1);
+    let words = bits.div_ceil(64);
+    if words > i32::MAX as u64 || hash_count > u8::MAX as usize {
+        let reason = format!("{} bits and {} hash functions", bits, hash_count);
+        return Err(BloomFilterError::InvalidParams(reason).into());
+    }
+
+    Ok(BloomFilter {
+        bit_array: BitArray::new(words as usize * 64),
+        hash_count,
+        compress_mode: CompressMode::None,
+        hash_scheme: HashScheme::Guava,
+        layout: Layout::Standard,
+    })
+}
+
+// Filter written by Guava's `writeTo`
+pub fn from_bytes(bytes: &[u8]) -> Result<BloomFilter> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+    match bytes[0] {
+        MURMUR128_MITZ_64 => {}
+        MURMUR128_MITZ_32 => {
+            let reason = "MURMUR128_MITZ_32 strategy, only MURMUR128_MITZ_64 is supported";
+            return Err(BloomFilterError::Unsupported(reason.to_string()).into());
+        }
+        _ => return Err(BloomFilterError::InvalidFormat.into()),
+    }
+    let hash_count = bytes[1] as usize;
+    let words = i32::from_be_bytes(bytes[2..HEADER_SIZE].try_into()?);
+    let data = &bytes[HEADER_SIZE..];
+    if hash_count == 0 || words <= 0 || data.len() != words as usize * 8 {
+        return Err(BloomFilterError::InvalidFormat.into());
+    }
+
+    Ok(BloomFilter {
+        bit_array: BitArray {
+            byte_array: data.to_vec(),
+            size: data.len() * 8,
+        },
+        hash_count,
+        compress_mode: CompressMode::None,
+        hash_scheme: HashScheme::Guava,
+        layout: Layout::Standard,
+    })
+}
+
+// Filter readable by Guava's `readFrom`
+pub fn to_bytes(bloom_filter: &BloomFilter) -> Result<Vec<u8>> {
+    if bloom_filter.hash_scheme != HashScheme::Guava {
+        let reason = format!(
+            "{:?} hashing, Guava filters use Guava hashing",
+            bloom_filter.hash_scheme
+        );
+        return Err(BloomFilterError::Unsupported(reason).into());
+    }
+    let bit_array = &bloom_filter.bit_array;
+    let words = bit_array.size / 64;
+    if !bit_array.size.is_multiple_of(64)
+        || bit_array.byte_array.len() != words * 8
+        || words > i32::MAX as usize
+    {
+        let reason = format!(
+            "{} bits, Guava filters hold up to 2^31 - 1 whole 64 bit words",
+            bit_array.size
+        );
+        return Err(BloomFilterError::Unsupported(reason).into());
+    }
+    if bloom_filter.hash_count > u8::MAX as usize {
+        let reason = format!(
+            "{} hash functions, Guava stores at most 255",
+            bloom_filter.hash_count
+        );
+        return Err(BloomFilterError::Unsupported(reason).into());
+    }
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + bit_array.byte_array.len());
+    bytes.push(MURMUR128_MITZ_64);
+    bytes.push(bloom_filter.hash_count as u8);
+    bytes.extend_from_slice(&(words as i32).to_be_bytes());
+    bytes.extend_from_slice(&bit_array.byte_array);
+    Ok(bytes)
+}
+
+// MURMUR128_MITZ_64: h1 + i * h2 over the 128 bit murmur3 halves, made
+// positive before reducing
+pub(crate) fn hashing(bytes: &[u8], hash_count: usize, bit_count: usize) -> Vec<usize> {
+    let bits = bit_count as u64;
+    let (h1, h2) = murmur3_x64_128(bytes, 0);
+    let mut combined = h1;
+
+    (0..hash_count)
+        .map(|_| {
+            let index = (combined & i64::MAX as u64) % bits;
+            combined = combined.wrapping_add(h2);
+            index as usize ^ 63
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    // Written by Guava 33.4.0 through tests/data/guava/GuavaGolden.java:
+    // key-0..key-{n - 1}, "" and "été ☃" inserted, along with the probes
+    // mightContain reports
+    const GOLDEN_1000: &[u8] = include_bytes!("../tests/data/guava/guava_1000_0.01.bin");
+    const GOLDEN_1000_PRESENT: &str = include_str!("../tests/data/guava/guava_1000_0.01.txt");
+    const GOLDEN_100: &[u8] = include_bytes!("../tests/data/guava/guava_100_0.001.bin");
+    const GOLDEN_100_PRESENT: &str = include_str!("../tests/data/guava/guava_100_0.001.txt");
+
+    fn golden_filter(expected_insertions: u64, fpp: f64, inserted: usize) -> BloomFilter {
+        let mut bloom_filter = create(expected_insertions, fpp).unwrap();
+        for i in 0..inserted {
+            bloom_filter.insert(&format!("key-{}", i));
+        }
+        bloom_filter.insert("");
+        bloom_filter.insert("été ☃");
+        bloom_filter
+    }
+
+    fn check_lookups(bloom_filter: &BloomFilter, present: &str, probes: usize) {
+        let present: HashSet<usize> = present.lines().map(|i| i.parse().unwrap()).collect();
+        for i in 0..probes {
+            assert_eq!(
+                bloom_filter.lookup(&format!("key-{}", i)),
+                present.contains(&i),
+                "key-{}",
+                i
+            );
+        }
+        assert!(bloom_filter.lookup(""));
+        assert!(bloom_filter.lookup("été ☃"));
+    }
+
+    #[test]
+    fn test_create() {
+        let bloom_filter = create(1000, 0.01).unwrap();
+        assert_eq!(bloom_filter.bit_array.size, 9600);
+        assert_eq!(bloom_filter.hash_count, 7);
+        let bloom_filter = create(0, 0.5).unwrap();
+        assert_eq!(bloom_filter.bit_array.size, 64);
+        assert_eq!(bloom_filter.hash_count, 1);
+        assert!(create(100, 0.0).is_err());
+        assert!(create(100, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_read_golden() {
+        let bloom_filter = from_bytes(GOLDEN_1000).unwrap();
+        assert_eq!(bloom_filter.hash_scheme, HashScheme::Guava);
+        assert_eq!(bloom_filter.hash_count, 7);
+        assert_eq!(bloom_filter.bit_array.size, 9600);
+        check_lookups(&bloom_filter, GOLDEN_1000_PRESENT, 20_000);
+
+        let bloom_filter = from_bytes(GOLDEN_100).unwrap();
+        assert_eq!(bloom_filter.hash_count, 10);
+        assert_eq!(bloom_filter.bit_array.size, 1472);
+        check_lookups(&bloom_filter, GOLDEN_100_PRESENT, 5_000);
+    }
+
+    #[test]
+    fn test_write_golden() {
+        let bloom_filter = golden_filter(1000, 0.01, 1000);
+        assert_eq!(to_bytes(&bloom_filter).unwrap(), GOLDEN_1000);
+        let bloom_filter = golden_filter(100, 0.001, 50);
+        assert_eq!(to_bytes(&bloom_filter).unwrap(), GOLDEN_100);
+    }
+
+    #[test]
+    fn test_crate_file_format() {
+        let bloom_filter = from_bytes(GOLDEN_100).unwrap();
+        let loaded = BloomFilter::from_bytes(&bloom_filter.to_bytes()).unwrap();
+        assert_eq!(loaded, bloom_filter);
+        assert_eq!(to_bytes(&loaded).unwrap(), GOLDEN_100);
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        let mut mitz_32 = GOLDEN_100.to_vec();
+        mitz_32[0] = 0;
+        assert_eq!(
+            from_bytes(&mitz_32).unwrap_err().to_string(),
+            "Unsupported filter: MURMUR128_MITZ_32 strategy, only MURMUR128_MITZ_64 is supported"
+        );
+
+        let mut unknown_strategy = GOLDEN_100.to_vec();
+        unknown_strategy[0] = 2;
+        assert!(from_bytes(&unknown_strategy).is_err());
+        assert!(from_bytes(&GOLDEN_100[..GOLDEN_100.len() - 1]).is_err());
+        assert!(from_bytes(&[GOLDEN_100, &[0]].concat()).is_err());
+        assert!(from_bytes(&[1, 3, 0, 0, 0, 0]).is_err());
+        assert!(from_bytes(&[1, 3, 0xff, 0xff, 0xff, 0xff]).is_err());
+        assert!(from_bytes(&[1, 3]).is_err());
+    }
+
+    #[test]
+    fn test_write_mismatch() {
+        let bloom_filter = BloomFilter::new(100);
+        assert_eq!(
+            to_bytes(&bloom_filter).unwrap_err().to_string(),
+            "Unsupported filter: Murmur3Fnv hashing, Guava filters use Guava hashing"
+        );
+
+        let mut bloom_filter = BloomFilter::new(100);
+        bloom_filter.hash_scheme = HashScheme::Guava;
+        assert_eq!(
+            to_bytes(&bloom_filter).unwrap_err().to_string(),
+            "Unsupported filter: 959 bits, Guava filters hold up to 2^31 - 1 whole 64 bit words"
+        );
+    }
+}
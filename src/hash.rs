@@ -1,5 +1,17 @@
 mod fnv;
+mod leveldb;
 mod murmur3;
+mod murmur64a;
+mod siphash;
+mod xxh3;
+mod xxhash64;
+mod xxph3;
 
 pub use fnv::fnv;
-pub use murmur3::murmur3;
+pub use leveldb::leveldb_hash;
+pub use murmur3::{fmix64, murmur3, murmur3_x64_128};
+pub use murmur64a::murmur64a;
+pub use siphash::siphash24;
+pub use xxh3::xxh3_64;
+pub use xxhash64::xxhash64;
+pub use xxph3::xxph3_64;
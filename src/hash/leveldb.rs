@@ -0,0 +1,41 @@
+// ref: https://github.com/google/leveldb/blob/main/util/hash.cc
+
+const M: u32 = 0xc6a4_a793;
+const R: u32 = 24;
+
+pub fn leveldb_hash(data: &[u8], seed: u32) -> u32 {
+    let mut hash = seed ^ (data.len() as u32).wrapping_mul(M);
+    let mut chunks = data.chunks_exact(4);
+
+    for chunk in &mut chunks {
+        hash = hash.wrapping_add(u32::from_le_bytes(chunk.try_into().unwrap()));
+        hash = hash.wrapping_mul(M);
+        hash ^= hash >> 16;
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &byte) in tail.iter().enumerate() {
+            hash = hash.wrapping_add((byte as u32) << (8 * i));
+        }
+        hash = hash.wrapping_mul(M);
+        hash ^= hash >> R;
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leveldb_hash() {
+        // util/hash_test.cc
+        let seed = 0xbc9f1d34;
+        assert_eq!(leveldb_hash(&[], seed), 0xbc9f1d34);
+        assert_eq!(leveldb_hash(&[0x62], seed), 0xef1345c4);
+        assert_eq!(leveldb_hash(&[0xc3, 0x97], seed), 0x5b663814);
+        assert_eq!(leveldb_hash(&[0xe2, 0x99, 0xa5], seed), 0x323c078f);
+        assert_eq!(leveldb_hash(&[0xe1, 0x80, 0xb9, 0x32], seed), 0xed21633a);
+    }
+}
@@ -50,6 +50,69 @@ pub fn murmur3(data: &[u8], seed: u32) -> u32 {
     hash
 }
 
+const C1: u64 = 0x87c3_7b91_1142_53d5;
+const C2: u64 = 0x4cf5_ad43_2745_937f;
+
+// Murmur3 finalizer, also a good 64 bit mixer on its own
+pub fn fmix64(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    k ^= k >> 33;
+    k
+}
+
+fn mix_k1(k1: u64) -> u64 {
+    k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2)
+}
+
+fn mix_k2(k2: u64) -> u64 {
+    k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1)
+}
+
+// MurmurHash3_x64_128, returns (h1, h2) which make up the hash little endian
+pub fn murmur3_x64_128(data: &[u8], seed: u32) -> (u64, u64) {
+    let mut h1 = seed as u64;
+    let mut h2 = seed as u64;
+    let mut blocks = data.chunks_exact(16);
+
+    for block in &mut blocks {
+        let k1 = u64::from_le_bytes(block[..8].try_into().unwrap());
+        let k2 = u64::from_le_bytes(block[8..].try_into().unwrap());
+
+        h1 ^= mix_k1(k1);
+        h1 = h1.rotate_left(27).wrapping_add(h2);
+        h1 = h1.wrapping_mul(5).wrapping_add(0x52dc_e729);
+        h2 ^= mix_k2(k2);
+        h2 = h2.rotate_left(31).wrapping_add(h1);
+        h2 = h2.wrapping_mul(5).wrapping_add(0x3849_5ab5);
+    }
+
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        // Pad with zeros
+        let mut buffer = [0; 16];
+        buffer[..tail.len()].copy_from_slice(tail);
+        let k1 = u64::from_le_bytes(buffer[..8].try_into().unwrap());
+        let k2 = u64::from_le_bytes(buffer[8..].try_into().unwrap());
+        h1 ^= mix_k1(k1);
+        h2 ^= mix_k2(k2);
+    }
+
+    let len = data.len() as u64;
+    h1 ^= len;
+    h2 ^= len;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix64(h1);
+    h2 = fmix64(h2);
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+
+    (h1, h2)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -72,4 +135,25 @@ mod tests {
             0x2fa826cd
         );
     }
+
+    #[test]
+    fn test_murmur3_x64_128() {
+        assert_eq!(murmur3_x64_128(b"", 0), (0, 0));
+        assert_eq!(
+            murmur3_x64_128(b"hell", 0),
+            (0x629942693e10f867, 0x92db0b82baeb5347)
+        );
+        assert_eq!(
+            murmur3_x64_128(b"hello", 0),
+            (0xcbd8a7b341bd9b02, 0x5b1e906a48ae1d19)
+        );
+        assert_eq!(
+            murmur3_x64_128(b"The quick brown fox jumps over the lazy dog", 0),
+            (0xe34bbc7bbc071b6c, 0x7a433ca9c49a9347)
+        );
+        assert_eq!(
+            murmur3_x64_128(b"0123456789abcdefXYZ", 0),
+            (0x99d375026c4a901d, 0x4e69e256eaf89cf3)
+        );
+    }
 }
@@ -0,0 +1,56 @@
+// ref: https://github.com/aappleby/smhasher/blob/master/src/MurmurHash2.cpp (MurmurHash64A)
+
+const M: u64 = 0xc6a4_a793_5bd1_e995;
+const R: u32 = 47;
+
+pub fn murmur64a(data: &[u8], seed: u64) -> u64 {
+    let mut hash = seed ^ (data.len() as u64).wrapping_mul(M);
+    let mut chunks = data.chunks_exact(8);
+
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        hash ^= k;
+        hash = hash.wrapping_mul(M);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &byte) in tail.iter().enumerate() {
+            hash ^= (byte as u64) << (8 * i);
+        }
+        hash = hash.wrapping_mul(M);
+    }
+
+    hash ^= hash >> R;
+    hash = hash.wrapping_mul(M);
+    hash ^= hash >> R;
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_murmur64a() {
+        assert_eq!(murmur64a(b"", 0), 0);
+        assert_eq!(murmur64a(b"", M), 0x1ab11ea5a7b2c56e);
+        assert_eq!(murmur64a(b"a", 0), 0x071717d2d36b6b11);
+        assert_eq!(murmur64a(b"a", M), 0x4292cee227b9150a);
+        assert_eq!(murmur64a(b"test", 0), 0x2f4a8724618f4c63);
+        assert_eq!(murmur64a(b"test", M), 0x10e05dc9fc21a9a3);
+        assert_eq!(murmur64a(b"Hello, world!", 0), 0xa0fe1b7e284d2b19);
+        assert_eq!(murmur64a(b"Hello, world!", M), 0x965f7f1da1388e05);
+        assert_eq!(
+            murmur64a(b"The quick brown fox jumps over the lazy dog", 0),
+            0x5589ca33042a861b
+        );
+        assert_eq!(
+            murmur64a(b"The quick brown fox jumps over the lazy dog", M),
+            0xc7a616a28f4a74d6
+        );
+    }
+}
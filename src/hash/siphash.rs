@@ -0,0 +1,65 @@
+// ref: https://cr.yp.to/siphash/siphash-20120918.pdf (SipHash-2-4)
+
+fn sip_round(v: &mut [u64; 4]) {
+    v[0] = v[0].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(13) ^ v[0];
+    v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(16) ^ v[2];
+    v[0] = v[0].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(21) ^ v[0];
+    v[2] = v[2].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(17) ^ v[2];
+    v[2] = v[2].rotate_left(32);
+}
+
+// k0 and k1 are the key's two halves, read little endian
+pub fn siphash24(data: &[u8], k0: u64, k1: u64) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+
+    let mut words = data.chunks_exact(8);
+    for word in &mut words {
+        let m = u64::from_le_bytes(word.try_into().unwrap());
+        v[3] ^= m;
+        sip_round(&mut v);
+        sip_round(&mut v);
+        v[0] ^= m;
+    }
+    let mut last = [0; 8];
+    let rest = words.remainder();
+    last[..rest.len()].copy_from_slice(rest);
+    let m = u64::from_le_bytes(last) | (data.len() as u64) << 56;
+    v[3] ^= m;
+    sip_round(&mut v);
+    sip_round(&mut v);
+    v[0] ^= m;
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        sip_round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_siphash24() {
+        // Reference vectors: key 00..0f, message 00..(len - 1)
+        let k0 = 0x0706_0504_0302_0100;
+        let k1 = 0x0f0e_0d0c_0b0a_0908;
+        let message: Vec<u8> = (0..64).collect();
+        assert_eq!(siphash24(&message[..0], k0, k1), 0x726fdb47dd0e0e31);
+        assert_eq!(siphash24(&message[..1], k0, k1), 0x74f839c593dc67fd);
+        assert_eq!(siphash24(&message[..2], k0, k1), 0x0d6c8009d9a94f5a);
+        assert_eq!(siphash24(&message[..3], k0, k1), 0x85676696d7fb7e2d);
+        assert_eq!(siphash24(&message[..15], k0, k1), 0xa129ca6149be45e5);
+    }
+}
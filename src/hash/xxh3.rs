@@ -0,0 +1,210 @@
+// ref: https://github.com/Cyan4973/xxHash/blob/dev/doc/xxhash_spec.md (XXH3_64bits)
+//
+// Default secret and seed 0 only
+
+pub(super) const PRIME32_1: u64 = 0x9e37_79b1;
+pub(super) const PRIME32_2: u64 = 0x85eb_ca77;
+pub(super) const PRIME32_3: u64 = 0xc2b2_ae3d;
+pub(super) const PRIME64_1: u64 = 0x9e37_79b1_85eb_ca87;
+pub(super) const PRIME64_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+pub(super) const PRIME64_3: u64 = 0x1656_67b1_9e37_79f9;
+pub(super) const PRIME64_4: u64 = 0x85eb_ca77_c2b2_ae63;
+pub(super) const PRIME64_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+pub(super) const STRIPE_LEN: usize = 64;
+pub(super) const SECRET_CONSUME_RATE: usize = 8;
+pub(super) const SECRET: [u8; 192] = [
+    0xb8, 0xfe, 0x6c, 0x39, 0x23, 0xa4, 0x4b, 0xbe, 0x7c, 0x01, 0x81, 0x2c, 0xf7, 0x21, 0xad, 0x1c,
+    0xde, 0xd4, 0x6d, 0xe9, 0x83, 0x90, 0x97, 0xdb, 0x72, 0x40, 0xa4, 0xa4, 0xb7, 0xb3, 0x67, 0x1f,
+    0xcb, 0x79, 0xe6, 0x4e, 0xcc, 0xc0, 0xe5, 0x78, 0x82, 0x5a, 0xd0, 0x7d, 0xcc, 0xff, 0x72, 0x21,
+    0xb8, 0x08, 0x46, 0x74, 0xf7, 0x43, 0x24, 0x8e, 0xe0, 0x35, 0x90, 0xe6, 0x81, 0x3a, 0x26, 0x4c,
+    0x3c, 0x28, 0x52, 0xbb, 0x91, 0xc3, 0x00, 0xcb, 0x88, 0xd0, 0x65, 0x8b, 0x1b, 0x53, 0x2e, 0xa3,
+    0x71, 0x64, 0x48, 0x97, 0xa2, 0x0d, 0xf9, 0x4e, 0x38, 0x19, 0xef, 0x46, 0xa9, 0xde, 0xac, 0xd8,
+    0xa8, 0xfa, 0x76, 0x3f, 0xe3, 0x9c, 0x34, 0x3f, 0xf9, 0xdc, 0xbb, 0xc7, 0xc7, 0x0b, 0x4f, 0x1d,
+    0x8a, 0x51, 0xe0, 0x4b, 0xcd, 0xb4, 0x59, 0x31, 0xc8, 0x9f, 0x7e, 0xc9, 0xd9, 0x78, 0x73, 0x64,
+    0xea, 0xc5, 0xac, 0x83, 0x34, 0xd3, 0xeb, 0xc3, 0xc5, 0x81, 0xa0, 0xff, 0xfa, 0x13, 0x63, 0xeb,
+    0x17, 0x0d, 0xdd, 0x51, 0xb7, 0xf0, 0xda, 0x49, 0xd3, 0x16, 0x55, 0x26, 0x29, 0xd4, 0x68, 0x9e,
+    0x2b, 0x16, 0xbe, 0x58, 0x7d, 0x47, 0xa1, 0xfc, 0x8f, 0xf8, 0xb8, 0xd1, 0x7a, 0xd0, 0x31, 0xce,
+    0x45, 0xcb, 0x3a, 0x8f, 0x95, 0x16, 0x04, 0x28, 0xaf, 0xd7, 0xfb, 0xca, 0xbb, 0x4b, 0x40, 0x7e,
+];
+
+pub(super) fn read_u32(bytes: &[u8], offset: usize) -> u64 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as u64
+}
+
+pub(super) fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+pub(super) fn mul128_fold64(left: u64, right: u64) -> u64 {
+    let product = left as u128 * right as u128;
+    product as u64 ^ (product >> 64) as u64
+}
+
+fn xxh64_avalanche(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME64_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME64_3);
+    hash ^ hash >> 32
+}
+
+fn avalanche(mut hash: u64) -> u64 {
+    hash ^= hash >> 37;
+    hash = hash.wrapping_mul(0x1656_6791_9e37_79f9);
+    hash ^ hash >> 32
+}
+
+fn rrmxmx(mut hash: u64, len: u64) -> u64 {
+    hash ^= hash.rotate_left(49) ^ hash.rotate_left(24);
+    hash = hash.wrapping_mul(0x9fb2_1c65_1e98_df25);
+    hash ^= (hash >> 35).wrapping_add(len);
+    hash = hash.wrapping_mul(0x9fb2_1c65_1e98_df25);
+    hash ^ hash >> 28
+}
+
+fn mix16(data: &[u8], offset: usize, secret_offset: usize) -> u64 {
+    mul128_fold64(
+        read_u64(data, offset) ^ read_u64(&SECRET, secret_offset),
+        read_u64(data, offset + 8) ^ read_u64(&SECRET, secret_offset + 8),
+    )
+}
+
+fn hash_0_to_16(data: &[u8]) -> u64 {
+    let len = data.len();
+    match len {
+        0 => xxh64_avalanche(read_u64(&SECRET, 56) ^ read_u64(&SECRET, 64)),
+        1..=3 => {
+            let combined = (data[0] as u64) << 16
+                | (data[len >> 1] as u64) << 24
+                | data[len - 1] as u64
+                | (len as u64) << 8;
+            xxh64_avalanche(combined ^ (read_u32(&SECRET, 0) ^ read_u32(&SECRET, 4)))
+        }
+        4..=8 => {
+            let input = read_u32(data, len - 4).wrapping_add(read_u32(data, 0) << 32);
+            rrmxmx(
+                input ^ (read_u64(&SECRET, 8) ^ read_u64(&SECRET, 16)),
+                len as u64,
+            )
+        }
+        _ => {
+            let low = read_u64(data, 0) ^ read_u64(&SECRET, 24) ^ read_u64(&SECRET, 32);
+            let high = read_u64(data, len - 8) ^ read_u64(&SECRET, 40) ^ read_u64(&SECRET, 48);
+            let acc = (len as u64)
+                .wrapping_add(low.swap_bytes())
+                .wrapping_add(high)
+                .wrapping_add(mul128_fold64(low, high));
+            avalanche(acc)
+        }
+    }
+}
+
+fn hash_17_to_128(data: &[u8]) -> u64 {
+    let len = data.len();
+    let mut acc = (len as u64).wrapping_mul(PRIME64_1);
+    let rounds = (len - 1) / 32;
+    for i in (0..=rounds).rev() {
+        acc = acc.wrapping_add(mix16(data, 16 * i, 32 * i));
+        acc = acc.wrapping_add(mix16(data, len - 16 * (i + 1), 32 * i + 16));
+    }
+    avalanche(acc)
+}
+
+fn hash_129_to_240(data: &[u8]) -> u64 {
+    let len = data.len();
+    let mut acc = (len as u64).wrapping_mul(PRIME64_1);
+    for i in 0..8 {
+        acc = acc.wrapping_add(mix16(data, 16 * i, 16 * i));
+    }
+    acc = avalanche(acc);
+    for i in 8..len / 16 {
+        acc = acc.wrapping_add(mix16(data, 16 * i, 16 * (i - 8) + 3));
+    }
+    acc = acc.wrapping_add(mix16(data, len - 16, 136 - 17));
+    avalanche(acc)
+}
+
+fn accumulate_stripe(acc: &mut [u64; 8], stripe: &[u8], secret_offset: usize) {
+    for i in 0..8 {
+        let value = read_u64(stripe, 8 * i);
+        let key = value ^ read_u64(&SECRET, secret_offset + 8 * i);
+        acc[i ^ 1] = acc[i ^ 1].wrapping_add(value);
+        acc[i] = acc[i].wrapping_add((key & 0xffff_ffff).wrapping_mul(key >> 32));
+    }
+}
+
+fn scramble(acc: &mut [u64; 8]) {
+    for (i, lane) in acc.iter_mut().enumerate() {
+        let key = read_u64(&SECRET, SECRET.len() - STRIPE_LEN + 8 * i);
+        *lane = (*lane ^ *lane >> 47 ^ key).wrapping_mul(PRIME32_1);
+    }
+}
+
+fn hash_long(data: &[u8]) -> u64 {
+    let len = data.len();
+    let mut acc = [
+        PRIME32_3, PRIME64_1, PRIME64_2, PRIME64_3, PRIME64_4, PRIME32_2, PRIME64_5, PRIME32_1,
+    ];
+    let stripes_per_block = (SECRET.len() - STRIPE_LEN) / SECRET_CONSUME_RATE;
+    let block_len = STRIPE_LEN * stripes_per_block;
+    let blocks = (len - 1) / block_len;
+
+    for block in 0..blocks {
+        for stripe in 0..stripes_per_block {
+            let offset = block * block_len + stripe * STRIPE_LEN;
+            accumulate_stripe(&mut acc, &data[offset..], stripe * SECRET_CONSUME_RATE);
+        }
+        scramble(&mut acc);
+    }
+    let stripes = (len - 1 - block_len * blocks) / STRIPE_LEN;
+    for stripe in 0..stripes {
+        let offset = blocks * block_len + stripe * STRIPE_LEN;
+        accumulate_stripe(&mut acc, &data[offset..], stripe * SECRET_CONSUME_RATE);
+    }
+    accumulate_stripe(
+        &mut acc,
+        &data[len - STRIPE_LEN..],
+        SECRET.len() - STRIPE_LEN - 7,
+    );
+
+    let mut result = (len as u64).wrapping_mul(PRIME64_1);
+    for i in 0..4 {
+        result = result.wrapping_add(mul128_fold64(
+            acc[2 * i] ^ read_u64(&SECRET, 11 + 16 * i),
+            acc[2 * i + 1] ^ read_u64(&SECRET, 11 + 16 * i + 8),
+        ));
+    }
+    avalanche(result)
+}
+
+pub fn xxh3_64(data: &[u8]) -> u64 {
+    match data.len() {
+        0..=16 => hash_0_to_16(data),
+        17..=128 => hash_17_to_128(data),
+        129..=240 => hash_129_to_240(data),
+        _ => hash_long(data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xxh3_64() {
+        assert_eq!(xxh3_64(b""), 0x2d06800538d394c2);
+        assert_eq!(xxh3_64(b"a"), 0xe6c632b61e964e1f);
+        assert_eq!(xxh3_64(b"abc"), 0x78af5f94892f3950);
+        assert_eq!(xxh3_64(b"hello"), 0x9555e8555c62dcfd);
+        assert_eq!(xxh3_64(b"rocksdb!"), 0x97bda4d076d9b6a5);
+        assert_eq!(xxh3_64(b"hello world!"), 0xe155d613728f4b18);
+        assert_eq!(
+            xxh3_64(b"Nobody inspects the spammish repetition"),
+            0x6cb00603b5cc47e9
+        );
+        let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        assert_eq!(xxh3_64(&data[..200]), 0xf42a8864feaf0703);
+        assert_eq!(xxh3_64(&data), 0x33ef703fb2b20ed1);
+    }
+}
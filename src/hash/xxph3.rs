@@ -0,0 +1,200 @@
+// ref: https://github.com/facebook/rocksdb/blob/main/util/xxph3.h (XXPH3_64bits)
+//
+// RocksDB's frozen copy of the XXH3 preview, behind its `Hash64`. It shares
+// the secret and accumulator with the final XXH3 but mixes short inputs,
+// avalanches and accumulates differently, so the two disagree on every
+// length. Default secret and seed 0 only, as RocksDB hashes filter keys
+
+use super::xxh3::{
+    mul128_fold64, read_u32, read_u64, PRIME32_1, PRIME32_2, PRIME32_3, PRIME64_1, PRIME64_2,
+    PRIME64_3, PRIME64_4, PRIME64_5, SECRET, SECRET_CONSUME_RATE, STRIPE_LEN,
+};
+
+fn avalanche(mut hash: u64) -> u64 {
+    hash ^= hash >> 37;
+    hash = hash.wrapping_mul(PRIME64_3);
+    hash ^ hash >> 32
+}
+
+fn mix16(data: &[u8], offset: usize, secret_offset: usize) -> u64 {
+    mul128_fold64(
+        read_u64(data, offset) ^ read_u64(&SECRET, secret_offset),
+        read_u64(data, offset + 8) ^ read_u64(&SECRET, secret_offset + 8),
+    )
+}
+
+fn hash_0_to_16(data: &[u8]) -> u64 {
+    let len = data.len();
+    match len {
+        // RocksDB's change from the preview, which returned 0
+        0 => mul128_fold64(read_u64(&SECRET, 0), PRIME64_2),
+        1..=3 => {
+            let combined = data[0] as u64
+                | (data[len >> 1] as u64) << 8
+                | (data[len - 1] as u64) << 16
+                | (len as u64) << 24;
+            avalanche((combined ^ read_u32(&SECRET, 0)).wrapping_mul(PRIME64_1))
+        }
+        4..=8 => {
+            let input = read_u32(data, 0) | read_u32(data, len - 4) << 32;
+            let keyed = input ^ read_u64(&SECRET, 0);
+            let mix = (len as u64).wrapping_add((keyed ^ keyed >> 51).wrapping_mul(PRIME32_1));
+            avalanche((mix ^ mix >> 47).wrapping_mul(PRIME64_2))
+        }
+        _ => {
+            let low = read_u64(data, 0) ^ read_u64(&SECRET, 0);
+            let high = read_u64(data, len - 8) ^ read_u64(&SECRET, 8);
+            let acc = (len as u64)
+                .wrapping_add(low.wrapping_add(high))
+                .wrapping_add(mul128_fold64(low, high));
+            avalanche(acc)
+        }
+    }
+}
+
+fn hash_17_to_128(data: &[u8]) -> u64 {
+    let len = data.len();
+    let mut acc = (len as u64).wrapping_mul(PRIME64_1);
+    let rounds = (len - 1) / 32;
+    for i in (0..=rounds).rev() {
+        acc = acc.wrapping_add(mix16(data, 16 * i, 32 * i));
+        acc = acc.wrapping_add(mix16(data, len - 16 * (i + 1), 32 * i + 16));
+    }
+    avalanche(acc)
+}
+
+fn hash_129_to_240(data: &[u8]) -> u64 {
+    let len = data.len();
+    let mut acc = (len as u64).wrapping_mul(PRIME64_1);
+    for i in 0..8 {
+        acc = acc.wrapping_add(mix16(data, 16 * i, 16 * i));
+    }
+    acc = avalanche(acc);
+    for i in 8..len / 16 {
+        acc = acc.wrapping_add(mix16(data, 16 * i, 16 * (i - 8) + 3));
+    }
+    acc = acc.wrapping_add(mix16(data, len - 16, 136 - 17));
+    avalanche(acc)
+}
+
+// 64 bit accumulator width: no swapping of adjacent lanes
+fn accumulate_stripe(acc: &mut [u64; 8], stripe: &[u8], secret_offset: usize) {
+    for (i, lane) in acc.iter_mut().enumerate() {
+        let value = read_u64(stripe, 8 * i);
+        let key = value ^ read_u64(&SECRET, secret_offset + 8 * i);
+        *lane = lane
+            .wrapping_add(value)
+            .wrapping_add((key & 0xffff_ffff).wrapping_mul(key >> 32));
+    }
+}
+
+fn scramble(acc: &mut [u64; 8]) {
+    for (i, lane) in acc.iter_mut().enumerate() {
+        let key = read_u64(&SECRET, SECRET.len() - STRIPE_LEN + 8 * i);
+        *lane = (*lane ^ *lane >> 47 ^ key).wrapping_mul(PRIME32_1);
+    }
+}
+
+fn hash_long(data: &[u8]) -> u64 {
+    let len = data.len();
+    let mut acc = [
+        PRIME32_3, PRIME64_1, PRIME64_2, PRIME64_3, PRIME64_4, PRIME32_2, PRIME64_5, PRIME32_1,
+    ];
+    let stripes_per_block = (SECRET.len() - STRIPE_LEN) / SECRET_CONSUME_RATE;
+    let block_len = STRIPE_LEN * stripes_per_block;
+    let blocks = len / block_len;
+
+    for block in 0..blocks {
+        for stripe in 0..stripes_per_block {
+            let offset = block * block_len + stripe * STRIPE_LEN;
+            accumulate_stripe(&mut acc, &data[offset..], stripe * SECRET_CONSUME_RATE);
+        }
+        scramble(&mut acc);
+    }
+    let stripes = (len - block_len * blocks) / STRIPE_LEN;
+    for stripe in 0..stripes {
+        let offset = blocks * block_len + stripe * STRIPE_LEN;
+        accumulate_stripe(&mut acc, &data[offset..], stripe * SECRET_CONSUME_RATE);
+    }
+    // Unlike XXH3, a length that is a multiple of the stripe skips this
+    if !len.is_multiple_of(STRIPE_LEN) {
+        accumulate_stripe(
+            &mut acc,
+            &data[len - STRIPE_LEN..],
+            SECRET.len() - STRIPE_LEN - 7,
+        );
+    }
+
+    let mut result = (len as u64).wrapping_mul(PRIME64_1);
+    for i in 0..4 {
+        result = result.wrapping_add(mul128_fold64(
+            acc[2 * i] ^ read_u64(&SECRET, 11 + 16 * i),
+            acc[2 * i + 1] ^ read_u64(&SECRET, 11 + 16 * i + 8),
+        ));
+    }
+    avalanche(result)
+}
+
+pub fn xxph3_64(data: &[u8]) -> u64 {
+    match data.len() {
+        0..=16 => hash_0_to_16(data),
+        17..=128 => hash_17_to_128(data),
+        129..=240 => hash_129_to_240(data),
+        _ => hash_long(data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Vectors from RocksDB's util/hash_test.cc
+    #[test]
+    fn test_xxph3_64() {
+        assert_eq!(xxph3_64(b""), 5999572062939766020);
+        assert_eq!(xxph3_64(b"\x08"), 583283813901344696);
+        assert_eq!(xxph3_64(b"\x4d\x76"), 6859542833406258115);
+        assert_eq!(xxph3_64(b"\x30\x46\x0b"), 11404699285340020889);
+        assert_eq!(xxph3_64(b"\x67\x53\x81\x1c"), 9010661983527562386);
+        assert_eq!(xxph3_64(b"\xd0\x7a\x6e\xea\x56"), 6384167718754869899);
+        assert_eq!(xxph3_64(b"\x5c\x5e\xe1\xa0\x73\x81"), 17358142495308219330);
+        assert_eq!(
+            xxph3_64(b"\x5c\xbe\x97\x75\x54\x9a\x52"),
+            5716522398211028826
+        );
+        assert_eq!(
+            xxph3_64(b"\x31\x1b\x98\x75\x96\x22\xd3\x9a"),
+            9844314944338447628
+        );
+        assert_eq!(
+            xxph3_64(b"\x1a\xb5\xd0\xfe\xab\xc3\x61\xb2\x99"),
+            13253403748084181481
+        );
+        assert_eq!(
+            xxph3_64(b"\xbd\x2c\x63\x38\xbf\xe9\x78\xb7\xbf\x15"),
+            10551812464348219044
+        );
+    }
+
+    // hash_test.cc's Hash64LargeValueSchema: one character per prefix length
+    // of "RocksDB" repeated, covering every size class
+    #[test]
+    fn test_xxph3_64_lengths() {
+        let alphabet = b"abcdefghijklmnopqrstuvwxyz123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+        let input = b"RocksDB".repeat(62);
+        let descriptor: String = (0..430)
+            .map(|len| alphabet[(xxph3_64(&input[..len]) % 61) as usize] as char)
+            .collect();
+        assert_eq!(
+            descriptor,
+            concat!(
+                "c1EeRkukbkb28wLTahwD2sfUhZzaBEnF8SVrxnPVB6A7b8CaAl3UKsDZISF92GSq2wDCukOq",
+                "Jgrsp7A3KZhDiLW8dFXp8UPqPxMCRlMdZeVeJ2dJxrmA6cyt99zkQFj7ELbut6jAeVqARFnw",
+                "fnWVXOsaLrq7bDCbMcns2DKvTaaqTCLMYxI7nhtLpFN1jR755FRQFcOzrrDbh7QhypjdvlYw",
+                "cdAMSZgp9JMHxbM23wPSuH6BOFgxejz35PScZfhDPvTOxIy1jc3MZsWrMC3P324zNolO7JdW",
+                "CX2I5UDKjjaEJfxbgVgJIXxtQGlmj2xkO5sPpjULQV4X2HlY7FQleJ4QRaJIB4buhCA4vUTF",
+                "eMFlxCIYUpTCsal2qsmnGOWa8WCcefrohMjDj1fjzSvSaQwlpyR1GZHF2uPOoQagiCpHpm",
+            )
+        );
+    }
+}
@@ -0,0 +1,402 @@
+use crate::error::BloomFilterError;
+use crate::format::{FilterKind, Persistable};
+use crate::hash::xxh3_64;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+mod bias;
+
+pub(crate) const MIN_PRECISION: u32 = 4;
+pub(crate) const MAX_PRECISION: u32 = 18;
+pub(crate) const SPARSE_PRECISION: u32 = 25;
+// Cardinalities below which linear counting beats the bias corrected
+// estimate, for precisions 4..=18
+const THRESHOLDS: [f64; 15] = [
+    10.0, 20.0, 40.0, 80.0, 220.0, 400.0, 900.0, 1800.0, 3100.0, 6500.0, 11500.0, 20000.0, 50000.0,
+    120000.0, 350000.0,
+];
+const BIAS_NEIGHBORS: usize = 6;
+
+// HyperLogLog++, ref: Heule, Nunkesser & Hall (2013), "HyperLogLog in
+// Practice: Algorithmic Engineering of a State of The Art Cardinality
+// Estimation Algorithm"
+//
+// Each key's 64 bit hash picks one of 2^p registers with its top bits and
+// keeps there the longest run of leading zeros seen in the rest. Small
+// sketches start sparse: only registers in use are kept, at 25 bits of
+// precision, until they would outgrow the dense registers.
+//
+// Dense sketches correct the raw estimate's bias for small cardinalities
+// with the paper's empirical tables, and fall back to linear counting over
+// the empty registers below a threshold per precision.
+#[derive(Debug, PartialEq, Clone)]
+pub struct HyperLogLog {
+    pub(crate) precision: u32,
+    pub(crate) registers: Registers,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Registers {
+    // Sparse register index to the rank at that precision
+    Sparse(BTreeMap<u32, u8>),
+    Dense(Vec<u8>),
+}
+
+impl HyperLogLog {
+    // 2^precision registers, the standard error is about 1.04 / 2^(p/2)
+    pub fn new(precision: u32) -> Result<Self> {
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+            let reason = format!(
+                "precision must be in [{}, {}]",
+                MIN_PRECISION, MAX_PRECISION
+            );
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        Ok(Self {
+            precision,
+            registers: Registers::Sparse(BTreeMap::new()),
+        })
+    }
+
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) {
+        let hash = xxh3_64(key.as_ref());
+        match &mut self.registers {
+            Registers::Sparse(sparse) => {
+                let (index, rank) = split(hash, SPARSE_PRECISION);
+                let entry = sparse.entry(index).or_insert(0);
+                *entry = (*entry).max(rank);
+                if sparse.len() > self.sparse_limit() {
+                    self.densify();
+                }
+            }
+            Registers::Dense(registers) => {
+                let (index, rank) = split(hash, self.precision);
+                let register = &mut registers[index as usize];
+                *register = (*register).max(rank);
+            }
+        }
+    }
+
+    // Estimated number of distinct keys inserted
+    pub fn estimate(&self) -> f64 {
+        match &self.registers {
+            // Linear counting over the 2^25 sparse registers
+            Registers::Sparse(sparse) => {
+                let m = (1u64 << SPARSE_PRECISION) as f64;
+                m * (m / (m - sparse.len() as f64)).ln()
+            }
+            Registers::Dense(registers) => {
+                let m = registers.len() as f64;
+                let sum: f64 = registers.iter().map(|&rank| 0.5f64.powi(rank as i32)).sum();
+                let raw = alpha(registers.len()) * m * m / sum;
+                let corrected = if raw <= 5.0 * m {
+                    raw - self.bias(raw)
+                } else {
+                    raw
+                };
+                let zeros = registers.iter().filter(|&&rank| rank == 0).count();
+                if zeros == 0 {
+                    return corrected;
+                }
+                let linear = m * (m / zeros as f64).ln();
+                if linear <= THRESHOLDS[(self.precision - MIN_PRECISION) as usize] {
+                    linear
+                } else {
+                    corrected
+                }
+            }
+        }
+    }
+
+    // Union of both sketches, which must have the same precision
+    pub fn merge(&mut self, other: &Self) -> Result<()> {
+        if self.precision != other.precision {
+            let reason = format!("precision {} != {}", self.precision, other.precision);
+            return Err(BloomFilterError::Incompatible(reason).into());
+        }
+
+        if let (Registers::Sparse(sparse), Registers::Sparse(other_sparse)) =
+            (&mut self.registers, &other.registers)
+        {
+            for (&index, &rank) in other_sparse {
+                let entry = sparse.entry(index).or_insert(0);
+                *entry = (*entry).max(rank);
+            }
+            if sparse.len() > self.sparse_limit() {
+                self.densify();
+            }
+            return Ok(());
+        }
+
+        self.densify();
+        let Registers::Dense(registers) = &mut self.registers else {
+            unreachable!()
+        };
+        for (index, rank) in other.dense_entries() {
+            let register = &mut registers[index];
+            *register = (*register).max(rank);
+        }
+        Ok(())
+    }
+
+    pub fn precision(&self) -> u32 {
+        self.precision
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        matches!(self.registers, Registers::Sparse(_))
+    }
+
+    // Standard error of the estimate, relative to the cardinality
+    pub fn standard_error(&self) -> f64 {
+        1.04 / ((1u64 << self.precision) as f64).sqrt()
+    }
+
+    // Mean bias of the 6 raw estimates in the tables nearest to `raw`
+    fn bias(&self, raw: f64) -> f64 {
+        let precision = (self.precision - MIN_PRECISION) as usize;
+        let mut nearest: Vec<(f64, f64)> = bias::RAW_ESTIMATES[precision]
+            .iter()
+            .zip(bias::BIASES[precision])
+            .map(|(&estimate, &bias)| ((estimate - raw).abs(), bias))
+            .collect();
+        nearest.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let nearest = &nearest[..BIAS_NEIGHBORS];
+        nearest.iter().map(|&(_, bias)| bias).sum::<f64>() / nearest.len() as f64
+    }
+
+    // Sparse entries take 4 bytes in the encoding, dense registers one
+    pub(crate) fn sparse_limit(&self) -> usize {
+        (1 << self.precision) / 4
+    }
+
+    fn densify(&mut self) {
+        if self.is_sparse() {
+            let mut registers = vec![0u8; 1 << self.precision];
+            for (index, rank) in self.dense_entries() {
+                registers[index] = registers[index].max(rank);
+            }
+            self.registers = Registers::Dense(registers);
+        }
+    }
+
+    // Register index and rank at the sketch's precision
+    fn dense_entries(&self) -> Box<dyn Iterator<Item = (usize, u8)> + '_> {
+        match &self.registers {
+            Registers::Sparse(sparse) => Box::new(
+                sparse
+                    .iter()
+                    .map(|(&index, &rank)| dense_entry(index, rank, self.precision)),
+            ),
+            Registers::Dense(registers) => Box::new(
+                registers
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &rank)| (index, rank)),
+            ),
+        }
+    }
+}
+
+impl Persistable for HyperLogLog {
+    fn kind() -> FilterKind {
+        FilterKind::HyperLogLog
+    }
+}
+
+// Top `precision` bits as the register index, then the rank: one more than
+// the leading zeros of the other bits, at most 65 - precision
+fn split(hash: u64, precision: u32) -> (u32, u8) {
+    let index = (hash >> (64 - precision)) as u32;
+    let rank = ((hash << precision) | 1 << (precision - 1)).leading_zeros() + 1;
+    (index, rank as u8)
+}
+
+// A sparse index carries 25 - p more hash bits, the leading zeros start
+// there unless they are all zero
+fn dense_entry(index: u32, rank: u8, precision: u32) -> (usize, u8) {
+    let extra_bits = SPARSE_PRECISION - precision;
+    let extra = index & ((1 << extra_bits) - 1);
+    let rank = match extra {
+        0 => extra_bits as u8 + rank,
+        _ => (extra.leading_zeros() - (32 - extra_bits) + 1) as u8,
+    };
+    ((index >> extra_bits) as usize, rank)
+}
+
+fn alpha(m: usize) -> f64 {
+    match m {
+        16 => 0.673,
+        32 => 0.697,
+        64 => 0.709,
+        _ => 0.7213 / (1.0 + 1.079 / m as f64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encodable;
+    use crate::test_util::prepare_tmp_dir;
+    use std::fs;
+
+    fn sketch(precision: u32, range: std::ops::Range<usize>) -> HyperLogLog {
+        let mut sketch = HyperLogLog::new(precision).unwrap();
+        for i in range {
+            sketch.insert(&format!("key-{}", i));
+        }
+        sketch
+    }
+
+    fn assert_close(sketch: &HyperLogLog, cardinality: usize) {
+        let error = (sketch.estimate() - cardinality as f64).abs();
+        let bound = 4.0 * sketch.standard_error() * cardinality as f64;
+        assert!(
+            error <= bound.max(1.0),
+            "{} for {}",
+            sketch.estimate(),
+            cardinality
+        );
+    }
+
+    #[test]
+    fn test_estimate() {
+        let mut sketch = HyperLogLog::new(14).unwrap();
+        assert_eq!(sketch.estimate(), 0.0);
+        let mut inserted = 0;
+        for cardinality in [1, 10, 100, 1000, 4096, 4097, 10_000, 100_000, 300_000] {
+            for i in inserted..cardinality {
+                sketch.insert(&format!("key-{}", i));
+            }
+            inserted = cardinality;
+            assert_close(&sketch, cardinality);
+            assert_eq!(sketch.is_sparse(), cardinality <= 4096);
+        }
+
+        // Duplicates do not count
+        let before = sketch.estimate();
+        for i in 0..1000 {
+            sketch.insert(&format!("key-{}", i));
+        }
+        assert_eq!(sketch.estimate(), before);
+    }
+
+    #[test]
+    fn test_precision() {
+        for precision in [MIN_PRECISION, 8, 12, MAX_PRECISION] {
+            for cardinality in [50, 20_000] {
+                assert_close(&sketch(precision, 0..cardinality), cardinality);
+            }
+        }
+        assert!(HyperLogLog::new(3).is_err());
+        assert!(HyperLogLog::new(19).is_err());
+        assert_eq!(HyperLogLog::new(10).unwrap().standard_error(), 1.04 / 32.0);
+    }
+
+    #[test]
+    fn test_bias_correction() {
+        let sketch = HyperLogLog::new(4).unwrap();
+        let first: f64 = bias::BIASES[0][..6].iter().sum();
+        assert_eq!(sketch.bias(0.0), first / 6.0);
+        let last: f64 = bias::BIASES[0][bias::BIASES[0].len() - 6..].iter().sum();
+        assert_eq!(sketch.bias(1e9), last / 6.0);
+
+        // Between 2 and 5 times the registers, past the linear counting
+        // threshold, where the raw estimate is biased upwards
+        let runs = 50;
+        let cardinality = 2500;
+        let mean_error = (0..runs)
+            .map(|run| {
+                let mut sketch = HyperLogLog::new(10).unwrap();
+                for i in 0..cardinality {
+                    sketch.insert(&format!("{}-{}", run, i));
+                }
+                sketch.estimate() / cardinality as f64 - 1.0
+            })
+            .sum::<f64>()
+            / runs as f64;
+        assert!(mean_error.abs() < 0.01, "{}", mean_error);
+    }
+
+    #[test]
+    fn test_dense_entry() {
+        // The same hash gives the same register either way
+        for hash in [0, 1, u64::MAX, 0x0000_4000_0000_0000, 0x1234_5678_9abc_def0] {
+            for precision in [MIN_PRECISION, 14, MAX_PRECISION] {
+                let (index, rank) = split(hash, SPARSE_PRECISION);
+                assert_eq!(
+                    dense_entry(index, rank, precision),
+                    {
+                        let (index, rank) = split(hash, precision);
+                        (index as usize, rank)
+                    },
+                    "{:x} {}",
+                    hash,
+                    precision
+                );
+            }
+        }
+        assert_eq!(split(0, 14), (0, 51));
+    }
+
+    #[test]
+    fn test_merge() {
+        // Sparse and dense sketches of overlapping key sets
+        let ranges = [0..1000, 500..1500, 0..50_000, 20_000..80_000];
+        for left in ranges.clone() {
+            for right in ranges.clone() {
+                let mut merged = sketch(12, left.clone());
+                merged.merge(&sketch(12, right.clone())).unwrap();
+                // The same registers as inserting both sets in one sketch
+                let mut expected = sketch(12, left.clone());
+                for i in right.clone() {
+                    expected.insert(&format!("key-{}", i));
+                }
+                if !merged.is_sparse() && !expected.is_sparse() {
+                    assert_eq!(merged, expected);
+                }
+                let overlap = left
+                    .end
+                    .min(right.end)
+                    .saturating_sub(left.start.max(right.start));
+                assert_close(&merged, left.len() + right.len() - overlap);
+            }
+        }
+
+        let mut sketch = sketch(12, 0..10);
+        assert_eq!(
+            sketch
+                .merge(&HyperLogLog::new(13).unwrap())
+                .unwrap_err()
+                .to_string(),
+            "Incompatible filters: precision 12 != 13"
+        );
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        for cardinality in [10, 10_000] {
+            let sketch = sketch(12, 0..cardinality);
+            let bytes = sketch.to_bytes();
+            assert_eq!(&bytes[..6], b"BFYS\x04\x0b");
+            assert_eq!(&bytes[6..], sketch.encode());
+            assert_eq!(HyperLogLog::from_bytes(&bytes).unwrap(), sketch);
+        }
+        let bytes = sketch(12, 0..10).to_bytes();
+        assert!(crate::BloomFilter::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_persist_local_file() {
+        prepare_tmp_dir();
+        let test_file = "tmp/hyperloglog_test_persist_local_file.bin";
+        let sketch = sketch(14, 0..100);
+        sketch.to_file(test_file).unwrap();
+
+        let loaded = HyperLogLog::from_file(test_file).unwrap();
+        assert_eq!(loaded, sketch);
+        assert_eq!(loaded.estimate(), sketch.estimate());
+
+        fs::remove_file(test_file).unwrap();
+    }
+}
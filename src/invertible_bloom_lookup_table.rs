@@ -0,0 +1,342 @@
+mod builder;
+
+use crate::error::BloomFilterError;
+use crate::format::{FilterKind, Persistable};
+use crate::hash::{xxh3_64, xxhash64};
+use anyhow::Result;
+pub use builder::InvertibleBloomLookupTableBuilder;
+
+// ref: Goodrich & Mitzenmacher (2011), "Invertible Bloom Lookup Tables";
+// Eppstein, Goodrich, Uyeda & Varghese (2011), "What's the Difference?
+// Efficient Set Reconciliation without Prior Context"
+//
+// A key is added to one cell in each of `hash_count` partitions. Cells keep
+// a count, the xor of their keys and the xor of the keys' checksums.
+// Subtracting the table of another set cancels the keys both hold, and the
+// rest can be peeled off cells left with a single key, as long as the
+// difference stays small next to the number of cells.
+//
+// A key is xored into `keySum` as its big endian u16 length then its bytes,
+// zero padded to `key_size`, so keys of any length up to it share cells.
+#[derive(Debug, PartialEq, Clone)]
+pub struct InvertibleBloomLookupTable {
+    pub(crate) cells: Vec<Cell>,
+    pub(crate) hash_count: usize,
+    pub(crate) key_size: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Cell {
+    pub(crate) count: i64,
+    pub(crate) key_sum: Vec<u8>,
+    pub(crate) hash_sum: u64,
+}
+
+// Keys peeled from a subtraction, sorted
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Difference {
+    // Held by the table subtracted from, missing from the other
+    pub left_only: Vec<Vec<u8>>,
+    // Held by the other table only
+    pub right_only: Vec<Vec<u8>>,
+}
+
+impl InvertibleBloomLookupTable {
+    // Sized to peel differences of up to `difference` keys
+    pub fn new(difference: usize) -> Result<Self> {
+        InvertibleBloomLookupTableBuilder::new(difference).build()
+    }
+
+    // Fails with `BloomFilterError::InvalidParams` for keys longer than the
+    // key size
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> Result<()> {
+        self.update(key.as_ref(), 1)
+    }
+
+    // A key removed without being inserted peels as one of the other side
+    pub fn remove<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> Result<()> {
+        self.update(key.as_ref(), -1)
+    }
+
+    // Cell by cell difference of the tables, keys held by both cancel out
+    pub fn subtract(&self, other: &Self) -> Result<Self> {
+        let dimensions = |table: &Self| (table.cells.len(), table.hash_count, table.key_size);
+        if dimensions(self) != dimensions(other) {
+            let reason = format!(
+                "{} cells, {} hashes, key size {} != {} cells, {} hashes, key size {}",
+                self.cells.len(),
+                self.hash_count,
+                self.key_size,
+                other.cells.len(),
+                other.hash_count,
+                other.key_size
+            );
+            return Err(BloomFilterError::Incompatible(reason).into());
+        }
+
+        let cells = self
+            .cells
+            .iter()
+            .zip(&other.cells)
+            .map(|(cell, other)| Cell {
+                count: cell.count.wrapping_sub(other.count),
+                key_sum: xor(&cell.key_sum, &other.key_sum),
+                hash_sum: cell.hash_sum ^ other.hash_sum,
+            })
+            .collect();
+        Ok(Self {
+            cells,
+            hash_count: self.hash_count,
+            key_size: self.key_size,
+        })
+    }
+
+    // Lists every key of the table, those counted once in `left_only` and
+    // those counted minus once in `right_only`. Meant for the result of
+    // `subtract`. Fails with `BloomFilterError::PeelFailed` when cells are
+    // left that hold several keys, once the difference outgrew the table.
+    pub fn peel(&self) -> Result<Difference> {
+        let mut table = self.clone();
+        let mut difference = Difference::default();
+        let mut pending: Vec<usize> = (0..table.cells.len()).collect();
+        let mut peeled = 0;
+        // Every peel empties a cell for good, more keys than cells only come
+        // from checksum collisions
+        while let Some(i) = pending.pop().filter(|_| peeled < table.cells.len()) {
+            let Some(key) = table.pure_key(i) else {
+                continue;
+            };
+            let count = table.cells[i].count;
+            let indexes = table.hashing(&key);
+            table.apply(&key, &indexes, -count);
+            pending.extend(indexes);
+            peeled += 1;
+            if count == 1 {
+                difference.left_only.push(key);
+            } else {
+                difference.right_only.push(key);
+            }
+        }
+
+        let remaining = table.cells.iter().filter(|cell| !cell.is_empty()).count();
+        if remaining > 0 {
+            let reason = format!(
+                "{} of {} cells still hold keys after peeling {}",
+                remaining,
+                table.cells.len(),
+                peeled
+            );
+            return Err(BloomFilterError::PeelFailed(reason).into());
+        }
+        difference.left_only.sort();
+        difference.right_only.sort();
+        Ok(difference)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(Cell::is_empty)
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn hash_count(&self) -> usize {
+        self.hash_count
+    }
+
+    // Longest key the table takes, in bytes
+    pub fn key_size(&self) -> usize {
+        self.key_size
+    }
+
+    fn update(&mut self, key: &[u8], count: i64) -> Result<()> {
+        if key.len() > self.key_size {
+            let reason = format!(
+                "key of {} bytes exceeds the key size {}",
+                key.len(),
+                self.key_size
+            );
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        let indexes = self.hashing(key);
+        self.apply(key, &indexes, count);
+        Ok(())
+    }
+
+    fn apply(&mut self, key: &[u8], indexes: &[usize], count: i64) {
+        let hash = xxh3_64(key);
+        let mut padded = vec![0; self.key_size + 2];
+        padded[..2].copy_from_slice(&(key.len() as u16).to_be_bytes());
+        padded[2..2 + key.len()].copy_from_slice(key);
+        for &i in indexes {
+            let cell = &mut self.cells[i];
+            cell.count = cell.count.wrapping_add(count);
+            cell.key_sum = xor(&cell.key_sum, &padded);
+            cell.hash_sum ^= hash;
+        }
+    }
+
+    // One cell in each partition, so a key never meets itself. Hashes are
+    // seeded apart rather than double hashed: two keys then share all
+    // their cells with odds 1 / partition^k instead of 1 / partition^2,
+    // and such a pair can never be peeled.
+    fn hashing(&self, key: &[u8]) -> Vec<usize> {
+        let partition = self.cells.len() / self.hash_count;
+        (0..self.hash_count)
+            .map(|i| i * partition + (xxhash64(key, i as u64) % partition as u64) as usize)
+            .collect()
+    }
+
+    // The key of a cell holding a single key, counted once either way, and
+    // matching its checksum
+    fn pure_key(&self, i: usize) -> Option<Vec<u8>> {
+        let cell = &self.cells[i];
+        if cell.count != 1 && cell.count != -1 {
+            return None;
+        }
+        let len = u16::from_be_bytes([cell.key_sum[0], cell.key_sum[1]]) as usize;
+        let key = cell.key_sum.get(2..2 + len)?;
+        let padding = &cell.key_sum[2 + len..];
+        (padding.iter().all(|&byte| byte == 0) && xxh3_64(key) == cell.hash_sum)
+            .then(|| key.to_vec())
+    }
+}
+
+impl Persistable for InvertibleBloomLookupTable {
+    fn kind() -> FilterKind {
+        FilterKind::Invertible
+    }
+}
+
+impl Cell {
+    pub(crate) fn new(key_size: usize) -> Self {
+        Self {
+            count: 0,
+            key_sum: vec![0; key_size + 2],
+            hash_sum: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.count == 0 && self.hash_sum == 0 && self.key_sum.iter().all(|&byte| byte == 0)
+    }
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encodable;
+    use crate::test_util::prepare_tmp_dir;
+    use std::fs;
+
+    fn keys(range: std::ops::Range<usize>) -> Vec<Vec<u8>> {
+        let mut keys: Vec<Vec<u8>> = range.map(|i| format!("key-{}", i).into_bytes()).collect();
+        keys.sort();
+        keys
+    }
+
+    fn table(difference: usize, keys: &[Vec<u8>]) -> InvertibleBloomLookupTable {
+        let mut table = InvertibleBloomLookupTable::new(difference).unwrap();
+        for key in keys {
+            table.insert(key).unwrap();
+        }
+        table
+    }
+
+    #[test]
+    fn test_peel() {
+        let table = table(100, &keys(0..100));
+        let difference = table.peel().unwrap();
+        assert_eq!(difference.left_only, keys(0..100));
+        assert!(difference.right_only.is_empty());
+
+        let mut table = table;
+        for key in keys(0..100) {
+            table.remove(&key).unwrap();
+        }
+        assert!(table.is_empty());
+        assert_eq!(table.peel().unwrap(), Difference::default());
+    }
+
+    #[test]
+    fn test_subtract() {
+        // Replicas sharing most of 10_000 keys, 40 missing on each side
+        let local = table(100, &keys(0..10_000));
+        let remote = table(100, &keys(40..10_040));
+        let difference = local.subtract(&remote).unwrap().peel().unwrap();
+        assert_eq!(difference.left_only, keys(0..40));
+        assert_eq!(difference.right_only, keys(10_000..10_040));
+
+        let difference = remote.subtract(&local).unwrap().peel().unwrap();
+        assert_eq!(difference.left_only, keys(10_000..10_040));
+        assert!(local.subtract(&local).unwrap().is_empty());
+
+        let other = InvertibleBloomLookupTableBuilder::new(100)
+            .key_size(16)
+            .build()
+            .unwrap();
+        assert_eq!(
+            local.subtract(&other).unwrap_err().to_string(),
+            "Incompatible filters: 232 cells, 4 hashes, key size 32 != \
+             232 cells, 4 hashes, key size 16"
+        );
+    }
+
+    #[test]
+    fn test_peel_failed() {
+        let local = table(10, &keys(0..1000));
+        let remote = table(10, &keys(500..1000));
+        let error = local.subtract(&remote).unwrap().peel().unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<BloomFilterError>(),
+            Some(BloomFilterError::PeelFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_key_size() {
+        // Lengths tell apart keys differing only by trailing zeros
+        let keys = [&b""[..], b"\0", b"a", b"a\0", &[0xff; 32]];
+        let mut table = InvertibleBloomLookupTable::new(10).unwrap();
+        for key in keys {
+            table.insert(key).unwrap();
+        }
+        let mut expected: Vec<Vec<u8>> = keys.iter().map(|key| key.to_vec()).collect();
+        expected.sort();
+        assert_eq!(table.peel().unwrap().left_only, expected);
+
+        assert!(table.insert(&[0; 33]).is_err());
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        let table = table(10, &keys(0..5));
+        let bytes = table.to_bytes();
+        assert_eq!(&bytes[..6], b"BFYS\x04\x0c");
+        assert_eq!(&bytes[6..], table.encode());
+        assert_eq!(
+            InvertibleBloomLookupTable::from_bytes(&bytes).unwrap(),
+            table
+        );
+        assert!(crate::BloomFilter::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_persist_local_file() {
+        prepare_tmp_dir();
+        let test_file = "tmp/invertible_bloom_lookup_table_test_persist_local_file.bin";
+        let table = table(20, &keys(0..10));
+        table.to_file(test_file).unwrap();
+
+        let loaded = InvertibleBloomLookupTable::from_file(test_file).unwrap();
+        assert_eq!(loaded, table);
+        assert_eq!(loaded.peel().unwrap().left_only, keys(0..10));
+
+        fs::remove_file(test_file).unwrap();
+    }
+}
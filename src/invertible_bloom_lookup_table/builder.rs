@@ -0,0 +1,117 @@
+use super::{Cell, InvertibleBloomLookupTable};
+use crate::error::BloomFilterError;
+use crate::format::Persistable;
+use anyhow::Result;
+
+const HASH_COUNT: usize = 4;
+const KEY_SIZE: usize = 32;
+
+// Two cells per key of difference and a few spare: peeling then fails about
+// once in a thousand at any size. Asymptotically 1.3 cells per key would do
+// for 4 hashes, but small tables need the room.
+const CELLS_PER_KEY: usize = 2;
+const SPARE_CELLS: usize = 32;
+
+pub struct InvertibleBloomLookupTableBuilder {
+    difference: usize,
+    hash_count: usize,
+    key_size: usize,
+}
+
+impl InvertibleBloomLookupTableBuilder {
+    // Largest number of keys missing on either side, together, the table is
+    // sized to peel
+    pub fn new(difference: usize) -> Self {
+        Self {
+            difference,
+            hash_count: HASH_COUNT,
+            key_size: KEY_SIZE,
+        }
+    }
+
+    pub fn hash_count(mut self, hash_count: usize) -> Self {
+        self.hash_count = hash_count;
+        self
+    }
+
+    // Longest key in bytes, every cell stores that many plus two
+    pub fn key_size(mut self, key_size: usize) -> Self {
+        self.key_size = key_size;
+        self
+    }
+
+    pub fn build(self) -> Result<InvertibleBloomLookupTable> {
+        if self.difference == 0 {
+            let reason = "difference must be positive".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        if self.hash_count == 0 {
+            let reason = "hash count must be positive".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        if !(1..=u16::MAX as usize).contains(&self.key_size) {
+            let reason = format!("key size must be in [1, {}]", u16::MAX);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+
+        // Equal partitions, one per hash
+        let cells = self
+            .difference
+            .saturating_mul(CELLS_PER_KEY)
+            .saturating_add(SPARE_CELLS);
+        let cell_count = cells.div_ceil(self.hash_count) * self.hash_count;
+        Ok(InvertibleBloomLookupTable {
+            cells: vec![Cell::new(self.key_size); cell_count],
+            hash_count: self.hash_count,
+            key_size: self.key_size,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<InvertibleBloomLookupTable> {
+        InvertibleBloomLookupTable::from_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        let table = InvertibleBloomLookupTableBuilder::new(100).build().unwrap();
+        assert_eq!(table.cell_count(), 232);
+        assert_eq!(table.hash_count(), 4);
+        assert_eq!(table.key_size(), 32);
+        assert_eq!(table.cells[0].key_sum.len(), 34);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_options() {
+        let table = InvertibleBloomLookupTableBuilder::new(10)
+            .hash_count(3)
+            .key_size(8)
+            .build()
+            .unwrap();
+        assert_eq!(table.cell_count(), 54);
+        assert_eq!(table.hash_count(), 3);
+        assert_eq!(table.cells[0].key_sum.len(), 10);
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        assert!(InvertibleBloomLookupTableBuilder::new(0).build().is_err());
+        assert!(InvertibleBloomLookupTableBuilder::new(10)
+            .hash_count(0)
+            .build()
+            .is_err());
+        assert!(InvertibleBloomLookupTableBuilder::new(10)
+            .key_size(0)
+            .build()
+            .is_err());
+        assert!(InvertibleBloomLookupTableBuilder::new(10)
+            .key_size(1 << 16)
+            .build()
+            .is_err());
+    }
+}
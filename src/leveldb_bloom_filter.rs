@@ -0,0 +1,103 @@
+// LevelDB's builtin Bloom filter policy, `NewBloomFilterPolicy`
+// ref: https://github.com/google/leveldb/blob/main/util/bloom.cc
+//
+// Keys are hashed once with LevelDB's hash (seed 0xbc9f1d34) and probed by
+// adding the hash rotated right 17 bits. Bits are numbered from the least
+// significant one of each byte.
+//
+// Block layout: the bit array, then a byte holding the number of probes.
+
+use crate::hash::leveldb_hash;
+
+const SEED: u32 = 0xbc9f_1d34;
+pub(crate) const MAX_HASH_COUNT: usize = 30;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LevelDbBloomFilter {
+    pub data: Vec<u8>,
+    pub hash_count: usize,
+}
+
+impl LevelDbBloomFilter {
+    // Same sizing as `CreateFilter`: at least 64 bits, and
+    // bits_per_key * 0.69 probes rounded down
+    pub fn new<I, K>(bits_per_key: usize, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        let keys: Vec<K> = keys.into_iter().collect();
+        let hash_count = ((bits_per_key as f64 * 0.69) as usize).clamp(1, MAX_HASH_COUNT);
+        let bits = (keys.len() * bits_per_key).max(64);
+        let mut filter = Self {
+            data: vec![0; bits.div_ceil(8)],
+            hash_count,
+        };
+        for key in keys {
+            for bit in filter.hashing(key.as_ref()) {
+                filter.data[bit / 8] |= 1 << (bit % 8);
+            }
+        }
+        filter
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        self.hashing(key.as_ref())
+            .all(|bit| self.data[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    fn hashing(&self, bytes: &[u8]) -> impl Iterator<Item = usize> {
+        let bits = self.data.len() * 8;
+        let mut hash = leveldb_hash(bytes, SEED);
+        let delta = hash.rotate_right(17);
+        (0..self.hash_count).map(move |_| {
+            let bit = hash as usize % bits;
+            hash = hash.wrapping_add(delta);
+            bit
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::keys;
+    use crate::{Decodable, Encodable};
+    use std::collections::HashSet;
+
+    // Generated by tests/data/sst/sst_golden.cc: key-0..key-{n - 1} inserted,
+    // along with the probes in key-n..key-{n + 9999} KeyMayMatch accepts
+    const GOLDEN_100: &[u8] = include_bytes!("../tests/data/sst/leveldb_10_100.bin");
+    const GOLDEN_100_PRESENT: &str = include_str!("../tests/data/sst/leveldb_10_100.txt");
+    const GOLDEN_3: &[u8] = include_bytes!("../tests/data/sst/leveldb_10_3.bin");
+    const GOLDEN_3_PRESENT: &str = include_str!("../tests/data/sst/leveldb_10_3.txt");
+
+    fn check_lookups(filter: &LevelDbBloomFilter, n: usize, present: &str) {
+        let present: HashSet<usize> = present.lines().map(|i| i.parse().unwrap()).collect();
+        assert!(keys(0..n).iter().all(|key| filter.lookup(key)));
+        for i in n..n + 10_000 {
+            let key = format!("key-{}", i);
+            assert_eq!(filter.lookup(&key), present.contains(&i), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_golden() {
+        let filter = LevelDbBloomFilter::new(10, keys(0..100));
+        assert_eq!(filter.hash_count, 6);
+        assert_eq!(filter.encode(), GOLDEN_100);
+        check_lookups(&filter, 100, GOLDEN_100_PRESENT);
+
+        let filter = LevelDbBloomFilter::decode(GOLDEN_3).unwrap();
+        assert_eq!(filter.data.len(), 8);
+        assert_eq!(filter, LevelDbBloomFilter::new(10, keys(0..3)));
+        check_lookups(&filter, 3, GOLDEN_3_PRESENT);
+    }
+
+    #[test]
+    fn test_hash_count() {
+        assert_eq!(LevelDbBloomFilter::new(1, keys(0..10)).hash_count, 1);
+        assert_eq!(LevelDbBloomFilter::new(100, keys(0..10)).hash_count, 30);
+        assert_eq!(LevelDbBloomFilter::new(0, keys(0..10)).data.len(), 8);
+    }
+}
@@ -1,9 +1,55 @@
+mod benchmark;
+mod binary_fuse_filter;
 mod bloom_filter;
 mod compressor;
+mod count_min_sketch;
+mod cuckoo_filter;
 mod decoder;
 mod encoder;
+mod fast_local_bloom_filter;
+mod format;
+mod gcs_filter;
 mod hash;
+mod hyperloglog;
+mod invertible_bloom_lookup_table;
+mod leveldb_bloom_filter;
+mod quotient_filter;
+mod ribbon_filter;
+mod rng;
+mod rotating_bloom_filter;
+mod split_block_bloom_filter;
+mod stable_bloom_filter;
+#[cfg(test)]
+mod test_util;
+mod xor_filter;
 
+pub mod bip37;
+pub mod bits_and_blooms;
 pub mod error;
+pub mod guava;
+pub mod params;
+pub mod redisbloom;
 
-pub use bloom_filter::{BloomFilter, BloomFilterBuilder, CompressMode};
+pub use benchmark::{FalsePositiveReport, FalsePositiveTest};
+pub use binary_fuse_filter::{BinaryFuse16Filter, BinaryFuse8Filter, BinaryFuseFilter};
+pub use bloom_filter::{
+    BloomFilter, BloomFilterBuilder, CompressMode, Estimate, HashScheme, Layout, Similarity,
+};
+pub use count_min_sketch::{CountMinSketch, CountMinSketchBuilder};
+pub use cuckoo_filter::{CuckooFilter, CuckooFilterBuilder};
+pub use decoder::Decodable;
+pub use encoder::Encodable;
+pub use fast_local_bloom_filter::FastLocalBloomFilter;
+pub use format::{FileFormat, FilterKind, Persistable};
+pub use gcs_filter::{GcsFilter, GCS_M, GCS_P};
+pub use hyperloglog::HyperLogLog;
+pub use invertible_bloom_lookup_table::{
+    Difference, InvertibleBloomLookupTable, InvertibleBloomLookupTableBuilder,
+};
+pub use leveldb_bloom_filter::LevelDbBloomFilter;
+pub use quotient_filter::QuotientFilter;
+pub use ribbon_filter::RibbonFilter;
+pub use rotating_bloom_filter::{Clock, ManualClock, RotatingBloomFilter, SystemClock};
+pub use split_block_bloom_filter::SplitBlockBloomFilter;
+pub use stable_bloom_filter::{StableBloomFilter, StableBloomFilterBuilder};
+pub use xor_filter::{Fingerprint, Xor16Filter, Xor8Filter, XorFilter};
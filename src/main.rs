@@ -1,22 +1,15 @@
-use bloom_filter_yss::{BloomFilter, BloomFilterBuilder};
+mod cli;
 
-fn lookup(bloom_filter: &BloomFilter, key: &str) {
-    if bloom_filter.lookup(key) {
-        println!("{} may exist", key);
-    } else {
-        println!("{} good!!!!!", key);
-    }
-}
+use std::env;
+use std::io;
+use std::process::ExitCode;
 
-fn main() {
-    let local_path = "tmp/bloom_filter.bin";
-    let mut bloom_filter = BloomFilterBuilder::new(10_000).build();
-    bloom_filter.insert("test");
-    bloom_filter.insert("test1");
-    bloom_filter.to_file(local_path);
-
-    let bloom_filter = BloomFilterBuilder::load(local_path).unwrap();
-    lookup(&bloom_filter, "test");
-    lookup(&bloom_filter, "test1");
-    lookup(&bloom_filter, "test2");
+fn main() -> ExitCode {
+    match cli::run(env::args().skip(1), &mut io::stdout().lock()) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::from(2)
+        }
+    }
 }
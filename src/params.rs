@@ -0,0 +1,300 @@
+// Bloom filter sizing
+// ref: https://en.wikipedia.org/wiki/Bloom_filter#Probability_of_false_positives
+//
+// n: expected number of items, m: number of bits, k: number of hash
+// functions, p: false positive rate once n items are inserted.
+
+use crate::error::BloomFilterError;
+use anyhow::Result;
+use std::f64::consts::LN_2;
+
+// Range `Params::clamped` sizes for, the bits of MAX_ITEMS at MIN_RATE
+// still fit in usize
+const MAX_ITEMS: usize = usize::MAX / 128;
+const MIN_RATE: f64 = 1e-15;
+const MAX_RATE: f64 = 0.5;
+
+// Relative slack allowed between a given false positive rate and the one
+// implied by n, m and k
+const RATE_TOLERANCE: f64 = 1e-3;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Params {
+    pub items: usize,
+    pub bits: usize,
+    pub hash_count: usize,
+    pub false_positive_rate: f64,
+}
+
+// Known values, the missing ones are solved by `solve`
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Query {
+    pub items: Option<usize>,
+    pub bits: Option<usize>,
+    pub hash_count: Option<usize>,
+    pub false_positive_rate: Option<f64>,
+}
+
+impl Params {
+    // Smallest filter reaching p for n items
+    pub fn optimal(items: usize, false_positive_rate: f64) -> Result<Self> {
+        solve(Query {
+            items: Some(items),
+            false_positive_rate: Some(false_positive_rate),
+            ..Query::default()
+        })
+    }
+
+    // Like `optimal` but never fails, items and the rate are clamped into
+    // [1, MAX_ITEMS] and [MIN_RATE, MAX_RATE]
+    pub fn clamped(items: usize, false_positive_rate: f64) -> Self {
+        let items = items.clamp(1, MAX_ITEMS);
+        let false_positive_rate = if false_positive_rate.is_nan() {
+            MAX_RATE
+        } else {
+            false_positive_rate.clamp(MIN_RATE, MAX_RATE)
+        };
+        Self {
+            items,
+            bits: optimal_bits(items, false_positive_rate).ceil() as usize,
+            hash_count: hash_count_for_rate(false_positive_rate),
+            false_positive_rate,
+        }
+    }
+
+    pub fn bits_per_key(&self) -> f64 {
+        self.bits as f64 / self.items as f64
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.bits.div_ceil(8)
+    }
+}
+
+pub fn solve(query: Query) -> Result<Params> {
+    let Query {
+        items,
+        bits,
+        hash_count,
+        false_positive_rate,
+    } = query;
+    check(items.is_none_or(|n| n > 0), "items must be positive")?;
+    check(bits.is_none_or(|m| m > 0), "bits must be positive")?;
+    check(
+        hash_count.is_none_or(|k| k > 0),
+        "hash count must be positive",
+    )?;
+    check(
+        false_positive_rate.is_none_or(|p| p > 0.0 && p < 1.0),
+        "false positive rate must be between 0 and 1",
+    )?;
+
+    let (n, m, k, p) = match (items, bits, hash_count, false_positive_rate) {
+        (Some(n), Some(m), Some(k), None) => (n, m, k, false_positive_rate_of(n, m, k)),
+        (Some(n), Some(m), None, None) => {
+            let k = optimal_hash_count(n, m);
+            (n, m, k, false_positive_rate_of(n, m, k))
+        }
+        (Some(n), None, None, Some(p)) => {
+            let m = to_usize(optimal_bits(n, p), "bits")?;
+            (n, m, hash_count_for_rate(p), p)
+        }
+        (Some(n), None, Some(k), Some(p)) => {
+            let m = to_usize(-(k as f64) * n as f64 / per_hash_miss(p, k), "bits")?;
+            (n, m, k, p)
+        }
+        (Some(n), Some(m), None, Some(p)) => {
+            let k = optimal_hash_count(n, m);
+            check(
+                false_positive_rate_of(n, m, k) <= p,
+                "bits are not enough to reach the false positive rate",
+            )?;
+            (n, m, k, p)
+        }
+        (None, Some(m), None, Some(p)) => {
+            let n = (-(m as f64) * LN_2 * LN_2 / p.ln()).floor() as usize;
+            (n, m, hash_count_for_rate(p), p)
+        }
+        (None, Some(m), Some(k), Some(p)) => {
+            let n = (-(m as f64) * per_hash_miss(p, k) / k as f64).floor() as usize;
+            (n, m, k, p)
+        }
+        (Some(n), Some(m), Some(k), Some(p)) => {
+            let implied = false_positive_rate_of(n, m, k);
+            if (implied - p).abs() > p * RATE_TOLERANCE {
+                let reason = format!(
+                    "false positive rate {} does not match {} implied by items, bits and hash count",
+                    p, implied
+                );
+                return Err(BloomFilterError::InvalidParams(reason).into());
+            }
+            (n, m, k, p)
+        }
+        _ => {
+            let reason = "need items or bits, plus enough of the others to solve".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+    };
+    check(n > 0, "bits are not enough for a single item")?;
+
+    Ok(Params {
+        items: n,
+        bits: m,
+        hash_count: k,
+        false_positive_rate: p,
+    })
+}
+
+// (1 - e^(-kn/m))^k
+pub fn false_positive_rate_of(items: usize, bits: usize, hash_count: usize) -> f64 {
+    let k = hash_count as f64;
+    (1.0 - (-k * items as f64 / bits as f64).exp()).powf(k)
+}
+
+// -n ln(p) / ln(2)^2
+fn optimal_bits(items: usize, false_positive_rate: f64) -> f64 {
+    -(items as f64) * false_positive_rate.ln() / (LN_2 * LN_2)
+}
+
+// ln(1 - p^(1/k)), the log probability of one hash hitting an unset bit
+fn per_hash_miss(false_positive_rate: f64, hash_count: usize) -> f64 {
+    (-false_positive_rate.powf(1.0 / hash_count as f64)).ln_1p()
+}
+
+fn optimal_hash_count(items: usize, bits: usize) -> usize {
+    ((bits as f64 / items as f64 * LN_2).round() as usize).max(1)
+}
+
+// Rounded up, as BloomFilter always has
+fn hash_count_for_rate(false_positive_rate: f64) -> usize {
+    (-false_positive_rate.log2()).ceil() as usize
+}
+
+fn to_usize(value: f64, name: &str) -> Result<usize> {
+    let value = value.ceil();
+    if value.is_finite() && value < usize::MAX as f64 {
+        Ok(value as usize)
+    } else {
+        let reason = format!("{} overflow", name);
+        Err(BloomFilterError::InvalidParams(reason).into())
+    }
+}
+
+fn check(condition: bool, reason: &str) -> Result<()> {
+    if condition {
+        Ok(())
+    } else {
+        Err(BloomFilterError::InvalidParams(reason.to_string()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(
+        items: Option<usize>,
+        bits: Option<usize>,
+        hash_count: Option<usize>,
+        false_positive_rate: Option<f64>,
+    ) -> Query {
+        Query {
+            items,
+            bits,
+            hash_count,
+            false_positive_rate,
+        }
+    }
+
+    #[test]
+    fn test_optimal() {
+        let params = Params::optimal(2, 0.01).unwrap();
+        assert_eq!(params.bits, 20);
+        assert_eq!(params.hash_count, 7);
+        assert_eq!(params.memory_bytes(), 3);
+        assert_eq!(params.bits_per_key(), 10.0);
+
+        let params = Params::optimal(1_000_000, 0.01).unwrap();
+        assert_eq!(params.bits, 9_585_059);
+        assert_eq!(params.hash_count, 7);
+    }
+
+    #[test]
+    fn test_optimal_large_capacity() {
+        // f32 loses every digit below 2^24 here
+        let params = Params::optimal(10_000_000_000, 0.001).unwrap();
+        assert_eq!(params.bits, 143_775_875_661);
+        assert_eq!(params.hash_count, 10);
+        assert_eq!(params.memory_bytes(), 17_971_984_458);
+
+        assert!(Params::optimal(usize::MAX, 1e-300).is_err());
+    }
+
+    #[test]
+    fn test_clamped() {
+        for (items, false_positive_rate) in [(2, 0.01), (1_000_000, 0.001)] {
+            assert_eq!(
+                Params::clamped(items, false_positive_rate),
+                Params::optimal(items, false_positive_rate).unwrap()
+            );
+        }
+        assert_eq!(Params::clamped(0, 0.01), Params::optimal(1, 0.01).unwrap());
+        assert_eq!(Params::clamped(10, 1.5).hash_count, 1);
+        assert_eq!(Params::clamped(10, f64::NAN).false_positive_rate, 0.5);
+        assert_eq!(Params::clamped(10, 0.0).false_positive_rate, 1e-15);
+        let params = Params::clamped(usize::MAX, 0.0);
+        assert_eq!(params.items, usize::MAX / 128);
+        assert!(params.bits < usize::MAX);
+    }
+
+    #[test]
+    fn test_solve_consistent() {
+        let params = solve(query(Some(1000), Some(9586), Some(7), Some(0.010034))).unwrap();
+        assert_eq!(params.bits, 9586);
+        let err = solve(query(Some(1000), Some(9586), Some(7), Some(0.001))).unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{}", err);
+        assert!(solve(query(Some(1000), Some(9586), Some(7), Some(0.02))).is_err());
+    }
+
+    #[test]
+    fn test_solve_false_positive_rate() {
+        let params = solve(query(Some(1000), Some(9586), Some(7), None)).unwrap();
+        assert!((params.false_positive_rate - 0.010034).abs() < 1e-6);
+
+        let params = solve(query(Some(1000), Some(9586), None, None)).unwrap();
+        assert_eq!(params.hash_count, 7);
+    }
+
+    #[test]
+    fn test_solve_bits() {
+        let params = solve(query(Some(1000), None, Some(4), Some(0.01))).unwrap();
+        assert_eq!(params.bits, 10_523);
+        assert!(false_positive_rate_of(1000, params.bits, 4) <= 0.01);
+    }
+
+    #[test]
+    fn test_solve_items() {
+        let params = solve(query(None, Some(9586), None, Some(0.01))).unwrap();
+        assert_eq!(params.items, 1000);
+        assert_eq!(params.hash_count, 7);
+
+        let params = solve(query(None, Some(10_523), Some(4), Some(0.01))).unwrap();
+        assert_eq!(params.items, 1000);
+    }
+
+    #[test]
+    fn test_solve_checks_bits() {
+        assert!(solve(query(Some(1000), Some(9700), None, Some(0.01))).is_ok());
+        assert!(solve(query(Some(1000), Some(5000), None, Some(0.01))).is_err());
+    }
+
+    #[test]
+    fn test_solve_invalid() {
+        assert!(solve(Query::default()).is_err());
+        assert!(solve(query(Some(1000), None, Some(7), None)).is_err());
+        assert!(solve(query(Some(0), None, None, Some(0.01))).is_err());
+        assert!(solve(query(Some(10), None, None, Some(1.0))).is_err());
+        assert!(solve(query(Some(10), Some(0), None, None)).is_err());
+        assert!(solve(query(None, Some(1), None, Some(0.01))).is_err());
+    }
+}
@@ -0,0 +1,638 @@
+use crate::error::BloomFilterError;
+use crate::format::{FilterKind, Persistable};
+use crate::hash::murmur3_x64_128;
+use anyhow::Result;
+use std::collections::VecDeque;
+
+const MURMUR3_SEED: u32 = 0x5eed_0f9f;
+pub(crate) const MAX_QUOTIENT_BITS: u32 = 32;
+pub(crate) const MAX_REMAINDER_BITS: u32 = 32;
+// Clusters, and so lookups, grow long past this load
+const MAX_LOAD_FACTOR: f64 = 0.75;
+
+const OCCUPIED: u64 = 1;
+const CONTINUATION: u64 = 2;
+const SHIFTED: u64 = 4;
+const METADATA_BITS: u32 = 3;
+
+// ref: Bender et al. (2012), "Don't Thrash: How to Cache Your Hash on Flash"
+//
+// A key's fingerprint is split into a quotient, its canonical slot, and a
+// remainder stored in that slot or, on collision, shifted to the right.
+// Slots hold the remainder above three bits: occupied (a run for this
+// quotient exists), continuation (not the first remainder of its run) and
+// shifted (not in its canonical slot). Runs are kept sorted and may hold
+// the same remainder more than once.
+//
+// Fingerprints are whole, so the filter can be resized, quotient taking a
+// bit from the remainder, and merged without the keys.
+#[derive(Debug, PartialEq, Clone)]
+pub struct QuotientFilter {
+    pub(crate) table: Vec<u8>,
+    pub(crate) quotient_bits: u32,
+    pub(crate) remainder_bits: u32,
+    pub(crate) len: usize,
+}
+
+impl QuotientFilter {
+    // Sized for `capacity` keys below the maximum load, false positives are
+    // then at most 2^-remainder_bits
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Result<Self> {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            let reason = "false positive rate must be in (0, 1)".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        let slots = (capacity.max(1) as f64 / MAX_LOAD_FACTOR).ceil();
+        let quotient_bits = (slots.log2().ceil() as u32).max(1);
+        let remainder_bits = ((1.0 / false_positive_rate).log2().ceil() as u32).max(1);
+        Self::with_bits(quotient_bits, remainder_bits)
+    }
+
+    pub fn with_bits(quotient_bits: u32, remainder_bits: u32) -> Result<Self> {
+        if !(1..=MAX_QUOTIENT_BITS).contains(&quotient_bits) {
+            let reason = format!("quotient bits must be in [1, {}]", MAX_QUOTIENT_BITS);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        if !(1..=MAX_REMAINDER_BITS).contains(&remainder_bits) {
+            let reason = format!("remainder bits must be in [1, {}]", MAX_REMAINDER_BITS);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        let table_bits = (1usize << quotient_bits) * (remainder_bits + METADATA_BITS) as usize;
+        Ok(Self {
+            table: vec![0; table_bits.div_ceil(8)],
+            quotient_bits,
+            remainder_bits,
+            len: 0,
+        })
+    }
+
+    // Fails with `BloomFilterError::Full` once every slot is used, `resize`
+    // makes room
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> Result<()> {
+        let fingerprint = self.hashing(key.as_ref());
+        self.insert_fingerprint(fingerprint)
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        let (quotient, remainder) = self.split(self.hashing(key.as_ref()));
+        matches!(self.find(quotient, remainder), Ok(Some(_)))
+    }
+
+    // Removes one copy of the key. Only remove keys that were inserted, any
+    // other key sharing the fingerprint would be removed instead.
+    pub fn remove<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> bool {
+        let fingerprint = self.hashing(key.as_ref());
+        // Only fails on a corrupt table, which decoding refuses
+        self.remove_fingerprint(fingerprint).unwrap_or(false)
+    }
+
+    fn remove_fingerprint(&mut self, fingerprint: u64) -> Result<bool> {
+        let (quotient, remainder) = self.split(fingerprint);
+        let Some((start, index)) = self.find(quotient, remainder)? else {
+            return Ok(false);
+        };
+
+        let removing_head = index == start;
+        if removing_head && self.slot(self.next(index)) & CONTINUATION == 0 {
+            self.set_flag(quotient, OCCUPIED, false);
+        }
+        // Shift the rest of the cluster left, up to an empty slot or a
+        // remainder in its canonical slot
+        let mut index = index;
+        let mut run_quotient = quotient;
+        loop {
+            let next = self.next(index);
+            let slot = self.slot(next);
+            if slot & SHIFTED == 0 {
+                self.set_entry(index, 0, 0);
+                break;
+            }
+            let mut flags = slot & CONTINUATION;
+            if flags == 0 {
+                run_quotient = self.next_occupied(run_quotient)?;
+            } else if removing_head && index == start {
+                flags = 0;
+            }
+            if index != run_quotient {
+                flags |= SHIFTED;
+            }
+            self.set_entry(index, slot >> METADATA_BITS, flags);
+            index = next;
+        }
+        self.len -= 1;
+        Ok(true)
+    }
+
+    // Doubles the slots, fingerprints keep their bits so each remainder
+    // gives its top bit to the quotient
+    pub fn resize(&mut self) -> Result<()> {
+        if self.remainder_bits == 1 || self.quotient_bits == MAX_QUOTIENT_BITS {
+            let reason = format!(
+                "{} quotient and {} remainder bits, cannot double",
+                self.quotient_bits, self.remainder_bits
+            );
+            return Err(BloomFilterError::Full(reason).into());
+        }
+        let mut resized = Self::with_bits(self.quotient_bits + 1, self.remainder_bits - 1)?;
+        for fingerprint in self.fingerprints()? {
+            resized.insert_fingerprint(fingerprint)?;
+        }
+        *self = resized;
+        Ok(())
+    }
+
+    // Adds every fingerprint of other, which must use as many fingerprint
+    // bits. Grows to the larger quotient, then doubles while past the
+    // maximum load.
+    pub fn merge(&mut self, other: &Self) -> Result<()> {
+        if self.fingerprint_bits() != other.fingerprint_bits() {
+            let reason = format!(
+                "fingerprint bits {} != {}",
+                self.fingerprint_bits(),
+                other.fingerprint_bits()
+            );
+            return Err(BloomFilterError::Incompatible(reason).into());
+        }
+        while self.quotient_bits < other.quotient_bits
+            || (self.len + other.len) as f64 > MAX_LOAD_FACTOR * self.capacity() as f64
+        {
+            if self.resize().is_err() {
+                break;
+            }
+        }
+        if self.len + other.len > self.capacity() {
+            let reason = format!(
+                "{} and {} items exceed {} slots",
+                self.len,
+                other.len,
+                self.capacity()
+            );
+            return Err(BloomFilterError::Full(reason).into());
+        }
+        for fingerprint in other.fingerprints()? {
+            self.insert_fingerprint(fingerprint)?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Number of slots
+    pub fn capacity(&self) -> usize {
+        1 << self.quotient_bits
+    }
+
+    pub fn load_factor(&self) -> f64 {
+        self.len as f64 / self.capacity() as f64
+    }
+
+    pub fn quotient_bits(&self) -> u32 {
+        self.quotient_bits
+    }
+
+    pub fn remainder_bits(&self) -> u32 {
+        self.remainder_bits
+    }
+
+    fn fingerprint_bits(&self) -> u32 {
+        self.quotient_bits + self.remainder_bits
+    }
+
+    fn hashing(&self, bytes: &[u8]) -> u64 {
+        let (hash, _) = murmur3_x64_128(bytes, MURMUR3_SEED);
+        hash & ((1 << self.fingerprint_bits()) - 1)
+    }
+
+    fn split(&self, fingerprint: u64) -> (usize, u64) {
+        let quotient = (fingerprint >> self.remainder_bits) as usize;
+        (quotient, fingerprint & ((1 << self.remainder_bits) - 1))
+    }
+
+    pub(crate) fn insert_fingerprint(&mut self, fingerprint: u64) -> Result<()> {
+        if self.len == self.capacity() {
+            let reason = format!("all {} slots used", self.capacity());
+            return Err(BloomFilterError::Full(reason).into());
+        }
+        let (quotient, remainder) = self.split(fingerprint);
+        let occupied = self.slot(quotient) & OCCUPIED != 0;
+        if is_empty_slot(self.slot(quotient)) {
+            self.set_entry(quotient, remainder, 0);
+            self.set_flag(quotient, OCCUPIED, true);
+            self.len += 1;
+            return Ok(());
+        }
+
+        self.set_flag(quotient, OCCUPIED, true);
+        let start = self.run_start(quotient)?;
+        let mut index = start;
+        if occupied {
+            // Sorted position within the run, or right after it
+            loop {
+                if self.slot(index) >> METADATA_BITS > remainder {
+                    break;
+                }
+                index = self.next(index);
+                if self.slot(index) & CONTINUATION == 0 {
+                    break;
+                }
+            }
+        }
+
+        let mut flags = if index != quotient { SHIFTED } else { 0 };
+        if occupied && index != start {
+            flags |= CONTINUATION;
+        }
+        // A new head turns the previous one into a continuation
+        let mut continues_run = occupied && index == start;
+        let mut entry = (remainder, flags);
+        loop {
+            let slot = self.slot(index);
+            self.set_entry(index, entry.0, entry.1);
+            if is_empty_slot(slot) {
+                break;
+            }
+            let mut flags = slot & CONTINUATION | SHIFTED;
+            if continues_run {
+                flags |= CONTINUATION;
+                continues_run = false;
+            }
+            entry = (slot >> METADATA_BITS, flags);
+            index = self.next(index);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    // Run start and index of the remainder, if present
+    fn find(&self, quotient: usize, remainder: u64) -> Result<Option<(usize, usize)>> {
+        if self.slot(quotient) & OCCUPIED == 0 {
+            return Ok(None);
+        }
+        let start = self.run_start(quotient)?;
+        let mut index = start;
+        loop {
+            let stored = self.slot(index) >> METADATA_BITS;
+            if stored == remainder {
+                return Ok(Some((start, index)));
+            }
+            if stored > remainder {
+                return Ok(None);
+            }
+            index = self.next(index);
+            if self.slot(index) & CONTINUATION == 0 {
+                return Ok(None);
+            }
+        }
+    }
+
+    // Where the run of an occupied quotient starts: back to the start of
+    // the cluster, then skip a run for each occupied quotient before it.
+    // Scans are bounded by the slot count, a corrupt table could loop.
+    fn run_start(&self, quotient: usize) -> Result<usize> {
+        let mut steps = 0..self.capacity();
+        let mut canonical = quotient;
+        while self.slot(canonical) & SHIFTED != 0 {
+            steps.next().ok_or(BloomFilterError::InvalidFormat)?;
+            canonical = self.prev(canonical);
+        }
+        let mut steps = 0..self.capacity();
+        let mut index = canonical;
+        while canonical != quotient {
+            loop {
+                steps.next().ok_or(BloomFilterError::InvalidFormat)?;
+                index = self.next(index);
+                if self.slot(index) & CONTINUATION == 0 {
+                    break;
+                }
+            }
+            canonical = self.next_occupied(canonical)?;
+        }
+        Ok(index)
+    }
+
+    fn next_occupied(&self, quotient: usize) -> Result<usize> {
+        let mut quotient = quotient;
+        for _ in 0..self.capacity() {
+            quotient = self.next(quotient);
+            if self.slot(quotient) & OCCUPIED != 0 {
+                return Ok(quotient);
+            }
+        }
+        Err(BloomFilterError::InvalidFormat.into())
+    }
+
+    fn next(&self, index: usize) -> usize {
+        (index + 1) & (self.capacity() - 1)
+    }
+
+    fn prev(&self, index: usize) -> usize {
+        index.wrapping_sub(1) & (self.capacity() - 1)
+    }
+
+    // Fingerprints in slot order, starting from a cluster start. Checks the
+    // flags along the way, so fails with `BloomFilterError::InvalidFormat`
+    // on a table no sequence of inserts and removes could have made.
+    pub(crate) fn fingerprints(&self) -> Result<Vec<u64>> {
+        let invalid = || BloomFilterError::InvalidFormat;
+        let mut fingerprints = Vec::new();
+        let first = (0..self.capacity())
+            .find(|&i| self.slot(i) & SHIFTED == 0)
+            .ok_or_else(invalid)?;
+        // Occupied quotients whose run is still to come, and the quotient
+        // and last remainder of the run being read
+        let mut pending = VecDeque::new();
+        let mut run: Option<(usize, u64)> = None;
+        for i in 0..self.capacity() {
+            let index = (first + i) & (self.capacity() - 1);
+            let slot = self.slot(index);
+            if slot & OCCUPIED != 0 {
+                pending.push_back(index);
+            }
+            if is_empty_slot(slot) {
+                // Clusters end with every run they owe
+                if !pending.is_empty() {
+                    return Err(invalid().into());
+                }
+                run = None;
+                continue;
+            }
+            let remainder = slot >> METADATA_BITS;
+            let quotient = match (slot & CONTINUATION != 0, slot & SHIFTED != 0) {
+                // Run head in its canonical slot, after the cluster's runs
+                (false, false) if pending.len() == 1 => pending.pop_front().unwrap(),
+                // Run head pushed right by the runs before it
+                (false, true) if run.is_some() => pending
+                    .pop_front()
+                    .filter(|&quotient| quotient != index)
+                    .ok_or_else(invalid)?,
+                // Sorted within the run
+                (true, true) => match run {
+                    Some((quotient, last)) if last <= remainder => quotient,
+                    _ => return Err(invalid().into()),
+                },
+                _ => return Err(invalid().into()),
+            };
+            run = Some((quotient, remainder));
+            fingerprints.push((quotient as u64) << self.remainder_bits | remainder);
+        }
+        if !pending.is_empty() {
+            return Err(invalid().into());
+        }
+        Ok(fingerprints)
+    }
+
+    // Slots span at most 5 bytes past their first, read little endian
+    fn slot(&self, index: usize) -> u64 {
+        let width = (self.remainder_bits + METADATA_BITS) as usize;
+        let bit = index * width;
+        let window = self.table[bit / 8..]
+            .iter()
+            .take(6)
+            .enumerate()
+            .fold(0u64, |window, (i, &byte)| window | (byte as u64) << (8 * i));
+        (window >> (bit % 8)) & ((1 << width) - 1)
+    }
+
+    fn set_slot(&mut self, index: usize, value: u64) {
+        let width = (self.remainder_bits + METADATA_BITS) as usize;
+        let bit = index * width;
+        let mask = ((1u64 << width) - 1) << (bit % 8);
+        let value = value << (bit % 8);
+        for (i, byte) in self.table[bit / 8..].iter_mut().take(6).enumerate() {
+            let byte_mask = (mask >> (8 * i)) as u8;
+            *byte = (*byte & !byte_mask) | ((value >> (8 * i)) as u8 & byte_mask);
+        }
+    }
+
+    // The occupied bit belongs to the slot's quotient, not its remainder,
+    // so entries moving around leave it in place
+    fn set_entry(&mut self, index: usize, remainder: u64, flags: u64) {
+        let occupied = self.slot(index) & OCCUPIED;
+        self.set_slot(
+            index,
+            remainder << METADATA_BITS | flags & !OCCUPIED | occupied,
+        );
+    }
+
+    fn set_flag(&mut self, index: usize, flag: u64, value: bool) {
+        let slot = self.slot(index);
+        self.set_slot(index, if value { slot | flag } else { slot & !flag });
+    }
+}
+
+impl Persistable for QuotientFilter {
+    fn kind() -> FilterKind {
+        FilterKind::Quotient
+    }
+}
+
+// Unshifted slots that are not run heads hold no remainder, and those are
+// the ones without any flag as a run head marks its own slot occupied
+fn is_empty_slot(slot: u64) -> bool {
+    slot & (OCCUPIED | CONTINUATION | SHIFTED) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encodable;
+    use crate::rng::SplitMix64;
+    use crate::test_util::prepare_tmp_dir;
+    use crate::BloomFilter;
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    fn sorted_fingerprints(filter: &QuotientFilter) -> Vec<u64> {
+        let mut fingerprints = filter.fingerprints().unwrap();
+        fingerprints.sort();
+        fingerprints
+    }
+
+    #[test]
+    fn test_new() {
+        let filter = QuotientFilter::new(1000, 0.01).unwrap();
+        assert_eq!(filter.quotient_bits(), 11);
+        assert_eq!(filter.remainder_bits(), 7);
+        assert_eq!(filter.table.len(), 2048 * 10 / 8);
+        assert!(QuotientFilter::new(1000, 0.0).is_err());
+        assert!(QuotientFilter::with_bits(0, 8).is_err());
+        assert!(QuotientFilter::with_bits(8, 33).is_err());
+    }
+
+    #[test]
+    fn test_insert_lookup() {
+        let mut filter = QuotientFilter::new(10_000, 0.001).unwrap();
+        for i in 0..10_000 {
+            filter.insert(&format!("key-{}", i)).unwrap();
+        }
+        assert_eq!(filter.len(), 10_000);
+        assert!((0..10_000).all(|i| filter.lookup(&format!("key-{}", i))));
+
+        let false_positives = (10_000..110_000)
+            .filter(|i| filter.lookup(&format!("key-{}", i)))
+            .count();
+        assert!(false_positives < 100, "{}", false_positives);
+    }
+
+    // Small tables wrap around and pack long clusters, checked against a
+    // multiset of fingerprints
+    #[test]
+    fn test_fingerprints_model() {
+        let mut rng = SplitMix64::new(42);
+        let mut filter = QuotientFilter::with_bits(6, 3).unwrap();
+        let mut model = BTreeMap::new();
+        for _ in 0..20_000 {
+            let fingerprint = rng.next_u64() & 0x1ff;
+            let (quotient, remainder) = filter.split(fingerprint);
+            let present = model.contains_key(&fingerprint);
+            assert_eq!(filter.find(quotient, remainder).unwrap().is_some(), present);
+            if rng.next_u64() % 5 < 3 && filter.len() < filter.capacity() {
+                filter.insert_fingerprint(fingerprint).unwrap();
+                *model.entry(fingerprint).or_insert(0) += 1;
+            } else {
+                assert_eq!(filter.remove_fingerprint(fingerprint).unwrap(), present);
+                if let Some(count) = model.get_mut(&fingerprint) {
+                    *count -= 1;
+                    if *count == 0 {
+                        model.remove(&fingerprint);
+                    }
+                }
+            }
+            let expected: Vec<u64> = model
+                .iter()
+                .flat_map(|(&fingerprint, &count)| std::iter::repeat_n(fingerprint, count))
+                .collect();
+            assert_eq!(sorted_fingerprints(&filter), expected);
+            assert_eq!(filter.len(), expected.len());
+        }
+    }
+
+    #[test]
+    fn test_bounded_scans() {
+        // Every slot occupied and shifted, so no cluster start
+        let mut filter = QuotientFilter {
+            table: vec![0x55; 2],
+            quotient_bits: 2,
+            remainder_bits: 1,
+            len: 4,
+        };
+        assert!(filter.run_start(0).is_err());
+        assert!(!filter.lookup("key"));
+        assert!(!filter.remove("key"));
+        assert!(filter.fingerprints().is_err());
+
+        filter.table = vec![0; 2];
+        assert!(filter.next_occupied(0).is_err());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut filter = QuotientFilter::new(100, 0.01).unwrap();
+        filter.insert("twice").unwrap();
+        filter.insert("twice").unwrap();
+        filter.insert("once").unwrap();
+
+        assert!(filter.remove("twice"));
+        assert!(filter.lookup("twice"));
+        assert!(filter.remove("twice"));
+        assert!(!filter.lookup("twice"));
+        assert!(!filter.remove("twice"));
+        assert!(filter.lookup("once"));
+        assert!(filter.remove("once"));
+        assert!(filter.is_empty());
+        assert!(filter.table.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_full() {
+        let mut filter = QuotientFilter::with_bits(3, 8).unwrap();
+        for i in 0..8 {
+            filter.insert(&format!("key-{}", i)).unwrap();
+        }
+        assert_eq!(
+            filter.insert("key-8").unwrap_err().to_string(),
+            "Filter is full: all 8 slots used"
+        );
+        assert!((0..8).all(|i| filter.lookup(&format!("key-{}", i))));
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut filter = QuotientFilter::with_bits(8, 12).unwrap();
+        for i in 0..200 {
+            filter.insert(&format!("key-{}", i)).unwrap();
+        }
+        let fingerprints = sorted_fingerprints(&filter);
+        filter.resize().unwrap();
+        assert_eq!(filter.quotient_bits(), 9);
+        assert_eq!(filter.remainder_bits(), 11);
+        assert_eq!(filter.len(), 200);
+        assert_eq!(sorted_fingerprints(&filter), fingerprints);
+        assert!((0..200).all(|i| filter.lookup(&format!("key-{}", i))));
+
+        let mut filter = QuotientFilter::with_bits(4, 1).unwrap();
+        assert_eq!(
+            filter.resize().unwrap_err().to_string(),
+            "Filter is full: 4 quotient and 1 remainder bits, cannot double"
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut filter = QuotientFilter::with_bits(6, 10).unwrap();
+        let mut other = QuotientFilter::with_bits(7, 9).unwrap();
+        for i in 0..40 {
+            filter.insert(&format!("key-{}", i)).unwrap();
+            other.insert(&format!("other-{}", i)).unwrap();
+        }
+        filter.merge(&other).unwrap();
+        assert_eq!(filter.quotient_bits(), 7);
+        assert_eq!(filter.len(), 80);
+        assert!((0..40).all(|i| filter.lookup(&format!("key-{}", i))));
+        assert!((0..40).all(|i| filter.lookup(&format!("other-{}", i))));
+
+        // Past the maximum load of 128 slots
+        filter.merge(&other.clone()).unwrap();
+        assert_eq!(filter.quotient_bits(), 8);
+        assert_eq!(filter.len(), 120);
+
+        let other = QuotientFilter::with_bits(7, 8).unwrap();
+        assert_eq!(
+            filter.merge(&other).unwrap_err().to_string(),
+            "Incompatible filters: fingerprint bits 16 != 15"
+        );
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        let mut filter = QuotientFilter::new(100, 0.01).unwrap();
+        filter.insert("key").unwrap();
+        let bytes = filter.to_bytes();
+        assert_eq!(&bytes[..6], b"BFYS\x04\x02");
+        assert_eq!(&bytes[6..], filter.encode());
+        assert_eq!(QuotientFilter::from_bytes(&bytes).unwrap(), filter);
+        assert!(BloomFilter::from_bytes(&bytes).is_err());
+        assert!(QuotientFilter::from_bytes(&BloomFilter::new(10).to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_persist_local_file() {
+        prepare_tmp_dir();
+        let test_file = "tmp/quotient_filter_test_persist_local_file.bin";
+        let mut filter = QuotientFilter::new(10, 0.01).unwrap();
+        filter.insert("abound").unwrap();
+        filter.to_file(test_file).unwrap();
+
+        let loaded = QuotientFilter::from_file(test_file).unwrap();
+        assert_eq!(loaded, filter);
+        assert!(loaded.lookup("abound"));
+
+        fs::remove_file(test_file).unwrap();
+    }
+}
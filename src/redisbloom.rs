@@ -0,0 +1,555 @@
+// RedisBloom BF.SCANDUMP / BF.LOADCHUNK chunks
+// ref: https://github.com/RedisBloom/RedisBloom/blob/master/src/sb.c
+// ref: https://github.com/RedisBloom/RedisBloom/blob/master/deps/bloom/bloom.c
+//
+// A dump is a list of (iterator, data) chunks. The first one, iterator 1,
+// holds the packed little endian chain header:
+//
+//   u64 items, u32 filter count, u32 options, u32 expansion
+//   per filter: u64 bytes, u64 bits, u64 items, f64 error rate,
+//               f64 bits per entry, u32 hashes, u64 capacity, u8 n2
+//
+// The next ones hold the bit array in order, each tagged with iterator
+// 1 + offset + length. BF.SCANDUMP ends with (0, ""), which is skipped.
+//
+// Only chains made of a single filter hashed with MurmurHash64A (the
+// FORCE64 option every RedisBloom 2.x filter sets) map onto a `BloomFilter`,
+// other dumps are rejected rather than loaded with the wrong hashing.
+
+use crate::bloom_filter::{BitArray, BloomFilter, CompressMode, HashScheme, Layout};
+use crate::error::BloomFilterError;
+use crate::hash::murmur64a;
+use anyhow::Result;
+use std::f64::consts::LN_2;
+
+// Chunk size used by BF.SCANDUMP
+pub const MAX_CHUNK_SIZE: usize = 10 * 1024 * 1024;
+
+const HEADER_ITER: i64 = 1;
+const HEADER_SIZE: usize = 8 + 4 + 4 + 4;
+const LINK_SIZE: usize = 8 + 8 + 8 + 8 + 8 + 4 + 8 + 1;
+
+const OPT_NOROUND: u32 = 1;
+const OPT_FORCE64: u32 = 4;
+const OPT_NO_SCALING: u32 = 8;
+const DEFAULT_EXPANSION: u32 = 2;
+
+const HASH_SEED: u64 = 0xc6a4_a793_5bd1_e995;
+// ln(2)^2 as written in bloom.c, kept to size filters exactly like RedisBloom
+const LN2_SQUARED: f64 = 0.480453013918201;
+
+// A single RedisBloom filter along with the header fields BF.INFO reports
+#[derive(Debug, PartialEq, Clone)]
+pub struct RedisBloomFilter {
+    pub bloom_filter: BloomFilter,
+    pub capacity: u64,
+    pub error_rate: f64,
+    // Inserts that changed the filter
+    pub items: u64,
+    // Growth factor of a scaling filter, None for NONSCALING
+    pub expansion: Option<u32>,
+}
+
+impl RedisBloomFilter {
+    // Sized like `BF.RESERVE key error_rate capacity NONSCALING`
+    pub fn new(capacity: u64, error_rate: f64) -> Result<Self> {
+        if capacity == 0 {
+            let reason = "capacity must be positive".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        if !(error_rate > 0.0 && error_rate < 1.0) {
+            let reason = "error rate must be in (0, 1)".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+
+        let bits_per_entry = bits_per_entry(error_rate);
+        let bits = (capacity as f64 * bits_per_entry) as usize;
+        let bytes = bits.div_ceil(64) * 8;
+        let bloom_filter = BloomFilter {
+            bit_array: BitArray::new(bytes * 8),
+            hash_count: (LN_2 * bits_per_entry).ceil() as usize,
+            compress_mode: CompressMode::None,
+            hash_scheme: HashScheme::RedisBloom,
+            layout: Layout::Standard,
+        };
+
+        Ok(Self {
+            bloom_filter,
+            capacity,
+            error_rate,
+            items: 0,
+            expansion: None,
+        })
+    }
+
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> bool {
+        let added = self.bloom_filter.insert(key);
+        if added {
+            self.items += 1;
+        }
+        added
+    }
+
+    // Chunks as returned by successive BF.SCANDUMP calls
+    pub fn from_chunks<I, D>(chunks: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (i64, D)>,
+        D: AsRef<[u8]>,
+    {
+        let mut chunks = chunks.into_iter();
+        let mut filter = match chunks.next() {
+            Some((HEADER_ITER, header)) => Self::from_header(header.as_ref())?,
+            Some((iter, _)) => {
+                return Err(invalid_dump(format!(
+                    "expected the header chunk first, got iterator {}",
+                    iter
+                )))
+            }
+            None => return Err(invalid_dump("no chunks")),
+        };
+
+        let byte_array = &mut filter.bloom_filter.bit_array.byte_array;
+        let mut loaded = 0;
+        for (iter, data) in chunks {
+            let data = data.as_ref();
+            if iter == 0 && data.is_empty() {
+                break;
+            }
+            // The iterator points one past the end of the chunk
+            let offset = iter
+                .checked_sub(HEADER_ITER + data.len() as i64)
+                .and_then(|offset| usize::try_from(offset).ok());
+            if offset != Some(loaded) {
+                return Err(invalid_dump(format!(
+                    "chunk {} of {} bytes does not follow byte {}",
+                    iter,
+                    data.len(),
+                    loaded
+                )));
+            }
+            if loaded + data.len() > byte_array.len() {
+                return Err(invalid_dump(format!(
+                    "chunk {} ends past the {} byte filter",
+                    iter,
+                    byte_array.len()
+                )));
+            }
+            byte_array[loaded..loaded + data.len()].copy_from_slice(data);
+            loaded += data.len();
+        }
+        if loaded != byte_array.len() {
+            return Err(invalid_dump(format!(
+                "{} of {} filter bytes in chunks",
+                loaded,
+                byte_array.len()
+            )));
+        }
+
+        Ok(filter)
+    }
+
+    // Chunks for successive BF.LOADCHUNK calls, header first
+    pub fn to_chunks(&self, max_chunk_size: usize) -> Result<Vec<(i64, Vec<u8>)>> {
+        if max_chunk_size == 0 {
+            let reason = "chunk size must be positive".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        let bloom_filter = &self.bloom_filter;
+        if bloom_filter.hash_scheme != HashScheme::RedisBloom {
+            return Err(unsupported(format!(
+                "{:?} hashing, RedisBloom filters use RedisBloom hashing",
+                bloom_filter.hash_scheme
+            )));
+        }
+        let size = bloom_filter.bit_array.size;
+        if !size.is_multiple_of(64) || size / 8 != bloom_filter.bit_array.byte_array.len() {
+            return Err(unsupported(format!(
+                "{} bits, RedisBloom filters hold whole 64 bit words",
+                size
+            )));
+        }
+        if bloom_filter.hash_count > u32::MAX as usize {
+            return Err(unsupported(format!(
+                "{} hash functions",
+                bloom_filter.hash_count
+            )));
+        }
+
+        let mut chunks = vec![(HEADER_ITER, self.header())];
+        let mut iter = HEADER_ITER;
+        for data in bloom_filter.bit_array.byte_array.chunks(max_chunk_size) {
+            iter += data.len() as i64;
+            chunks.push((iter, data.to_vec()));
+        }
+        Ok(chunks)
+    }
+
+    fn from_header(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes };
+        let items = reader.u64()?;
+        let filter_count = reader.u32()?;
+        let options = reader.u32()?;
+        let expansion = reader.u32()?;
+
+        match filter_count {
+            0 => return Err(invalid_dump("header without filters")),
+            1 => {}
+            _ => {
+                return Err(unsupported(format!(
+                    "scaled chain of {} filters, only single filter chains can be loaded",
+                    filter_count
+                )))
+            }
+        }
+        if bytes.len() != HEADER_SIZE + LINK_SIZE {
+            return Err(invalid_dump(format!(
+                "header of {} bytes, expected {}",
+                bytes.len(),
+                HEADER_SIZE + LINK_SIZE
+            )));
+        }
+        if options & OPT_FORCE64 == 0 {
+            return Err(unsupported(format!(
+                "32-bit hashing (options {:#x}), only 64-bit hashing is supported",
+                options
+            )));
+        }
+
+        let byte_count = reader.u64()?;
+        let bits = reader.u64()?;
+        // Items of the chain and of its only filter are the same
+        let _ = reader.u64()?;
+        let error_rate = reader.f64()?;
+        let _bits_per_entry = reader.f64()?;
+        let hash_count = reader.u32()?;
+        let capacity = reader.u64()?;
+        let n2 = reader.u8()?;
+
+        if byte_count.checked_mul(8) != Some(bits) {
+            return Err(unsupported(format!(
+                "{} bits stored in {} bytes",
+                bits, byte_count
+            )));
+        }
+        // Rounded filters probe modulo 2^n2 instead of the bit count
+        if n2 != 0 && 1u64.checked_shl(n2 as u32) != Some(bits) {
+            return Err(unsupported(format!(
+                "probes modulo 2^{} over {} bits",
+                n2, bits
+            )));
+        }
+        if bits == 0 || hash_count == 0 {
+            return Err(invalid_dump(format!(
+                "{} bits and {} hash functions",
+                bits, hash_count
+            )));
+        }
+        let bits = usize::try_from(bits).map_err(|_| unsupported(format!("{} bits", bits)))?;
+
+        let bloom_filter = BloomFilter {
+            bit_array: BitArray::new(bits),
+            hash_count: hash_count as usize,
+            compress_mode: CompressMode::None,
+            hash_scheme: HashScheme::RedisBloom,
+            layout: Layout::Standard,
+        };
+        Ok(Self {
+            bloom_filter,
+            capacity,
+            error_rate,
+            items,
+            expansion: (options & OPT_NO_SCALING == 0).then_some(expansion),
+        })
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let bit_array = &self.bloom_filter.bit_array;
+        let mut options = OPT_NOROUND | OPT_FORCE64;
+        if self.expansion.is_none() {
+            options |= OPT_NO_SCALING;
+        }
+
+        let mut header = Vec::with_capacity(HEADER_SIZE + LINK_SIZE);
+        header.extend_from_slice(&self.items.to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes());
+        header.extend_from_slice(&options.to_le_bytes());
+        header.extend_from_slice(&self.expansion.unwrap_or(DEFAULT_EXPANSION).to_le_bytes());
+
+        header.extend_from_slice(&(bit_array.byte_array.len() as u64).to_le_bytes());
+        header.extend_from_slice(&(bit_array.size as u64).to_le_bytes());
+        header.extend_from_slice(&self.items.to_le_bytes());
+        header.extend_from_slice(&self.error_rate.to_le_bytes());
+        header.extend_from_slice(&bits_per_entry(self.error_rate).to_le_bytes());
+        header.extend_from_slice(&(self.bloom_filter.hash_count as u32).to_le_bytes());
+        header.extend_from_slice(&self.capacity.to_le_bytes());
+        // n2 = 0, probes are taken modulo the bit count
+        header.push(0);
+        header
+    }
+}
+
+// Bit indexes of `bytes` in a filter of `bit_count` bits. RedisBloom numbers
+// bits from the least significant one of each byte, the crate's `BitArray`
+// from the most significant one, which flipping the low three bits converts.
+pub(crate) fn hashing(bytes: &[u8], hash_count: usize, bit_count: usize) -> Vec<usize> {
+    let bits = bit_count as u64;
+    let a = murmur64a(bytes, HASH_SEED);
+    let b = murmur64a(bytes, a);
+
+    (0..hash_count as u64)
+        .map(|i| (a.wrapping_add(i.wrapping_mul(b)) % bits) as usize ^ 7)
+        .collect()
+}
+
+fn bits_per_entry(error_rate: f64) -> f64 {
+    (error_rate.ln() / LN2_SQUARED).abs()
+}
+
+fn invalid_dump(reason: impl Into<String>) -> anyhow::Error {
+    BloomFilterError::InvalidDump(reason.into()).into()
+}
+
+fn unsupported(reason: impl Into<String>) -> anyhow::Error {
+    BloomFilterError::Unsupported(reason.into()).into()
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.bytes.len() < N {
+            return Err(invalid_dump("truncated header"));
+        }
+        let (head, tail) = self.bytes.split_at(N);
+        self.bytes = tail;
+        Ok(head.try_into()?)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.take()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BF.RESERVE f 0.01 100 NONSCALING, BF.MADD f foo bar
+    fn golden_header() -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend(2u64.to_le_bytes()); // Items
+        header.extend(1u32.to_le_bytes()); // Filters
+        header.extend(13u32.to_le_bytes()); // NOROUND | FORCE64 | NO_SCALING
+        header.extend(2u32.to_le_bytes()); // Expansion
+        header.extend(120u64.to_le_bytes()); // Bytes
+        header.extend(960u64.to_le_bytes()); // Bits
+        header.extend(2u64.to_le_bytes()); // Items
+        header.extend([123, 20, 174, 71, 225, 122, 132, 63]); // Error rate
+        header.extend([136, 22, 138, 197, 140, 43, 35, 64]); // Bits per entry
+        header.extend(7u32.to_le_bytes()); // Hashes
+        header.extend(100u64.to_le_bytes()); // Capacity
+        header.push(0); // n2
+        header
+    }
+
+    fn golden_data() -> Vec<u8> {
+        let mut data = vec![0; 120];
+        for (i, byte) in [
+            (4, 8),
+            (26, 32),
+            (32, 8),
+            (33, 32),
+            (34, 8),
+            (42, 8),
+            (51, 2),
+            (60, 8),
+            (62, 8),
+            (88, 8),
+            (90, 10),
+            (98, 128),
+            (105, 128),
+        ] {
+            data[i] = byte;
+        }
+        data
+    }
+
+    #[test]
+    fn test_new() {
+        let filter = RedisBloomFilter::new(100, 0.01).unwrap();
+        assert_eq!(filter.bloom_filter.bit_array.size, 960);
+        assert_eq!(filter.bloom_filter.hash_count, 7);
+        assert_eq!(filter.bloom_filter.hash_scheme, HashScheme::RedisBloom);
+
+        let filter = RedisBloomFilter::new(1000, 0.001).unwrap();
+        assert_eq!(filter.bloom_filter.bit_array.size, 14400);
+        assert_eq!(filter.bloom_filter.hash_count, 10);
+
+        assert!(RedisBloomFilter::new(0, 0.01).is_err());
+        assert!(RedisBloomFilter::new(100, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_export_golden() {
+        let mut filter = RedisBloomFilter::new(100, 0.01).unwrap();
+        assert!(filter.insert("foo"));
+        assert!(filter.insert("bar"));
+        assert!(!filter.insert("foo"));
+
+        let chunks = filter.to_chunks(MAX_CHUNK_SIZE).unwrap();
+        assert_eq!(chunks, [(1, golden_header()), (121, golden_data())]);
+    }
+
+    #[test]
+    fn test_import_golden() {
+        let chunks = [
+            (1, golden_header()),
+            (61, golden_data()[..60].to_vec()),
+            (121, golden_data()[60..].to_vec()),
+            (0, Vec::new()),
+        ];
+        let filter = RedisBloomFilter::from_chunks(chunks).unwrap();
+        assert_eq!(filter.capacity, 100);
+        assert_eq!(filter.error_rate, 0.01);
+        assert_eq!(filter.items, 2);
+        assert_eq!(filter.expansion, None);
+        assert!(filter.bloom_filter.lookup("foo"));
+        assert!(filter.bloom_filter.lookup("bar"));
+        assert!(!filter.bloom_filter.lookup("baz"));
+        assert_eq!(filter, {
+            let mut expected = RedisBloomFilter::new(100, 0.01).unwrap();
+            expected.insert("foo");
+            expected.insert("bar");
+            expected
+        });
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut filter = RedisBloomFilter::new(10_000, 0.001).unwrap();
+        filter.expansion = Some(4);
+        for i in 0..5_000 {
+            filter.insert(&i.to_string());
+        }
+
+        let chunks = filter.to_chunks(1000).unwrap();
+        assert_eq!(chunks.len(), 1 + 18);
+        assert_eq!(chunks.last().unwrap().0, 1 + 17_976);
+        let loaded = RedisBloomFilter::from_chunks(chunks).unwrap();
+        assert_eq!(loaded, filter);
+
+        // Survives the crate file format
+        let bloom_filter = BloomFilter::from_bytes(&filter.bloom_filter.to_bytes()).unwrap();
+        assert_eq!(bloom_filter, filter.bloom_filter);
+    }
+
+    #[test]
+    fn test_import_mismatch() {
+        let data = golden_data();
+        let load = |header: Vec<u8>| {
+            RedisBloomFilter::from_chunks([(1, header), (121, data.clone())])
+                .unwrap_err()
+                .to_string()
+        };
+
+        let mut header = golden_header();
+        header[8..12].copy_from_slice(&2u32.to_le_bytes());
+        assert_eq!(
+            load(header),
+            "Unsupported filter: scaled chain of 2 filters, only single filter chains can be loaded"
+        );
+
+        let mut header = golden_header();
+        header[12..16].copy_from_slice(&9u32.to_le_bytes());
+        assert_eq!(
+            load(header),
+            "Unsupported filter: 32-bit hashing (options 0x9), only 64-bit hashing is supported"
+        );
+
+        let mut header = golden_header();
+        header[28..36].copy_from_slice(&950u64.to_le_bytes());
+        assert_eq!(
+            load(header),
+            "Unsupported filter: 950 bits stored in 120 bytes"
+        );
+
+        let mut header = golden_header();
+        *header.last_mut().unwrap() = 9;
+        assert_eq!(
+            load(header),
+            "Unsupported filter: probes modulo 2^9 over 960 bits"
+        );
+
+        let mut header = golden_header();
+        header.pop();
+        assert_eq!(
+            load(header),
+            "Invalid dump: header of 72 bytes, expected 73"
+        );
+    }
+
+    #[test]
+    fn test_import_invalid_chunks() {
+        let header = golden_header();
+        let data = golden_data();
+        let load = |chunks: Vec<(i64, &[u8])>| {
+            RedisBloomFilter::from_chunks(chunks)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(load(vec![]), "Invalid dump: no chunks");
+        assert_eq!(
+            load(vec![(121, &data)]),
+            "Invalid dump: expected the header chunk first, got iterator 121"
+        );
+        assert_eq!(
+            load(vec![(1, &header)]),
+            "Invalid dump: 0 of 120 filter bytes in chunks"
+        );
+        assert_eq!(
+            load(vec![(1, &header), (121, &data[60..])]),
+            "Invalid dump: chunk 121 of 60 bytes does not follow byte 0"
+        );
+        assert_eq!(
+            load(vec![
+                (1, &header),
+                (131, &[data.as_slice(), &[0; 10]].concat())
+            ]),
+            "Invalid dump: chunk 131 ends past the 120 byte filter"
+        );
+    }
+
+    #[test]
+    fn test_export_mismatch() {
+        let mut filter = RedisBloomFilter::new(100, 0.01).unwrap();
+        filter.bloom_filter.hash_scheme = HashScheme::Murmur3Fnv;
+        assert_eq!(
+            filter.to_chunks(MAX_CHUNK_SIZE).unwrap_err().to_string(),
+            "Unsupported filter: Murmur3Fnv hashing, RedisBloom filters use RedisBloom hashing"
+        );
+
+        let mut filter = RedisBloomFilter::new(100, 0.01).unwrap();
+        filter.bloom_filter = BloomFilter::new(100);
+        filter.bloom_filter.hash_scheme = HashScheme::RedisBloom;
+        assert_eq!(
+            filter.to_chunks(MAX_CHUNK_SIZE).unwrap_err().to_string(),
+            "Unsupported filter: 959 bits, RedisBloom filters hold whole 64 bit words"
+        );
+        assert!(filter.to_chunks(0).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use crate::error::BloomFilterError;
+use crate::format::{FilterKind, Persistable};
+use crate::hash::fmix64;
+use crate::rng::SplitMix64;
+use crate::xor_filter;
+use anyhow::Result;
+
+const SEED: u64 = 0x3c6e_f372_fe94_f82b;
+const MAX_ATTEMPTS: usize = 100;
+pub(crate) const COEFF_BITS: usize = 128;
+pub(crate) const MAX_RESULT_BITS: u32 = 32;
+
+// Standard Ribbon, ref: Dillinger & Walzer (2021), "Ribbon filter:
+// practically smaller than Bloom and Xor"
+// ref: https://github.com/facebook/rocksdb/blob/main/util/ribbon_alg.h
+//
+// Each key gives a 128 bit coefficient row starting at some slot and an
+// r bit result, the filter stores a solution S to row · S = result for
+// all keys, found by Gaussian elimination kept banded as keys come in.
+// A key absent from the set matches with probability 2^-r.
+//
+// The solution is interleaved: per block of 128 slots, one u128 per result
+// bit, so a lookup reads r words from two adjacent blocks.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RibbonFilter {
+    pub(crate) seed: u64,
+    pub(crate) result_bits: u32,
+    pub(crate) num_slots: usize,
+    pub(crate) solution: Vec<u128>,
+}
+
+impl RibbonFilter {
+    // Duplicate keys are allowed, results of 7 bits give about 1% false
+    // positives. Fails with `BloomFilterError::ConstructionFailed` when no
+    // seed bands every key.
+    //
+    // Construction holds about 30 bytes per key: its 8 byte hash, then a
+    // 16 byte row and 4 byte result per slot while banding.
+    pub fn new<I, K>(result_bits: u32, keys: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        if !(1..=MAX_RESULT_BITS).contains(&result_bits) {
+            let reason = format!("result bits must be in [1, {}]", MAX_RESULT_BITS);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        let hashes = xor_filter::key_hashes(keys);
+        let mut filter = Self {
+            seed: 0,
+            result_bits,
+            num_slots: 0,
+            solution: Vec::new(),
+        };
+
+        let mut seeds = SplitMix64::new(SEED);
+        for attempt in 0..MAX_ATTEMPTS {
+            filter.seed = seeds.next_u64();
+            filter.num_slots = num_slots(hashes.len(), attempt);
+            if let Some((coefficients, results)) = filter.band(&hashes) {
+                filter.solution = filter.back_substitute(&coefficients, &results);
+                return Ok(filter);
+            }
+        }
+        let reason = format!(
+            "{} keys left unsolved after {} seeds",
+            hashes.len(),
+            MAX_ATTEMPTS
+        );
+        Err(BloomFilterError::ConstructionFailed(reason).into())
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        let (start, coefficients, result) = self.hashing(xor_filter::key_hash(key.as_ref()));
+        let block = start / COEFF_BITS;
+        let offset = start % COEFF_BITS;
+        let result_bits = self.result_bits as usize;
+        (0..result_bits).all(|bit| {
+            let low = self.solution[block * result_bits + bit] >> offset;
+            let window = match offset {
+                0 => low,
+                _ => low | self.solution[(block + 1) * result_bits + bit] << (COEFF_BITS - offset),
+            };
+            (window & coefficients).count_ones() % 2 == (result >> bit) & 1
+        })
+    }
+
+    // Solution bits per key for `len` keys
+    pub fn bits_per_key(&self, len: usize) -> f64 {
+        (self.num_slots * self.result_bits as usize) as f64 / len as f64
+    }
+
+    // Start slot, coefficients with the lowest bit set, and result
+    fn hashing(&self, key_hash: u64) -> (usize, u128, u32) {
+        let hash = fmix64(key_hash.wrapping_add(self.seed));
+        let num_starts = (self.num_slots - COEFF_BITS + 1) as u128;
+        let start = ((hash as u128 * num_starts) >> 64) as usize;
+        let coefficients = (fmix64(hash ^ 0x9e37_79b9_7f4a_7c15) as u128) << 64
+            | fmix64(hash ^ 0xc2b2_ae3d_27d4_eb4f) as u128
+            | 1;
+        let result = fmix64(hash ^ 0x1656_67b1_9e37_79f9) as u32;
+        (start, coefficients, result & mask(self.result_bits))
+    }
+
+    // Rows kept in the slot of their lowest coefficient, each new row XORs
+    // out the rows already there until it lands in an empty one. None when
+    // a row reduces to nothing but a result, the system then being
+    // inconsistent.
+    fn band(&self, hashes: &[u64]) -> Option<(Vec<u128>, Vec<u32>)> {
+        let mut rows = vec![0u128; self.num_slots];
+        let mut results = vec![0u32; self.num_slots];
+        for &hash in hashes {
+            let (mut start, mut coefficients, mut result) = self.hashing(hash);
+            loop {
+                if rows[start] == 0 {
+                    rows[start] = coefficients;
+                    results[start] = result;
+                    break;
+                }
+                coefficients ^= rows[start];
+                result ^= results[start];
+                if coefficients == 0 {
+                    if result != 0 {
+                        return None;
+                    }
+                    break;
+                }
+                let shift = coefficients.trailing_zeros();
+                start += shift as usize;
+                coefficients >>= shift;
+            }
+        }
+        Some((rows, results))
+    }
+
+    // Solves slots from the last one, each result bit keeping the solution
+    // of the 128 slots after the current one
+    fn back_substitute(&self, rows: &[u128], results: &[u32]) -> Vec<u128> {
+        let result_bits = self.result_bits as usize;
+        let mut solution = vec![0u128; self.num_slots / COEFF_BITS * result_bits];
+        let mut state = vec![0u128; result_bits];
+        for slot in (0..self.num_slots).rev() {
+            let block = slot / COEFF_BITS;
+            for (bit, state) in state.iter_mut().enumerate() {
+                let parity = (*state << 1 & rows[slot]).count_ones() as u128 % 2;
+                *state = *state << 1 | (parity ^ (results[slot] >> bit) as u128 & 1);
+                solution[block * result_bits + bit] |= (*state & 1) << (slot % COEFF_BITS);
+            }
+        }
+        solution
+    }
+}
+
+impl Persistable for RibbonFilter {
+    fn kind() -> FilterKind {
+        FilterKind::Ribbon
+    }
+}
+
+fn mask(bits: u32) -> u32 {
+    ((1u64 << bits) - 1) as u32
+}
+
+// Banding fails more often as keys grow, measured with 128 bit rows: 3%
+// extra slots succeed on nearly every seed for 10^5 keys, 5% for 10^6 and
+// 6.5% for 10^7. Each failed attempt adds another half percent.
+fn num_slots(len: usize, attempt: usize) -> usize {
+    let overhead = (0.015 * (len.max(1) as f64).log10() - 0.04).max(0.01) + 0.005 * attempt as f64;
+    ((len as f64 * (1.0 + overhead)) as usize + COEFF_BITS).next_multiple_of(COEFF_BITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encodable;
+    use crate::test_util::keys;
+    use crate::Xor8Filter;
+
+    #[test]
+    fn test_lookup() {
+        let filter = RibbonFilter::new(7, keys(0..100_000)).unwrap();
+        assert!(keys(0..100_000).iter().all(|key| filter.lookup(key)));
+
+        // About 1 / 128
+        let false_positives = keys(100_000..200_000)
+            .iter()
+            .filter(|key| filter.lookup(key))
+            .count();
+        assert!((600..950).contains(&false_positives), "{}", false_positives);
+
+        // Close to the 7 bits per key bound, where an 8 bit xor filter
+        // takes about 9.8
+        assert!(filter.bits_per_key(100_000) < 7.3);
+        let xor_filter = Xor8Filter::new(keys(0..100_000)).unwrap();
+        assert!(xor_filter.bits_per_key(100_000) > 9.8);
+    }
+
+    #[test]
+    fn test_result_bits() {
+        for result_bits in [1, 16, 32] {
+            let filter = RibbonFilter::new(result_bits, keys(0..1000)).unwrap();
+            assert!(keys(0..1000).iter().all(|key| filter.lookup(key)));
+        }
+        let filter = RibbonFilter::new(1, keys(0..1000)).unwrap();
+        let false_positives = keys(1000..11_000)
+            .iter()
+            .filter(|key| filter.lookup(key))
+            .count();
+        assert!(
+            (4500..5500).contains(&false_positives),
+            "{}",
+            false_positives
+        );
+
+        assert!(RibbonFilter::new(0, keys(0..10)).is_err());
+        assert!(RibbonFilter::new(33, keys(0..10)).is_err());
+    }
+
+    #[test]
+    fn test_small_and_duplicates() {
+        for n in 0..10 {
+            let filter = RibbonFilter::new(8, keys(0..n)).unwrap();
+            assert!(filter.num_slots <= 256);
+            assert!(keys(0..n).iter().all(|key| filter.lookup(key)));
+        }
+        let filter = RibbonFilter::new(8, ["a", "a", "b"]).unwrap();
+        assert!(filter.lookup("a") && filter.lookup("b"));
+    }
+
+    #[test]
+    fn test_num_slots() {
+        assert_eq!(num_slots(0, 0), 128);
+        assert_eq!(num_slots(100_000, 0), 103_680);
+        assert_eq!(num_slots(1_000_000, 0), 1_050_240);
+        assert!(num_slots(1_000_000, 2) > num_slots(1_000_000, 1));
+    }
+
+    // Takes a few minutes and about 3 GB, run with --release -- --ignored
+    #[test]
+    #[ignore]
+    fn test_hundred_million_keys() {
+        let len = 100_000_000u64;
+        let filter = RibbonFilter::new(7, (0..len).map(u64::to_le_bytes)).unwrap();
+        assert!((0..len).all(|i| filter.lookup(&i.to_le_bytes())));
+
+        let false_positives = (len..len + 1_000_000)
+            .filter(|i| filter.lookup(&i.to_le_bytes()))
+            .count();
+        assert!(
+            (7000..8700).contains(&false_positives),
+            "{}",
+            false_positives
+        );
+        assert!(filter.bits_per_key(len as usize) < 7.7);
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        let filter = RibbonFilter::new(9, keys(0..100)).unwrap();
+        let bytes = filter.to_bytes();
+        assert_eq!(&bytes[..6], b"BFYS\x04\x07");
+        assert_eq!(&bytes[6..], filter.encode());
+        assert_eq!(RibbonFilter::from_bytes(&bytes).unwrap(), filter);
+        assert!(Xor8Filter::from_bytes(&bytes).is_err());
+    }
+}
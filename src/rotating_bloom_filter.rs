@@ -0,0 +1,341 @@
+use crate::bloom_filter::{BitArray, BloomFilter};
+use crate::error::BloomFilterError;
+use crate::format::{FilterKind, Persistable};
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Seconds since the Unix epoch, the time generations are started at
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    }
+}
+
+// Clock moved by hand, clones share the same time
+#[derive(Debug, Default, Clone)]
+pub struct ManualClock {
+    now: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(now: u64) -> Self {
+        Self {
+            now: Arc::new(AtomicU64::new(now)),
+        }
+    }
+
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.fetch_add(duration.as_secs(), Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Generation {
+    pub(crate) started: u64,
+    pub(crate) bloom_filter: BloomFilter,
+}
+
+// Ring of up to `generation_count` Bloom filters, each covering `window`
+// seconds. Inserts go to the newest generation, lookups check all live
+// ones, so a key is remembered for between N - 1 and N windows. Once the
+// clock passes the newest window a generation is started and the oldest
+// one dropped.
+#[derive(Debug, Clone)]
+pub struct RotatingBloomFilter<C: Clock = SystemClock> {
+    pub(crate) generations: VecDeque<Generation>,
+    pub(crate) generation_count: usize,
+    pub(crate) window: u64,
+    clock: C,
+}
+
+impl RotatingBloomFilter<SystemClock> {
+    pub fn new(template: BloomFilter, generation_count: usize, window: Duration) -> Result<Self> {
+        Self::with_clock(template, generation_count, window, SystemClock)
+    }
+}
+
+impl<C: Clock> RotatingBloomFilter<C> {
+    // Generations are empty copies of template, sharing its size, hashing
+    // and compression. The window is counted in whole seconds.
+    pub fn with_clock(
+        template: BloomFilter,
+        generation_count: usize,
+        window: Duration,
+        clock: C,
+    ) -> Result<Self> {
+        if generation_count == 0 {
+            let reason = "generation count must be positive".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        if window.as_secs() == 0 {
+            let reason = "window must be at least one second".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        let generation = Generation {
+            started: clock.now(),
+            bloom_filter: empty_copy(&template),
+        };
+        Ok(Self {
+            generations: VecDeque::from([generation]),
+            generation_count,
+            window: window.as_secs(),
+            clock,
+        })
+    }
+
+    // Returns false when a live generation already had the key, which is
+    // then added to the newest one anyway to keep it for another N windows
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> bool {
+        self.rotate();
+        let seen = self.lookup(key);
+        self.newest_mut().insert(key);
+        !seen
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        self.live_generations()
+            .any(|generation| generation.bloom_filter.lookup(key))
+    }
+
+    // Starts the generations due by the clock and drops the expired ones,
+    // `insert` calls it first. Windows follow on from the first generation,
+    // whole windows without inserts still count.
+    pub fn rotate(&mut self) {
+        let now = self.clock.now();
+        let newest = self.generations.back().unwrap().started;
+        let elapsed = now.saturating_sub(newest) / self.window;
+        if elapsed == 0 {
+            return;
+        }
+        let started = newest + elapsed * self.window;
+        let bloom_filter = empty_copy(&self.generations.back().unwrap().bloom_filter);
+        self.generations.push_back(Generation {
+            started,
+            bloom_filter,
+        });
+        let expiry = self.expiry();
+        self.generations
+            .retain(|generation| generation.started.saturating_add(expiry) > started);
+    }
+
+    // Start times and filters of the live generations, oldest first
+    pub fn generations(&self) -> impl Iterator<Item = (u64, &BloomFilter)> {
+        self.live_generations()
+            .map(|generation| (generation.started, &generation.bloom_filter))
+    }
+
+    pub fn generation_count(&self) -> usize {
+        self.generation_count
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.window)
+    }
+
+    pub fn set_clock(&mut self, clock: C) {
+        self.clock = clock;
+    }
+
+    // Generations a rotation at the current time would keep, so lookups
+    // agree before and after it
+    fn live_generations(&self) -> impl Iterator<Item = &Generation> {
+        let now = self.clock.now();
+        let expiry = self.expiry();
+        self.generations
+            .iter()
+            .filter(move |generation| generation.started.saturating_add(expiry) > now)
+    }
+
+    fn expiry(&self) -> u64 {
+        (self.generation_count as u64).saturating_mul(self.window)
+    }
+
+    fn newest_mut(&mut self) -> &mut BloomFilter {
+        &mut self.generations.back_mut().unwrap().bloom_filter
+    }
+}
+
+// Loaded filters get a default clock, see `set_clock` to swap it
+impl<C: Clock + Default> Persistable for RotatingBloomFilter<C> {
+    fn kind() -> FilterKind {
+        FilterKind::Rotating
+    }
+}
+
+impl<C: Clock + Default> RotatingBloomFilter<C> {
+    pub(crate) fn from_generations(
+        generations: VecDeque<Generation>,
+        generation_count: usize,
+        window: u64,
+    ) -> Self {
+        Self {
+            generations,
+            generation_count,
+            window,
+            clock: C::default(),
+        }
+    }
+}
+
+// Equal generations, whatever the clocks
+impl<C: Clock> PartialEq for RotatingBloomFilter<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.generations == other.generations
+            && self.generation_count == other.generation_count
+            && self.window == other.window
+    }
+}
+
+fn empty_copy(bloom_filter: &BloomFilter) -> BloomFilter {
+    let mut empty = bloom_filter.clone();
+    empty.bit_array = BitArray::new(bloom_filter.bit_array.size);
+    empty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encodable;
+    use crate::test_util::prepare_tmp_dir;
+    use crate::BloomFilterBuilder;
+    use std::fs;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn filter(clock: &ManualClock) -> RotatingBloomFilter<ManualClock> {
+        let template = BloomFilterBuilder::new(1000).build();
+        RotatingBloomFilter::with_clock(template, 24, HOUR, clock.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_expiry() {
+        let clock = ManualClock::new(1_000_000);
+        let mut filter = filter(&clock);
+        assert!(filter.insert("early"));
+        assert!(!filter.insert("early"));
+        clock.advance(HOUR * 10);
+        assert!(filter.insert("later"));
+
+        // Still within 24 one hour windows of each insert
+        clock.advance(HOUR * 13 + Duration::from_secs(3599));
+        assert!(filter.lookup("early") && filter.lookup("later"));
+        assert_eq!(filter.generations().count(), 2);
+
+        // The first window ends 24 hours after it started
+        clock.advance(Duration::from_secs(1));
+        assert!(!filter.lookup("early"));
+        assert!(filter.lookup("later"));
+        filter.rotate();
+        assert!(!filter.lookup("early"));
+        assert_eq!(filter.generations().count(), 2);
+
+        clock.advance(HOUR * 10);
+        assert!(!filter.lookup("later"));
+        assert_eq!(filter.generations().count(), 1);
+    }
+
+    #[test]
+    fn test_insert_refreshes() {
+        let clock = ManualClock::new(0);
+        let mut filter = filter(&clock);
+        filter.insert("key");
+        for _ in 0..100 {
+            clock.advance(HOUR * 12);
+            assert!(filter.lookup("key"));
+            assert!(!filter.insert("key"));
+        }
+        assert_eq!(filter.generations.len(), 2);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let clock = ManualClock::new(100);
+        let mut filter = filter(&clock);
+        for hour in 0..30 {
+            clock.set(100 + hour * 3600 + 1800);
+            filter.insert(&hour.to_string());
+        }
+        assert_eq!(filter.generations.len(), 24);
+        let starts: Vec<u64> = filter.generations().map(|(started, _)| started).collect();
+        assert_eq!(starts[0], 100 + 6 * 3600);
+        assert_eq!(starts[23], 100 + 29 * 3600);
+        assert!(!filter.lookup("5") && filter.lookup("6"));
+
+        // A long pause leaves only the new generation, on the same grid
+        clock.set(100 + 1000 * 3600 + 5);
+        filter.rotate();
+        assert_eq!(filter.generations.len(), 1);
+        assert_eq!(filter.generations().next().unwrap().0, 100 + 1000 * 3600);
+        assert!(!filter.lookup("29"));
+
+        // A clock going back leaves the generations alone
+        clock.set(0);
+        filter.insert("past");
+        assert_eq!(filter.generations.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let template = BloomFilterBuilder::new(10).build();
+        assert!(RotatingBloomFilter::new(template.clone(), 0, HOUR).is_err());
+        assert!(RotatingBloomFilter::new(template.clone(), 2, Duration::from_millis(10)).is_err());
+        let filter = RotatingBloomFilter::new(template, 2, HOUR).unwrap();
+        assert!(!filter.lookup("key"));
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        let clock = ManualClock::new(0);
+        let mut filter = filter(&clock);
+        filter.insert("key");
+        clock.advance(HOUR);
+        filter.insert("other key");
+
+        let bytes = filter.to_bytes();
+        assert_eq!(&bytes[..6], b"BFYS\x04\x09");
+        assert_eq!(&bytes[6..], filter.encode());
+        let mut loaded = RotatingBloomFilter::<ManualClock>::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded, filter);
+        loaded.set_clock(clock);
+        assert!(loaded.lookup("key") && loaded.lookup("other key"));
+        assert!(BloomFilter::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_persist_local_file() {
+        prepare_tmp_dir();
+        let test_file = "tmp/rotating_bloom_filter_test_persist_local_file.bin";
+        let template = BloomFilterBuilder::new(100).build();
+        let mut filter = RotatingBloomFilter::new(template, 3, HOUR).unwrap();
+        filter.insert("test");
+        filter.to_file(test_file).unwrap();
+
+        let loaded = RotatingBloomFilter::from_file(test_file).unwrap();
+        assert_eq!(loaded, filter);
+        assert!(loaded.lookup("test"));
+
+        fs::remove_file(test_file).unwrap();
+    }
+}
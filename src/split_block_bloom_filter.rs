@@ -0,0 +1,353 @@
+// Parquet split block Bloom filter (SBBF)
+// ref: https://github.com/apache/parquet-format/blob/master/BloomFilter.md
+//
+// The bitset is a run of 256 bit blocks, each made of eight 32 bit words.
+// A key's xxHash64 picks a block from its upper half, and its lower half,
+// multiplied by eight salts, sets one bit in every word of that block.
+// Words are stored little endian.
+
+use crate::error::BloomFilterError;
+use crate::hash::xxhash64;
+use anyhow::Result;
+
+const SALT: [u32; 8] = [
+    0x47b6_137b,
+    0x4497_4d91,
+    0x8824_ad5b,
+    0xa2b7_289d,
+    0x7054_95c7,
+    0x2df1_424b,
+    0x9efc_4947,
+    0x5c6b_fb31,
+];
+pub const BLOCK_SIZE: usize = 32;
+// Bounds used by parquet-mr and arrow-rs
+const MIN_BYTES: usize = BLOCK_SIZE;
+const MAX_BYTES: usize = 128 * 1024 * 1024;
+
+type Block = [u32; 8];
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SplitBlockBloomFilter {
+    pub blocks: Vec<Block>,
+}
+
+impl SplitBlockBloomFilter {
+    // Rounded to a power of two within [32 bytes, 128 MiB] like Parquet writers
+    pub fn new(num_bytes: usize) -> Self {
+        let num_bytes = num_bytes.clamp(MIN_BYTES, MAX_BYTES).next_power_of_two();
+        Self {
+            blocks: vec![[0; 8]; num_bytes / BLOCK_SIZE],
+        }
+    }
+
+    // Sized for `ndv` distinct values at false positive rate `fpp`
+    pub fn with_false_positive_rate(ndv: u64, fpp: f64) -> Result<Self> {
+        if !(fpp > 0.0 && fpp < 1.0) {
+            let reason = "false positive rate must be in (0, 1)".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        let bits = -8.0 * ndv as f64 / (1.0 - fpp.powf(1.0 / 8.0)).ln();
+        Ok(Self::new(bits as usize / 8))
+    }
+
+    // Bitset as stored after the header in a Parquet file
+    pub fn from_bitset(bitset: &[u8]) -> Result<Self> {
+        if bitset.is_empty() || !bitset.len().is_multiple_of(BLOCK_SIZE) {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        let blocks = bitset
+            .chunks_exact(BLOCK_SIZE)
+            .map(|chunk| {
+                let mut block = [0; 8];
+                for (word, bytes) in block.iter_mut().zip(chunk.chunks_exact(4)) {
+                    *word = u32::from_le_bytes(bytes.try_into().unwrap());
+                }
+                block
+            })
+            .collect();
+        Ok(Self { blocks })
+    }
+
+    pub fn bitset(&self) -> Vec<u8> {
+        self.blocks
+            .iter()
+            .flatten()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+
+    pub fn num_bytes(&self) -> usize {
+        self.blocks.len() * BLOCK_SIZE
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        self.lookup_hash(xxhash64(key.as_ref(), 0))
+    }
+
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> bool {
+        self.insert_hash(xxhash64(key.as_ref(), 0))
+    }
+
+    // Parquet hashes the plain encoding of values, callers hashing values
+    // themselves go through these
+    pub fn lookup_hash(&self, hash: u64) -> bool {
+        let block = &self.blocks[self.block_index(hash)];
+        mask(hash as u32)
+            .iter()
+            .zip(block)
+            .all(|(bit, word)| word & bit != 0)
+    }
+
+    pub fn insert_hash(&mut self, hash: u64) -> bool {
+        if self.lookup_hash(hash) {
+            return false;
+        }
+        let index = self.block_index(hash);
+        for (word, bit) in self.blocks[index].iter_mut().zip(mask(hash as u32)) {
+            *word |= bit;
+        }
+        true
+    }
+
+    fn block_index(&self, hash: u64) -> usize {
+        (((hash >> 32) * self.blocks.len() as u64) >> 32) as usize
+    }
+}
+
+// One bit per word
+fn mask(key: u32) -> Block {
+    let mut mask = [0; 8];
+    for (bit, salt) in mask.iter_mut().zip(SALT) {
+        *bit = 1 << (key.wrapping_mul(salt) >> 27);
+    }
+    mask
+}
+
+// Thrift compact encoding of BloomFilterHeader, the only variant Parquet
+// defines: SPLIT_BLOCK algorithm, XXHASH hash, UNCOMPRESSED compression.
+// ref: https://github.com/apache/parquet-format/blob/master/src/main/thrift/parquet.thrift
+const THRIFT_I32: u8 = 5;
+const THRIFT_STRUCT: u8 = 12;
+const HEADER_UNIONS: [&str; 3] = ["algorithm", "hash", "compression"];
+
+pub(crate) fn write_header(num_bytes: usize, output: &mut Vec<u8>) {
+    // Field 1, i32 numBytes, zigzag varint
+    output.push(1 << 4 | THRIFT_I32);
+    let mut value = ((num_bytes as i32) << 1 ^ (num_bytes as i32) >> 31) as u32;
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+    // Fields 2 to 4, unions holding their empty struct member 1
+    for _ in HEADER_UNIONS {
+        output.extend([1 << 4 | THRIFT_STRUCT, 1 << 4 | THRIFT_STRUCT, 0, 0]);
+    }
+    output.push(0);
+}
+
+// Returns the bitset length along with the header length
+pub(crate) fn read_header(bytes: &[u8]) -> Result<(usize, usize)> {
+    let mut reader = ThriftReader { bytes, position: 0 };
+    let mut num_bytes = None;
+    let mut unions = [false; 3];
+    let mut last_id = 0;
+
+    while let Some((id, field_type)) = reader.field_header(last_id)? {
+        last_id = id;
+        match (id, field_type) {
+            (1, THRIFT_I32) => num_bytes = Some(reader.zigzag()?),
+            (2..=4, THRIFT_STRUCT) => {
+                let name = HEADER_UNIONS[id as usize - 2];
+                match reader.field_header(0)? {
+                    Some((1, THRIFT_STRUCT)) => reader.skip(THRIFT_STRUCT)?,
+                    Some((member, _)) => {
+                        let reason = format!("Parquet bloom filter {} {}", name, member);
+                        return Err(BloomFilterError::Unsupported(reason).into());
+                    }
+                    None => return Err(BloomFilterError::InvalidFormat.into()),
+                }
+                if reader.field_header(1)?.is_some() {
+                    return Err(BloomFilterError::InvalidFormat.into());
+                }
+                unions[id as usize - 2] = true;
+            }
+            _ => reader.skip(field_type)?,
+        }
+    }
+
+    match num_bytes {
+        Some(num_bytes) if num_bytes > 0 && unions.iter().all(|&seen| seen) => {
+            Ok((num_bytes as usize, reader.position))
+        }
+        _ => Err(BloomFilterError::InvalidFormat.into()),
+    }
+}
+
+struct ThriftReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl ThriftReader<'_> {
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or(BloomFilterError::InvalidFormat)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(BloomFilterError::InvalidFormat.into())
+    }
+
+    fn zigzag(&mut self) -> Result<i64> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    // (field id, type), None at the end of a struct
+    fn field_header(&mut self, last_id: i16) -> Result<Option<(i16, u8)>> {
+        let byte = self.byte()?;
+        if byte == 0 {
+            return Ok(None);
+        }
+        let id = match byte >> 4 {
+            0 => self.zigzag()? as i16,
+            delta => last_id + delta as i16,
+        };
+        Ok(Some((id, byte & 0x0f)))
+    }
+
+    fn skip(&mut self, field_type: u8) -> Result<()> {
+        match field_type {
+            // Booleans live in the field type
+            1 | 2 => {}
+            3 => self.position += 1,
+            4..=6 => {
+                self.varint()?;
+            }
+            7 => self.position += 8,
+            8 => self.position += self.varint()? as usize,
+            THRIFT_STRUCT => {
+                let mut last_id = 0;
+                while let Some((id, field_type)) = self.field_header(last_id)? {
+                    self.skip(field_type)?;
+                    last_id = id;
+                }
+            }
+            _ => return Err(BloomFilterError::InvalidFormat.into()),
+        }
+        if self.position > self.bytes.len() {
+            return Err(BloomFilterError::InvalidFormat.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // arrow-rs test_with_fixture: bitset written by parquet-mr for "a0".."a9"
+    const FIXTURE: [u8; 32] = [
+        200, 1, 80, 20, 64, 68, 8, 109, 6, 37, 4, 67, 144, 80, 96, 32, 8, 132, 43, 33, 0, 5, 99,
+        65, 2, 0, 224, 44, 64, 78, 96, 4,
+    ];
+
+    #[test]
+    fn test_mask() {
+        for key in (0..u32::MAX).step_by(9973) {
+            assert!(mask(key).iter().all(|word| word.count_ones() == 1));
+        }
+        assert_eq!(mask(0), [1; 8]);
+    }
+
+    #[test]
+    fn test_fixture() {
+        let filter = SplitBlockBloomFilter::from_bitset(&FIXTURE).unwrap();
+        for i in 0..10 {
+            assert!(filter.lookup(&format!("a{}", i)));
+        }
+        assert_eq!(filter.bitset(), FIXTURE);
+
+        let mut rebuilt = SplitBlockBloomFilter::new(32);
+        for i in 0..10 {
+            rebuilt.insert(&format!("a{}", i));
+        }
+        assert_eq!(rebuilt, filter);
+    }
+
+    #[test]
+    fn test_insert_lookup() {
+        let mut filter = SplitBlockBloomFilter::with_false_positive_rate(1000, 0.01).unwrap();
+        assert_eq!(filter.num_bytes(), 2048);
+        for i in 0..1000 {
+            assert!(filter.insert(&i.to_string()));
+        }
+        assert!(!filter.insert("0"));
+        assert!((0..1000).all(|i| filter.lookup(&i.to_string())));
+        let false_positives = (1000..11_000)
+            .filter(|i| filter.lookup(&i.to_string()))
+            .count();
+        assert!(false_positives < 200, "{}", false_positives);
+    }
+
+    #[test]
+    fn test_sizing() {
+        assert_eq!(SplitBlockBloomFilter::new(0).num_bytes(), 32);
+        assert_eq!(SplitBlockBloomFilter::new(33).num_bytes(), 64);
+        assert_eq!(
+            SplitBlockBloomFilter::new(usize::MAX).num_bytes(),
+            MAX_BYTES
+        );
+        assert!(SplitBlockBloomFilter::with_false_positive_rate(10, 0.0).is_err());
+        assert!(SplitBlockBloomFilter::from_bitset(&[0; 31]).is_err());
+        assert!(SplitBlockBloomFilter::from_bitset(&[]).is_err());
+    }
+
+    #[test]
+    fn test_header() {
+        let mut header = Vec::new();
+        write_header(32, &mut header);
+        // arrow-rs test_bloom_filter_header_size_assumption
+        assert_eq!(
+            header,
+            [21, 64, 28, 28, 0, 0, 28, 28, 0, 0, 28, 28, 0, 0, 0]
+        );
+        assert_eq!(
+            read_header(&[&header[..], &[99]].concat()).unwrap(),
+            (32, 15)
+        );
+
+        let mut header = Vec::new();
+        write_header(1 << 20, &mut header);
+        assert_eq!(read_header(&header).unwrap(), (1 << 20, header.len()));
+
+        // Long form field id and an unknown trailing field
+        let header = [
+            5, 2, 64, 28, 28, 0, 0, 28, 28, 0, 0, 28, 28, 0, 0, 0x18, 1, b'x', 0,
+        ];
+        assert_eq!(read_header(&header).unwrap(), (32, header.len()));
+
+        let unknown_hash = [21, 64, 28, 28, 0, 0, 28, 44, 0, 0, 28, 28, 0, 0, 0];
+        assert_eq!(
+            read_header(&unknown_hash).unwrap_err().to_string(),
+            "Unsupported filter: Parquet bloom filter hash 2"
+        );
+        assert!(read_header(&[21, 64, 28, 28, 0, 0, 0]).is_err());
+        assert!(read_header(&[21, 64, 28, 28]).is_err());
+        assert!(read_header(&[]).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+mod builder;
+
+use crate::bloom_filter::murmur3_fnv_hashing;
+use crate::format::{FilterKind, Persistable};
+use crate::rng::SplitMix64;
+pub use builder::StableBloomFilterBuilder;
+
+const CELL_BITS: u32 = 3;
+const SEED: u64 = 0x5851_f42d_4c95_7f2d;
+
+// ref: Deng & Rafiei (2006), "Approximately Detecting Duplicates for
+// Streaming Data using Stable Bloom Filters"
+//
+// Cells are small counters packed `cell_bits` apiece. Each insert first
+// decrements `decrement` (P in the paper) random cells, then sets the key's
+// k cells to the maximum. Old keys fade out, so the fraction of zero cells
+// converges and false positives stay bounded on an endless stream, at the
+// cost of false negatives for keys not seen in a while.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StableBloomFilter {
+    pub(crate) cells: Vec<u8>,
+    pub(crate) cell_count: usize,
+    pub(crate) cell_bits: u32,
+    pub(crate) hash_count: usize,
+    pub(crate) decrement: usize,
+    pub(crate) rng: SplitMix64,
+}
+
+impl StableBloomFilter {
+    // 3 bit cells, about 1% false positives once stable
+    pub fn new(cell_count: usize) -> Self {
+        StableBloomFilterBuilder::new(cell_count)
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Returns false when the key looked like a duplicate. The key is
+    // recorded either way, refreshing its cells.
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> bool {
+        let indexes = self.hashing(key.as_ref());
+        let duplicate = indexes.iter().all(|&i| self.get(i) > 0);
+
+        for _ in 0..self.decrement {
+            let index = (self.rng.next_u64() % self.cell_count as u64) as usize;
+            let value = self.get(index);
+            if value > 0 {
+                self.set(index, value - 1);
+            }
+        }
+        let max = self.max_value();
+        for i in indexes {
+            self.set(i, max);
+        }
+        !duplicate
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        self.hashing(key.as_ref()).iter().all(|&i| self.get(i) > 0)
+    }
+
+    // Fraction of non zero cells
+    pub fn fill_ratio(&self) -> f64 {
+        let non_zero = (0..self.cell_count).filter(|&i| self.get(i) > 0).count();
+        non_zero as f64 / self.cell_count as f64
+    }
+
+    // Limit of the false positive rate as the stream grows, from the
+    // stable fraction of zero cells (Theorem 1 of the paper)
+    pub fn stable_false_positive_rate(&self) -> f64 {
+        stable_false_positive_rate(
+            self.cell_count,
+            self.cell_bits,
+            self.hash_count,
+            self.decrement,
+        )
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cell_count
+    }
+
+    pub fn cell_bits(&self) -> u32 {
+        self.cell_bits
+    }
+
+    pub fn hash_count(&self) -> usize {
+        self.hash_count
+    }
+
+    pub fn decrement(&self) -> usize {
+        self.decrement
+    }
+
+    fn hashing(&self, bytes: &[u8]) -> Vec<usize> {
+        murmur3_fnv_hashing(bytes, self.hash_count, self.cell_count)
+    }
+
+    fn max_value(&self) -> u8 {
+        ((1u16 << self.cell_bits) - 1) as u8
+    }
+
+    // Cells span at most 2 bytes, read little endian
+    fn get(&self, index: usize) -> u8 {
+        let bit = index * self.cell_bits as usize;
+        let window = self.cells[bit / 8..]
+            .iter()
+            .take(2)
+            .enumerate()
+            .fold(0u16, |window, (i, &byte)| window | (byte as u16) << (8 * i));
+        (window >> (bit % 8)) as u8 & self.max_value()
+    }
+
+    fn set(&mut self, index: usize, value: u8) {
+        let bit = index * self.cell_bits as usize;
+        let mask = (self.max_value() as u16) << (bit % 8);
+        let value = (value as u16) << (bit % 8);
+        for (i, byte) in self.cells[bit / 8..].iter_mut().take(2).enumerate() {
+            let byte_mask = (mask >> (8 * i)) as u8;
+            *byte = (*byte & !byte_mask) | ((value >> (8 * i)) as u8 & byte_mask);
+        }
+    }
+}
+
+impl Persistable for StableBloomFilter {
+    fn kind() -> FilterKind {
+        FilterKind::Stable
+    }
+}
+
+// Each insert leaves a cell untouched by the decrements with probability
+// (1 - 1/m)^P and by the key with 1 - k/m, the zero fraction then tends to
+// (1 / (1 + 1 / (P (1/k - 1/m))))^max
+fn stable_false_positive_rate(
+    cell_count: usize,
+    cell_bits: u32,
+    hash_count: usize,
+    decrement: usize,
+) -> f64 {
+    let max = ((1u32 << cell_bits) - 1) as f64;
+    let rate = decrement as f64 * (1.0 / hash_count as f64 - 1.0 / cell_count as f64);
+    let zero_fraction = (1.0 / (1.0 + 1.0 / rate)).powf(max);
+    (1.0 - zero_fraction).powi(hash_count as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encodable;
+    use crate::test_util::prepare_tmp_dir;
+    use crate::BloomFilter;
+    use std::fs;
+
+    fn false_positives(filter: &StableBloomFilter, range: std::ops::Range<usize>) -> usize {
+        range
+            .filter(|i| filter.lookup(&format!("fresh-{}", i)))
+            .count()
+    }
+
+    #[test]
+    fn test_insert_lookup() {
+        let mut filter = StableBloomFilter::new(10_000);
+        assert!(filter.insert("event"));
+        assert!(filter.lookup("event"));
+        assert!(!filter.insert("event"));
+        assert!(!filter.lookup("other event"));
+    }
+
+    #[test]
+    fn test_stable_false_positive_rate() {
+        let mut filter = StableBloomFilter::new(10_000);
+        let stable_rate = filter.stable_false_positive_rate();
+        assert!(
+            stable_rate > 0.005 && stable_rate <= 0.01,
+            "{}",
+            stable_rate
+        );
+
+        // A plain Bloom filter of the same memory answers yes to anything
+        // after such a stream, the stable one stays near its limit
+        for i in 0..200_000 {
+            filter.insert(&format!("event-{}", i));
+        }
+        let fill_ratio = filter.fill_ratio();
+        assert!(fill_ratio > 0.4 && fill_ratio < 0.6, "{}", fill_ratio);
+        let rate = false_positives(&filter, 0..100_000) as f64 / 100_000.0;
+        assert!(
+            (rate - stable_rate).abs() < 0.004,
+            "{} {}",
+            rate,
+            stable_rate
+        );
+
+        // Recent keys are still found, the oldest ones have faded out
+        assert!((199_900..200_000).all(|i| filter.lookup(&format!("event-{}", i))));
+        let old = (0..1000)
+            .filter(|i| filter.lookup(&format!("event-{}", i)))
+            .count();
+        assert!(old < 50, "{}", old);
+
+        let mut bloom_filter = BloomFilter::with_false_positive_rate(3_000, 0.01);
+        for i in 0..200_000 {
+            bloom_filter.insert(&format!("event-{}", i));
+        }
+        assert!(bloom_filter.fill_ratio() > 0.99);
+    }
+
+    #[test]
+    fn test_cells() {
+        for cell_bits in 1..=8 {
+            let mut filter = StableBloomFilterBuilder::new(101)
+                .cell_bits(cell_bits)
+                .build()
+                .unwrap();
+            let max = filter.max_value();
+            for i in 0..101 {
+                filter.set(i, (i as u8) & max);
+            }
+            assert!((0..101).all(|i| filter.get(i) == (i as u8) & max));
+            assert_eq!(filter.cells.len(), (101 * cell_bits as usize).div_ceil(8));
+        }
+    }
+
+    #[test]
+    fn test_decay() {
+        // A single 2 bit cell: set to 3 by the key, then only decremented
+        // by inserts without hashes
+        let mut filter = StableBloomFilterBuilder::new(1)
+            .cell_bits(2)
+            .hash_count(1)
+            .decrement(1)
+            .build()
+            .unwrap();
+        filter.insert("key");
+        assert_eq!(filter.get(0), 3);
+        filter.hash_count = 0;
+        filter.insert("");
+        filter.insert("");
+        assert_eq!(filter.get(0), 1);
+        filter.insert("");
+        filter.insert("");
+        assert_eq!(filter.get(0), 0);
+        filter.hash_count = 1;
+        assert!(!filter.lookup("key"));
+    }
+
+    #[test]
+    fn test_seed() {
+        let build = |seed| {
+            let mut filter = StableBloomFilterBuilder::new(1000)
+                .seed(seed)
+                .build()
+                .unwrap();
+            for i in 0..5000 {
+                filter.insert(&i.to_string());
+            }
+            filter
+        };
+        assert_eq!(build(7), build(7));
+        assert_ne!(build(7).cells, build(8).cells);
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        let mut filter = StableBloomFilter::new(100);
+        filter.insert("key");
+        let bytes = filter.to_bytes();
+        assert_eq!(&bytes[..6], b"BFYS\x04\x08");
+        assert_eq!(&bytes[6..], filter.encode());
+        assert_eq!(StableBloomFilter::from_bytes(&bytes).unwrap(), filter);
+        assert!(BloomFilter::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_persist_local_file() {
+        prepare_tmp_dir();
+        let test_file = "tmp/stable_bloom_filter_test_persist_local_file.bin";
+        let mut filter = StableBloomFilter::new(1000);
+        filter.insert("test");
+        filter.to_file(test_file).unwrap();
+
+        // The generator state is saved, so both copies keep decaying alike
+        let mut loaded = StableBloomFilterBuilder::load(test_file).unwrap();
+        assert!(loaded.lookup("test"));
+        filter.insert("next");
+        loaded.insert("next");
+        assert_eq!(loaded, filter);
+
+        fs::remove_file(test_file).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+use super::{StableBloomFilter, CELL_BITS, SEED};
+use crate::bloom_filter::FALSE_POSITIVE_RATE;
+use crate::error::BloomFilterError;
+use crate::format::Persistable;
+use crate::rng::SplitMix64;
+use anyhow::Result;
+
+pub struct StableBloomFilterBuilder {
+    cell_count: usize,
+    cell_bits: u32,
+    false_positive_rate: f64,
+    hash_count: Option<usize>,
+    decrement: Option<usize>,
+    seed: u64,
+}
+
+impl StableBloomFilterBuilder {
+    pub fn new(cell_count: usize) -> Self {
+        Self {
+            cell_count,
+            cell_bits: CELL_BITS,
+            false_positive_rate: FALSE_POSITIVE_RATE,
+            hash_count: None,
+            decrement: None,
+            seed: SEED,
+        }
+    }
+
+    // 1 to 8, wider cells keep keys longer but need more decrements
+    pub fn cell_bits(mut self, cell_bits: u32) -> Self {
+        self.cell_bits = cell_bits;
+        self
+    }
+
+    // Stable false positive rate the hash count and decrement are solved
+    // for, when not given
+    pub fn false_positive_rate(mut self, false_positive_rate: f64) -> Self {
+        self.false_positive_rate = false_positive_rate;
+        self
+    }
+
+    pub fn hash_count(mut self, hash_count: usize) -> Self {
+        self.hash_count = Some(hash_count);
+        self
+    }
+
+    // Cells decremented per insert, P in the paper
+    pub fn decrement(mut self, decrement: usize) -> Self {
+        self.decrement = Some(decrement);
+        self
+    }
+
+    // Seeds the generator picking the cells to decrement
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn build(self) -> Result<StableBloomFilter> {
+        if self.cell_count == 0 {
+            let reason = "cell count must be positive".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        if !(1..=8).contains(&self.cell_bits) {
+            let reason = "cell bits must be in [1, 8]".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        let p = self.false_positive_rate;
+        if !(p > 0.0 && p < 1.0) {
+            let reason = format!("false positive rate {} must be in (0, 1)", p);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+
+        let hash_count = self
+            .hash_count
+            .unwrap_or_else(|| (1.0 / p).log2().ceil() as usize);
+        if !(1..=self.cell_count).contains(&hash_count) {
+            let reason = format!("hash count must be in [1, {}]", self.cell_count);
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+        let decrement = self
+            .decrement
+            .unwrap_or_else(|| self.optimal_decrement(hash_count));
+        if decrement == 0 {
+            let reason = "decrement must be positive".to_string();
+            return Err(BloomFilterError::InvalidParams(reason).into());
+        }
+
+        let cell_bits = self.cell_count * self.cell_bits as usize;
+        Ok(StableBloomFilter {
+            cells: vec![0; cell_bits.div_ceil(8)],
+            cell_count: self.cell_count,
+            cell_bits: self.cell_bits,
+            hash_count,
+            decrement,
+            rng: SplitMix64::new(self.seed),
+        })
+    }
+
+    pub fn load(path: &str) -> Result<StableBloomFilter> {
+        StableBloomFilter::from_file(path)
+    }
+
+    // Smallest P whose stable false positive rate is within the target,
+    // solving the zero fraction limit for P
+    fn optimal_decrement(&self, hash_count: usize) -> usize {
+        let max = ((1u32 << self.cell_bits) - 1) as f64;
+        let zero_fraction = 1.0 - self.false_positive_rate.powf(1.0 / hash_count as f64);
+        let denominator = (1.0 / zero_fraction.powf(1.0 / max) - 1.0)
+            * (1.0 / hash_count as f64 - 1.0 / self.cell_count as f64);
+        if denominator > 0.0 {
+            ((1.0 / denominator).ceil() as usize).max(1)
+        } else {
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        let filter = StableBloomFilterBuilder::new(10_000).build().unwrap();
+        assert_eq!(filter.cell_bits, 3);
+        assert_eq!(filter.hash_count, 7);
+        assert_eq!(filter.decrement, 64);
+        assert_eq!(filter.cells.len(), 3750);
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        // The smallest decrement within the target
+        for (cell_bits, p) in [(1, 0.01), (3, 0.001), (8, 0.05)] {
+            let builder = || {
+                StableBloomFilterBuilder::new(100_000)
+                    .cell_bits(cell_bits)
+                    .false_positive_rate(p)
+            };
+            let filter = builder().build().unwrap();
+            assert!(filter.stable_false_positive_rate() <= p);
+            let fewer = builder().decrement(filter.decrement - 1).build().unwrap();
+            assert!(fewer.stable_false_positive_rate() > p);
+        }
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        assert!(StableBloomFilterBuilder::new(0).build().is_err());
+        assert!(StableBloomFilterBuilder::new(100)
+            .cell_bits(0)
+            .build()
+            .is_err());
+        assert!(StableBloomFilterBuilder::new(100)
+            .cell_bits(9)
+            .build()
+            .is_err());
+        assert!(StableBloomFilterBuilder::new(100)
+            .false_positive_rate(1.0)
+            .build()
+            .is_err());
+        assert!(StableBloomFilterBuilder::new(100)
+            .hash_count(0)
+            .build()
+            .is_err());
+        assert!(StableBloomFilterBuilder::new(5)
+            .hash_count(6)
+            .build()
+            .is_err());
+        assert!(StableBloomFilterBuilder::new(100)
+            .decrement(0)
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,24 @@
+// Helpers shared by the unit tests
+use std::fs;
+use std::path::Path;
+
+// Files written by the tests go under tmp/
+pub fn prepare_tmp_dir() {
+    let tmp_dir = Path::new("tmp");
+
+    if !tmp_dir.exists() {
+        fs::create_dir(tmp_dir).unwrap();
+    }
+}
+
+// "key-{i}" for each i in range
+pub fn keys(range: std::ops::Range<usize>) -> Vec<String> {
+    range.map(|i| format!("key-{}", i)).collect()
+}
+
+pub fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
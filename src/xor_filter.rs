@@ -0,0 +1,281 @@
+use crate::error::BloomFilterError;
+use crate::format::{FilterKind, Persistable};
+use crate::hash::{fmix64, murmur3_x64_128};
+use crate::rng::SplitMix64;
+use anyhow::Result;
+use std::fmt::Debug;
+use std::ops::BitXor;
+
+const SEED: u64 = 0x726b_2b9d_438b_9d4d;
+// Most seeds peel, running out of them takes a pathological key set
+const MAX_ATTEMPTS: usize = 100;
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+}
+
+// u8 or u16 fingerprints, false positives are about 2^-bits
+pub trait Fingerprint:
+    private::Sealed + Copy + Default + Debug + PartialEq + BitXor<Output = Self>
+{
+    const BITS: u32;
+
+    fn from_hash(hash: u64) -> Self;
+
+    fn read_le(bytes: &[u8]) -> Self;
+
+    fn write_le(&self, output: &mut Vec<u8>);
+}
+
+impl Fingerprint for u8 {
+    const BITS: u32 = 8;
+
+    fn from_hash(hash: u64) -> Self {
+        (hash ^ (hash >> 32)) as u8
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+
+    fn write_le(&self, output: &mut Vec<u8>) {
+        output.push(*self);
+    }
+}
+
+impl Fingerprint for u16 {
+    const BITS: u32 = 16;
+
+    fn from_hash(hash: u64) -> Self {
+        (hash ^ (hash >> 32)) as u16
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        u16::from_le_bytes([bytes[0], bytes[1]])
+    }
+
+    fn write_le(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+// ref: Graf & Lemire (2020), "Xor Filters: Faster and Smaller Than Bloom
+// and Cuckoo Filters"
+// ref: https://github.com/FastFilter/xor_singleheader
+//
+// Each key maps to one slot in each third of the array, chosen so the
+// three fingerprints there XOR to the key's fingerprint. The slots are
+// assigned by peeling keys off slots only one key maps to, retrying with
+// another seed when some are left. Static: keys cannot be added later.
+#[derive(Debug, PartialEq, Clone)]
+pub struct XorFilter<F: Fingerprint> {
+    pub(crate) seed: u64,
+    pub(crate) block_length: usize,
+    pub(crate) fingerprints: Vec<F>,
+}
+
+pub type Xor8Filter = XorFilter<u8>;
+pub type Xor16Filter = XorFilter<u16>;
+
+impl<F: Fingerprint> XorFilter<F> {
+    // Duplicate keys are allowed. Fails with
+    // `BloomFilterError::ConstructionFailed` when no seed peels every key.
+    pub fn new<I, K>(keys: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        let hashes = key_hashes(keys);
+        let capacity = 32 + (1.23 * hashes.len() as f64).ceil() as usize;
+        let block_length = capacity / 3;
+        let mut filter = Self {
+            seed: 0,
+            block_length,
+            fingerprints: vec![F::default(); 3 * block_length],
+        };
+
+        let mut seeds = SplitMix64::new(SEED);
+        for _ in 0..MAX_ATTEMPTS {
+            filter.seed = seeds.next_u64();
+            let order = peel(&hashes, filter.fingerprints.len(), |hash| {
+                filter.positions(fmix64(hash.wrapping_add(filter.seed)))
+            });
+            if let Some(order) = order {
+                filter.assign(&order);
+                return Ok(filter);
+            }
+        }
+        Err(construction_failed(hashes.len()))
+    }
+
+    pub fn lookup<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        let hash = fmix64(key_hash(key.as_ref()).wrapping_add(self.seed));
+        let [h0, h1, h2] = self.positions(hash);
+        F::from_hash(hash) == self.fingerprints[h0] ^ self.fingerprints[h1] ^ self.fingerprints[h2]
+    }
+
+    // Fingerprint bits per key for `len` keys
+    pub fn bits_per_key(&self, len: usize) -> f64 {
+        (self.fingerprints.len() * F::BITS as usize) as f64 / len as f64
+    }
+
+    fn positions(&self, hash: u64) -> [usize; 3] {
+        let block_length = self.block_length as u64;
+        [0, 1, 2].map(|i| {
+            let reduced = (hash.rotate_left(21 * i) as u32 as u64 * block_length) >> 32;
+            (reduced + i as u64 * block_length) as usize
+        })
+    }
+
+    fn assign(&mut self, order: &[(u64, usize)]) {
+        for &(hash, index) in order.iter().rev() {
+            let hash = fmix64(hash.wrapping_add(self.seed));
+            let [h0, h1, h2] = self.positions(hash);
+            self.fingerprints[index] = F::from_hash(hash)
+                ^ self.fingerprints[h0]
+                ^ self.fingerprints[h1]
+                ^ self.fingerprints[h2];
+        }
+    }
+}
+
+impl<F: Fingerprint> Persistable for XorFilter<F> {
+    fn kind() -> FilterKind {
+        match F::BITS {
+            8 => FilterKind::Xor8,
+            _ => FilterKind::Xor16,
+        }
+    }
+}
+
+// Keys are hashed once, the seed is mixed in afterwards. Equal hashes are
+// dropped as they could never be peeled apart.
+pub(crate) fn key_hashes<I, K>(keys: I) -> Vec<u64>
+where
+    I: IntoIterator<Item = K>,
+    K: AsRef<[u8]>,
+{
+    let mut hashes: Vec<u64> = keys.into_iter().map(|key| key_hash(key.as_ref())).collect();
+    hashes.sort_unstable();
+    hashes.dedup();
+    hashes
+}
+
+pub(crate) fn key_hash(bytes: &[u8]) -> u64 {
+    murmur3_x64_128(bytes, 0).0
+}
+
+pub(crate) fn construction_failed(len: usize) -> anyhow::Error {
+    let reason = format!("{} keys left unpeeled after {} seeds", len, MAX_ATTEMPTS);
+    BloomFilterError::ConstructionFailed(reason).into()
+}
+
+// Repeatedly removes keys mapped to a slot no other key maps to, returning
+// them with that slot in removal order, or None when some key remains.
+// Slots track how many keys map there and the XOR of their hashes, which
+// is the remaining key's hash once the count drops to one.
+pub(crate) fn peel<P>(hashes: &[u64], len: usize, positions: P) -> Option<Vec<(u64, usize)>>
+where
+    P: Fn(u64) -> [usize; 3],
+{
+    let mut counts = vec![0u32; len];
+    let mut xor_hashes = vec![0u64; len];
+    for &hash in hashes {
+        for index in positions(hash) {
+            counts[index] += 1;
+            xor_hashes[index] ^= hash;
+        }
+    }
+
+    let mut queue: Vec<usize> = (0..len).filter(|&i| counts[i] == 1).collect();
+    let mut order = Vec::with_capacity(hashes.len());
+    while let Some(index) = queue.pop() {
+        if counts[index] != 1 {
+            continue;
+        }
+        let hash = xor_hashes[index];
+        order.push((hash, index));
+        for other in positions(hash) {
+            counts[other] -= 1;
+            xor_hashes[other] ^= hash;
+            if counts[other] == 1 {
+                queue.push(other);
+            }
+        }
+    }
+
+    if order.len() == hashes.len() {
+        Some(order)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::Encodable;
+    use crate::test_util::keys;
+    use crate::BloomFilter;
+
+    #[test]
+    fn test_xor8() {
+        let filter = Xor8Filter::new(keys(0..10_000)).unwrap();
+        assert_eq!(filter.fingerprints.len(), 12_330);
+        assert!(keys(0..10_000).iter().all(|key| filter.lookup(key)));
+
+        // About 1 / 256
+        let false_positives = keys(10_000..110_000)
+            .iter()
+            .filter(|key| filter.lookup(key))
+            .count();
+        assert!((250..550).contains(&false_positives), "{}", false_positives);
+        assert!(filter.bits_per_key(10_000) < 9.9);
+    }
+
+    #[test]
+    fn test_xor16() {
+        let filter = Xor16Filter::new(keys(0..10_000)).unwrap();
+        assert!(keys(0..10_000).iter().all(|key| filter.lookup(key)));
+
+        // About 1 / 65536
+        let false_positives = keys(10_000..110_000)
+            .iter()
+            .filter(|key| filter.lookup(key))
+            .count();
+        assert!(false_positives < 10, "{}", false_positives);
+    }
+
+    #[test]
+    fn test_small_and_duplicates() {
+        let filter = Xor8Filter::new(Vec::<&str>::new()).unwrap();
+        assert_eq!(filter.fingerprints.len(), 30);
+        let filter = Xor8Filter::new(["a", "b", "a", "a"]).unwrap();
+        assert!(filter.lookup("a"));
+        assert!(filter.lookup("b"));
+    }
+
+    #[test]
+    fn test_peel() {
+        // Two keys on the same three slots never peel
+        assert!(peel(&[1, 2], 3, |_| [0, 1, 2]).is_none());
+        let order = peel(&[1, 2], 4, |hash| [0, hash as usize, 3]).unwrap();
+        assert_eq!(order.len(), 2);
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        let filter = Xor16Filter::new(keys(0..100)).unwrap();
+        let bytes = filter.to_bytes();
+        assert_eq!(&bytes[..6], b"BFYS\x04\x04");
+        assert_eq!(&bytes[6..], filter.encode());
+        assert_eq!(Xor16Filter::from_bytes(&bytes).unwrap(), filter);
+        assert_eq!(
+            Xor8Filter::from_bytes(&bytes).unwrap_err().to_string(),
+            "Unsupported filter: Xor16 filter file, expected Xor8"
+        );
+        assert!(BloomFilter::from_bytes(&bytes).is_err());
+    }
+}
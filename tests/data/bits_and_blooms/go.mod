@@ -0,0 +1,5 @@
+module golden
+
+go 1.20
+
+require github.com/bits-and-blooms/bloom/v3 v3.7.0
@@ -0,0 +1,56 @@
+// Writes the bits-and-blooms golden files used by src/bits_and_blooms.rs.
+//
+// For each filter, <name>.bin is WriteTo's output after adding key-0 ..
+// key-{n - 1}, "" and "été ☃", and <name>.txt lists the probes in key-n ..
+// key-{n + 9999} Test reports.
+//
+// The Go module beside it pins bits-and-blooms/bloom v3.7.0:
+//
+//   go mod tidy && go run .
+//
+// golden.py transcribes the same steps in Python. The checked in files come
+// from it, as no Go toolchain was at hand when they were written; running
+// this must reproduce them byte for byte, which `test_write_golden` and
+// `test_read_golden` then hold the crate to.
+package main
+
+import (
+	"fmt"
+	"os"
+
+	"github.com/bits-and-blooms/bloom/v3"
+)
+
+func write(name string, f *bloom.BloomFilter, n int) {
+	for i := 0; i < n; i++ {
+		f.AddString(fmt.Sprintf("key-%d", i))
+	}
+	f.AddString("")
+	f.AddString("été ☃")
+
+	bin, err := os.Create(name + ".bin")
+	if err != nil {
+		panic(err)
+	}
+	defer bin.Close()
+	if _, err := f.WriteTo(bin); err != nil {
+		panic(err)
+	}
+
+	txt, err := os.Create(name + ".txt")
+	if err != nil {
+		panic(err)
+	}
+	defer txt.Close()
+	for i := n; i < n+10000; i++ {
+		if f.TestString(fmt.Sprintf("key-%d", i)) {
+			fmt.Fprintln(txt, i)
+		}
+	}
+}
+
+func main() {
+	write("bloom_1000_0.01", bloom.NewWithEstimates(1000, 0.01), 1000)
+	write("bloom_100_0.001", bloom.NewWithEstimates(100, 0.001), 100)
+	write("bloom_200_3", bloom.New(200, 3), 20)
+}
@@ -0,0 +1,92 @@
+# Writes the bits-and-blooms golden files used by src/bits_and_blooms.rs,
+# transcribing bits-and-blooms/bloom v3 (EstimateParameters, baseHashes,
+# location, WriteTo and bitset.WriteTo) from its sources. golden.go writes the
+# same files with the Go package itself.
+#
+# For each filter, <name>.bin is WriteTo's output after adding key-0 ..
+# key-{n - 1}, "" and "été ☃", and <name>.txt lists the probes in key-n ..
+# key-{n + 9999} Test reports.
+#
+#   python3 golden.py
+import math
+import struct
+
+MASK = (1 << 64) - 1
+C1 = 0x87C37B91114253D5
+C2 = 0x4CF5AD432745937F
+
+
+def rotl(x, r):
+    return ((x << r) | (x >> (64 - r))) & MASK
+
+
+def fmix(k):
+    k ^= k >> 33
+    k = (k * 0xFF51AFD7ED558CCD) & MASK
+    k ^= k >> 33
+    k = (k * 0xC4CEB9FE1A85EC53) & MASK
+    return k ^ (k >> 33)
+
+
+# spaolacci/murmur3 New128 with seed 0, Sum128
+def murmur3_128(data):
+    h1 = h2 = 0
+    full = len(data) - len(data) % 16
+    for i in range(0, full, 16):
+        k1, k2 = struct.unpack("<QQ", data[i : i + 16])
+        h1 ^= rotl(k1 * C1 & MASK, 31) * C2 & MASK
+        h1 = (rotl(h1, 27) + h2) * 5 + 0x52DCE729 & MASK
+        h2 ^= rotl(k2 * C2 & MASK, 33) * C1 & MASK
+        h2 = (rotl(h2, 31) + h1) * 5 + 0x38495AB5 & MASK
+    tail = data[full:] + bytes(16 - len(data) % 16)
+    k1, k2 = struct.unpack("<QQ", tail[:16])
+    if len(data) % 16:
+        h1 ^= rotl(k1 * C1 & MASK, 31) * C2 & MASK
+        h2 ^= rotl(k2 * C2 & MASK, 33) * C1 & MASK
+    h1 ^= len(data)
+    h2 ^= len(data)
+    h1 = (h1 + h2) & MASK
+    h2 = (h2 + h1) & MASK
+    h1, h2 = fmix(h1), fmix(h2)
+    h1 = (h1 + h2) & MASK
+    h2 = (h2 + h1) & MASK
+    return h1, h2
+
+
+def base_hashes(data):
+    return murmur3_128(data) + murmur3_128(data + b"\x01")
+
+
+def locations(data, m, k):
+    h = base_hashes(data)
+    for i in range(k):
+        yield (h[i % 2] + i * h[2 + ((i + i % 2) % 4) // 2] & MASK) % m
+
+
+def estimate_parameters(n, p):
+    m = math.ceil(-1 * n * math.log(p) / math.pow(math.log(2), 2))
+    k = math.ceil(math.log(2) * m / n)
+    return m, k
+
+
+def write(name, m, k, n):
+    m, k = max(1, m), max(1, k)
+    bits = set()
+    keys = ["key-%d" % i for i in range(n)] + ["", "été ☃"]
+    for key in keys:
+        bits.update(locations(key.encode(), m, k))
+    words = [0] * ((m + 63) // 64)
+    for bit in bits:
+        words[bit // 64] |= 1 << (bit % 64)
+    with open(name + ".bin", "wb") as f:
+        f.write(struct.pack(">QQQ", m, k, m))
+        f.write(b"".join(struct.pack(">Q", w) for w in words))
+    with open(name + ".txt", "w") as f:
+        for i in range(n, n + 10000):
+            if all(b in bits for b in locations(("key-%d" % i).encode(), m, k)):
+                f.write("%d\n" % i)
+
+
+write("bloom_1000_0.01", *estimate_parameters(1000, 0.01), 1000)
+write("bloom_100_0.001", *estimate_parameters(100, 0.001), 100)
+write("bloom_200_3", 200, 3, 20)
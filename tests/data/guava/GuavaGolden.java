@@ -0,0 +1,37 @@
+// Writes the Guava golden files used by src/guava.rs with Guava itself,
+// BloomFilter.create(Funnels.byteArrayFunnel(), n, fpp) then writeTo. The
+// checked in files come from Guava 33.4.0-jre.
+//
+//   javac -cp guava.jar GuavaGolden.java && java -cp guava.jar:. GuavaGolden
+import com.google.common.hash.BloomFilter;
+import com.google.common.hash.Funnels;
+import com.google.common.hash.Hashing;
+import java.io.*;
+import java.nio.charset.StandardCharsets;
+
+public class GuavaGolden {
+    static byte[] utf8(String s) { return s.getBytes(StandardCharsets.UTF_8); }
+
+    // Inserts key-0..key-{inserted - 1}, then lists which of key-0..key-{probes - 1}
+    // mightContain reports
+    static void generate(String name, long n, double fpp, int inserted, int probes) throws IOException {
+        BloomFilter<byte[]> filter = BloomFilter.create(Funnels.byteArrayFunnel(), n, fpp);
+        for (int i = 0; i < inserted; i++) filter.put(utf8("key-" + i));
+        filter.put(utf8(""));
+        filter.put(utf8("\u00e9t\u00e9 \u2603"));
+        try (OutputStream out = new FileOutputStream(name + ".bin")) {
+            filter.writeTo(out);
+        }
+        try (PrintWriter out = new PrintWriter(name + ".txt", "UTF-8")) {
+            for (int i = 0; i < probes; i++) if (filter.mightContain(utf8("key-" + i))) out.println(i);
+        }
+    }
+
+    public static void main(String[] args) throws IOException {
+        for (String key : new String[] {"", "hell", "hello", "The quick brown fox jumps over the lazy dog", "0123456789abcdefXYZ"}) {
+            System.out.printf("%s => %s%n", key, Hashing.murmur3_128().hashBytes(utf8(key)));
+        }
+        generate("guava_1000_0.01", 1000, 0.01, 1000, 20000);
+        generate("guava_100_0.001", 100, 0.001, 50, 5000);
+    }
+}
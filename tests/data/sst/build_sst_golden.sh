@@ -0,0 +1,47 @@
+#!/bin/sh
+# Builds sst_golden.cc against LevelDB and RocksDB sources and writes the
+# golden files to the current directory. The checked in files were made
+# with the sources bundled in the leveldb-sys 2.0.8 (LevelDB 1.22) and
+# librocksdb-sys 0.17.3+10.4.2 (RocksDB 10.4.2) crates:
+#
+#   build_sst_golden.sh <leveldb-sys dir> <librocksdb-sys dir>
+set -e
+
+here=$(cd "$(dirname "$0")" && pwd)
+leveldb=$1/deps/leveldb-1.22
+rocksdb_sys=$2
+rocksdb=$rocksdb_sys/rocksdb
+build=${BUILD_DIR:-build}
+mkdir -p "$build/leveldb/port" "$build/rocksdb"
+
+# LevelDB: only the Bloom filter policy and its hash
+cat > "$build/leveldb/port/port_config.h" <<EOF
+#define HAVE_FDATASYNC 1
+#define HAVE_FULLFSYNC 0
+#define HAVE_O_CLOEXEC 1
+#define HAVE_CRC32C 0
+#define HAVE_SNAPPY 0
+EOF
+leveldb_flags="-std=c++11 -O2 -DLEVELDB_PLATFORM_POSIX -DLEVELDB_IS_BIG_ENDIAN=0
+  -I$build/leveldb -I$leveldb -I$leveldb/include"
+for source in bloom filter_policy hash; do
+  g++ $leveldb_flags -c "$leveldb/util/$source.cc" -o "$build/leveldb/$source.o"
+done
+
+# RocksDB: the whole library, without compression. The crate ships a
+# generated build_version.cc in place of util/build_version.cc.
+rocksdb_flags="-std=c++20 -O1 -DNDEBUG -DOS_LINUX -DROCKSDB_PLATFORM_POSIX
+  -DROCKSDB_LIB_IO_POSIX -DROCKSDB_SUPPORT_THREAD_LOCAL -w -I$rocksdb
+  -I$rocksdb/include"
+sources=$(grep -v '^util/build_version.cc$' "$rocksdb_sys/rocksdb_lib_sources.txt")
+for source in $sources ../build_version.cc; do
+  object=$build/rocksdb/$(echo "$source" | sed 's|^\.\./||; s|/|_|g; s|\.cc$|.o|')
+  [ -f "$object" ] || g++ $rocksdb_flags -c "$rocksdb/$source" -o "$object"
+done
+rm -f "$build/librocksdb.a"
+ar rcs "$build/librocksdb.a" "$build"/rocksdb/*.o
+
+g++ $rocksdb_flags -I$leveldb/include -c "$here/sst_golden.cc" -o "$build/sst_golden.o"
+g++ -o "$build/sst_golden" "$build/sst_golden.o" "$build"/leveldb/*.o \
+  "$build/librocksdb.a" -lpthread -ldl
+"$build/sst_golden"
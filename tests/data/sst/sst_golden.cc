@@ -0,0 +1,92 @@
+// Writes the SST filter golden files used by src/leveldb_bloom_filter.rs and
+// src/fast_local_bloom_filter.rs with the libraries themselves: LevelDB's
+// NewBloomFilterPolicy, and RocksDB's NewBloomFilterPolicy through the
+// builder and reader it hands out for format_version 5 and later tables,
+// which is FastLocalBloom. The checked in files come from LevelDB 1.22 and
+// RocksDB 10.4.2, see build_sst_golden.sh.
+#include <cstdio>
+#include <memory>
+#include <string>
+#include <vector>
+
+#include "leveldb/filter_policy.h"
+#include "leveldb/slice.h"
+#include "rocksdb/filter_policy.h"
+#include "rocksdb/slice.h"
+#include "rocksdb/table.h"
+#include "table/block_based/filter_policy_internal.h"
+
+static std::vector<std::string> Keys(size_t from, size_t to) {
+  std::vector<std::string> keys;
+  for (size_t i = from; i < to; i++) keys.push_back("key-" + std::to_string(i));
+  return keys;
+}
+
+static std::string LevelDbFilter(int bits_per_key, const std::vector<std::string>& keys) {
+  std::unique_ptr<const leveldb::FilterPolicy> policy(leveldb::NewBloomFilterPolicy(bits_per_key));
+  std::vector<leveldb::Slice> slices(keys.begin(), keys.end());
+  std::string block;
+  policy->CreateFilter(slices.data(), static_cast<int>(slices.size()), &block);
+  return block;
+}
+
+static bool LevelDbMayMatch(int bits_per_key, const std::string& key, const std::string& block) {
+  std::unique_ptr<const leveldb::FilterPolicy> policy(leveldb::NewBloomFilterPolicy(bits_per_key));
+  return policy->KeyMayMatch(key, block);
+}
+
+static std::string FastLocalFilter(double bits_per_key, const std::vector<std::string>& keys) {
+  std::unique_ptr<const rocksdb::FilterPolicy> policy(rocksdb::NewBloomFilterPolicy(bits_per_key));
+  rocksdb::BlockBasedTableOptions options;
+  options.format_version = 5;
+  // Otherwise the filter is sized by the allocator's malloc_usable_size and
+  // probes follow that size, rather than bits_per_key
+  options.optimize_filters_for_memory = false;
+  rocksdb::FilterBuildingContext context(options);
+  std::unique_ptr<rocksdb::FilterBitsBuilder> builder(policy->GetBuilderWithContext(context));
+  for (const std::string& key : keys) builder->AddKey(key);
+  std::unique_ptr<const char[]> buf;
+  rocksdb::Slice block = builder->Finish(&buf);
+  return block.ToString();
+}
+
+static bool FastLocalMayMatch(double bits_per_key, const std::string& key, const std::string& block) {
+  std::unique_ptr<const rocksdb::FilterPolicy> policy(rocksdb::NewBloomFilterPolicy(bits_per_key));
+  std::unique_ptr<rocksdb::FilterBitsReader> reader(policy->GetFilterBitsReader(block));
+  return reader->MayMatch(key);
+}
+
+// Output: <name>.bin holds the block for key-0..key-{n - 1}, <name>.txt the
+// probes in key-n..key-{n + 9999} the filter matches
+
+template <typename MayMatch>
+static void Write(const std::string& name, const std::string& block, size_t n, MayMatch may_match) {
+  FILE* bin = fopen((name + ".bin").c_str(), "wb");
+  fwrite(block.data(), 1, block.size(), bin);
+  fclose(bin);
+  FILE* txt = fopen((name + ".txt").c_str(), "w");
+  for (const std::string& key : Keys(0, n)) {
+    if (!may_match(key, block)) fprintf(stderr, "%s: missing %s\n", name.c_str(), key.c_str());
+  }
+  for (size_t i = n; i < n + 10000; i++) {
+    if (may_match("key-" + std::to_string(i), block)) fprintf(txt, "%zu\n", i);
+  }
+  fclose(txt);
+}
+
+int main() {
+  auto leveldb = [](const std::string& key, const std::string& block) {
+    return LevelDbMayMatch(10, key, block);
+  };
+  Write("leveldb_10_100", LevelDbFilter(10, Keys(0, 100)), 100, leveldb);
+  Write("leveldb_10_3", LevelDbFilter(10, Keys(0, 3)), 3, leveldb);
+  Write("fast_local_10_100", FastLocalFilter(10.0, Keys(0, 100)), 100,
+        [](const std::string& key, const std::string& block) {
+          return FastLocalMayMatch(10.0, key, block);
+        });
+  Write("fast_local_7.5_1000", FastLocalFilter(7.5, Keys(0, 1000)), 1000,
+        [](const std::string& key, const std::string& block) {
+          return FastLocalMayMatch(7.5, key, block);
+        });
+  return 0;
+}